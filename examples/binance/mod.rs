@@ -5,29 +5,26 @@ use {
         logger::{debug, error, info},
         s,
     },
-    rustler_core::{
-        rustler,
-        rustlers::{svc::quote, MarketHourType, Rustler, RustlerAccessor, RustlerStatus, Ticker},
+    rustler_core::rustlers::{
+        rustler, svc::quote, MarketHourType, Rustler, RustlerAccessor, RustlerStatus, Ticker,
     },
-    std::collections::HashMap,
     tokio::select,
 };
 
-rustler!(
-    /// A fake rustler that does nothing but changing between different statuses.
-    pub struct FooRustler {}
-);
+/// A fake rustler that does nothing but changing between different statuses.
+#[rustler]
+pub struct FooRustler {}
 
 #[allow(dead_code)]
 impl FooRustler {
     pub fn create() -> impl Rustler {
-        Self::default()
+        Self::new()
     }
 
     pub fn create_with_external_stuff(name: String) -> impl Fn() -> FooRustler {
         move || {
             println!("Creating a new FooRustler using external name = {}", name);
-            Self::default()
+            Self::new()
         }
     }
 
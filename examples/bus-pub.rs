@@ -38,6 +38,9 @@ async fn main() -> Result<()> {
                 market_hours: MarketHourType::Regular,
                 price,
                 time: 198798798798,
+                source: None,
+                volume: None,
+                stats: None,
             };
 
             println!("Publishing quote, {}", quote);
@@ -71,6 +74,9 @@ async fn main() -> Result<()> {
                 market_hours: MarketHourType::Regular,
                 price,
                 time: 198798798798,
+                source: None,
+                volume: None,
+                stats: None,
             };
 
             println!("Publishing quote, {}", quote);
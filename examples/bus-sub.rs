@@ -20,6 +20,7 @@ async fn main() -> Result<()> {
         market: "BINANCE".to_string(),
         symbol: "BTCUSDT".to_string(),
         quote_asset: None,
+        provider_symbol: None,
     };
 
     let mut stream =
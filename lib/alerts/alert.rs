@@ -0,0 +1,147 @@
+use {
+    super::AlertCondition,
+    crate::bus::{
+        codec::proto, redis::stream::StreamMsg, BusFields, BusMessage, FromBusVal, MessageFields,
+        ToBusKey, ToBusVal, ToFromBusMessage, ToFromProto,
+    },
+    eyre::Result,
+    lool::s,
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// #### 🐎 » Alert
+///
+/// Published to the bus when an alert rule fires.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Alert {
+    /// id of the rule that fired
+    pub rule_id: String,
+    pub market: String,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    /// price of the quote that triggered the alert
+    pub price: f64,
+    /// change percent of the quote that triggered the alert
+    pub change_percent: f64,
+    /// time of the quote that triggered the alert
+    pub time: i64,
+}
+
+impl ToBusVal for Alert {
+    fn to_bus_val(&self) -> Vec<(String, String)> {
+        vec![
+            (s!("rule_id"), self.rule_id.to_owned()),
+            (s!("market"), self.market.to_owned()),
+            (s!("symbol"), self.symbol.to_owned()),
+            (s!("condition"), self.condition.to_string()),
+            (s!("threshold"), self.threshold.to_string()),
+            (s!("price"), self.price.to_string()),
+            (s!("change_percent"), self.change_percent.to_string()),
+            (s!("time"), self.time.to_string()),
+        ]
+    }
+}
+
+impl FromBusVal for Alert {
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self> {
+        let fields = BusFields::new(fields);
+
+        Ok(Self {
+            rule_id: fields.get("rule_id")?.to_string(),
+            market: fields.get("market")?.to_string(),
+            symbol: fields.get("symbol")?.to_string(),
+            condition: fields.parse("condition")?,
+            threshold: fields.parse::<f64>("threshold")?,
+            price: fields.parse::<f64>("price")?,
+            change_percent: fields.parse::<f64>("change_percent")?,
+            time: fields.parse::<i64>("time")?,
+        })
+    }
+}
+
+impl ToBusKey for Alert {
+    fn to_bus_key(&self) -> String {
+        format!("alert:{}:{}:{}", self.market, self.symbol, self.rule_id)
+    }
+}
+
+impl ToFromBusMessage for Alert {
+    /// 🐎 » converts an `Alert` to a serialized message that can be sent over a redis channel
+    ///
+    /// the message is in the format
+    /// `rule_id¦market¦symbol¦condition¦threshold¦price¦change_percent¦time`
+    fn as_message(&self) -> String {
+        format!(
+            "{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}",
+            self.rule_id,
+            self.market,
+            self.symbol,
+            self.condition,
+            self.threshold,
+            self.price,
+            self.change_percent,
+            self.time,
+        )
+    }
+
+    /// 🐎 » creates an `Alert` from a message
+    ///
+    /// fails if the message is not in the correct format
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self> {
+        let fields = MessageFields::new(msg.as_ref());
+
+        Ok(Self {
+            rule_id: fields.get(0, "rule_id")?.to_string(),
+            market: fields.get(1, "market")?.to_string(),
+            symbol: fields.get(2, "symbol")?.to_string(),
+            condition: fields.parse(3, "condition")?,
+            threshold: fields.parse::<f64>(4, "threshold")?,
+            price: fields.parse::<f64>(5, "price")?,
+            change_percent: fields.parse::<f64>(6, "change_percent")?,
+            time: fields.parse::<i64>(7, "time")?,
+        })
+    }
+}
+
+impl ToFromProto for Alert {
+    type Proto = proto::Alert;
+
+    /// 🐎 » converts an `Alert` to its protobuf message
+    fn to_proto(&self) -> proto::Alert {
+        proto::Alert {
+            rule_id: self.rule_id.clone(),
+            market: self.market.clone(),
+            symbol: self.symbol.clone(),
+            condition: self.condition.to_string(),
+            threshold: self.threshold,
+            price: self.price,
+            change_percent: self.change_percent,
+            time: self.time,
+        }
+    }
+
+    /// 🐎 » creates an `Alert` from its protobuf message
+    ///
+    /// fails if the condition is unknown
+    fn from_proto(alert: proto::Alert) -> Result<Self> {
+        Ok(Self {
+            condition: alert.condition.parse()?,
+            rule_id: alert.rule_id,
+            market: alert.market,
+            symbol: alert.symbol,
+            threshold: alert.threshold,
+            price: alert.price,
+            change_percent: alert.change_percent,
+            time: alert.time,
+        })
+    }
+}
+
+impl StreamMsg for Alert {}
+impl BusMessage for Alert {
+    fn message_time(&self) -> Option<i64> {
+        Some(self.time)
+    }
+}
@@ -0,0 +1,236 @@
+use {
+    super::{Alert, AlertRule},
+    crate::rustlers::Quote,
+    std::collections::HashMap,
+};
+
+/// runtime state of a rule
+#[derive(Debug, Clone)]
+struct RuleState {
+    rule: AlertRule,
+    /// whether the condition held on the last evaluated quote (`None` before the first quote)
+    held: Option<bool>,
+}
+
+/// #### 🐎 » Alert evaluator
+///
+/// Evaluates the alert rules against a stream of quotes. It doesn't do any i/o, so it can be fed
+/// with live or replayed quotes (see [`super::AlertSvc`] for the bus based service).
+#[derive(Debug, Default)]
+pub struct AlertEvaluator {
+    /// rules by ticker key (`market:symbol`)
+    rules: HashMap<String, Vec<RuleState>>,
+}
+
+impl AlertEvaluator {
+    /// 🐎 » creates an evaluator for the given rules
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let mut evaluator = Self::default();
+        evaluator.set_rules(rules);
+        evaluator
+    }
+
+    /// 🐎 » replaces the evaluated rules
+    ///
+    /// the state of the rules that didn't change is kept, so reloading the rules doesn't fire
+    /// alerts again
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) {
+        let mut previous: HashMap<String, RuleState> = self
+            .rules
+            .drain()
+            .flat_map(|(_, states)| states)
+            .map(|state| (state.rule.id.clone(), state))
+            .collect();
+
+        for mut rule in rules {
+            let held = match previous.remove(&rule.id) {
+                Some(state) if same_condition(&state.rule, &rule) => {
+                    // the stored trigger time might not be up to date yet
+                    rule.last_triggered_at =
+                        rule.last_triggered_at.max(state.rule.last_triggered_at);
+                    state.held
+                }
+                _ => None,
+            };
+
+            self.rules.entry(rule.ticker.clone()).or_default().push(RuleState { rule, held });
+        }
+    }
+
+    /// 🐎 » returns the number of rules being evaluated
+    pub fn len(&self) -> usize {
+        self.rules.values().map(Vec::len).sum()
+    }
+
+    /// 🐎 » returns `true` if there are no rules being evaluated
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 🐎 » evaluates the rules of the quote's ticker and returns the alerts that fired
+    ///
+    /// one-shot rules are removed from the evaluator after they fire
+    pub fn on_quote(&mut self, quote: &Quote) -> Vec<Alert> {
+        let key = format!("{}:{}", quote.market, quote.id);
+        let Some(states) = self.rules.get_mut(&key) else {
+            return vec![];
+        };
+
+        let mut alerts = vec![];
+        for state in states.iter_mut() {
+            let rule = &mut state.rule;
+            let holds = rule.condition.holds(rule.threshold, quote.price, quote.change_percent);
+
+            let fires = match state.held {
+                Some(held) => holds && !held,
+                None => holds && !rule.condition.is_crossing(),
+            };
+            state.held = Some(holds);
+
+            let cooling_down = rule
+                .last_triggered_at
+                .is_some_and(|last| quote.time.saturating_sub(last) < rule.cooldown);
+
+            if fires && !cooling_down {
+                rule.last_triggered_at = Some(quote.time);
+                alerts.push(Alert {
+                    rule_id: rule.id.clone(),
+                    market: quote.market.clone(),
+                    symbol: quote.id.clone(),
+                    condition: rule.condition,
+                    threshold: rule.threshold,
+                    price: quote.price,
+                    change_percent: quote.change_percent,
+                    time: quote.time,
+                });
+            }
+        }
+
+        states.retain(|s| !(s.rule.one_shot && alerts.iter().any(|a| a.rule_id == s.rule.id)));
+        alerts
+    }
+}
+
+/// whether two versions of a rule evaluate the same condition
+fn same_condition(a: &AlertRule, b: &AlertRule) -> bool {
+    a.ticker == b.ticker && a.condition == b.condition && a.threshold == b.threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{alerts::AlertCondition, rustlers::MarketHourType},
+    };
+
+    fn rule(id: &str, condition: AlertCondition, threshold: f64) -> AlertRule {
+        AlertRule {
+            id: id.into(),
+            ticker: "NASDAQ:AAPL".into(),
+            condition,
+            threshold,
+            cooldown: 0,
+            one_shot: false,
+            last_triggered_at: None,
+        }
+    }
+
+    fn quote(time: i64, price: f64, change_percent: f64) -> Quote {
+        Quote {
+            id: "AAPL".into(),
+            market: "NASDAQ".into(),
+            price,
+            change_percent,
+            time,
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume: None,
+            stats: None,
+        }
+    }
+
+    /// replays prices through the evaluator, returning the rule and time of each alert
+    fn replay(evaluator: &mut AlertEvaluator, prices: &[f64]) -> Vec<(String, i64)> {
+        prices
+            .iter()
+            .enumerate()
+            .flat_map(|(time, price)| evaluator.on_quote(&quote(time as i64, *price, 0.0)))
+            .map(|alert| (alert.rule_id, alert.time))
+            .collect()
+    }
+
+    #[test]
+    fn crossings_fire_on_the_edge_only() {
+        let mut evaluator = AlertEvaluator::new(vec![
+            rule("above", AlertCondition::CrossesAbove, 100.0),
+            rule("below", AlertCondition::CrossesBelow, 100.0),
+        ]);
+
+        // the first quote is already above, but there's no crossing without a previous quote
+        let alerts = replay(&mut evaluator, &[101.0, 99.0, 98.0, 100.0, 102.0, 97.0]);
+
+        assert_eq!(
+            alerts,
+            [("below".into(), 1), ("above".into(), 3), ("below".into(), 5)]
+        );
+    }
+
+    #[test]
+    fn rules_dont_fire_again_while_the_condition_holds() {
+        let mut evaluator =
+            AlertEvaluator::new(vec![rule("move", AlertCondition::MovesMoreThan, 3.0)]);
+
+        let changes = [4.0, 5.0, -6.0, 1.0, 3.0, 3.5];
+        let alerts: Vec<i64> = changes
+            .iter()
+            .enumerate()
+            .flat_map(|(time, change)| evaluator.on_quote(&quote(time as i64, 100.0, *change)))
+            .map(|alert| alert.time)
+            .collect();
+
+        // level conditions fire on the first quote if they already hold
+        assert_eq!(alerts, [0, 4]);
+    }
+
+    #[test]
+    fn one_shot_rules_are_removed_after_firing() {
+        let mut once = rule("once", AlertCondition::CrossesAbove, 100.0);
+        once.one_shot = true;
+        let mut evaluator = AlertEvaluator::new(vec![
+            once,
+            rule("always", AlertCondition::CrossesAbove, 100.0),
+        ]);
+
+        let alerts = replay(&mut evaluator, &[99.0, 101.0, 99.0, 101.0]);
+
+        assert_eq!(
+            alerts,
+            [("once".into(), 1), ("always".into(), 1), ("always".into(), 3)]
+        );
+        assert_eq!(evaluator.len(), 1);
+    }
+
+    #[test]
+    fn cooldown_suppresses_alerts() {
+        let mut cooled = rule("cooled", AlertCondition::CrossesAbove, 100.0);
+        cooled.cooldown = 3;
+        let mut evaluator = AlertEvaluator::new(vec![cooled]);
+
+        let alerts = replay(&mut evaluator, &[99.0, 101.0, 99.0, 101.0, 99.0, 101.0]);
+
+        assert_eq!(alerts, [("cooled".into(), 1), ("cooled".into(), 5)]);
+    }
+
+    #[test]
+    fn reloading_rules_keeps_their_state() {
+        let mut evaluator =
+            AlertEvaluator::new(vec![rule("above", AlertCondition::CrossesAbove, 100.0)]);
+        assert_eq!(
+            replay(&mut evaluator, &[99.0, 101.0]),
+            [("above".into(), 1)]
+        );
+
+        evaluator.set_rules(vec![rule("above", AlertCondition::CrossesAbove, 100.0)]);
+        assert!(evaluator.on_quote(&quote(2, 102.0, 0.0)).is_empty());
+    }
+}
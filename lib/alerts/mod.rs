@@ -0,0 +1,158 @@
+//! 🐎 » price alerts
+//!
+//! Evaluates the alert rules stored in the `alert_rule` table (e.g. "AAPL crosses above 200",
+//! "AAPL moves more than 3% intraday") against the quotes of the bus, and publishes an [`Alert`]
+//! every time a rule fires. Rules can be managed through the gRPC api.
+
+use {
+    crate::{
+        bus::{PublisherTrait, SubscriberTrait},
+        entities::{alert_rule, market, sea_orm::DatabaseConnection},
+        rustlers::{Quote, Ticker},
+    },
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::{collections::HashMap, time::Duration},
+    tokio::select,
+};
+
+mod alert;
+mod evaluator;
+mod rule;
+
+pub use {alert::Alert, evaluator::AlertEvaluator, rule::*};
+
+/// #### 🐎 » Alerts service
+///
+/// Subscribes to the quotes of the bus, evaluates the active alert rules stored in the database
+/// and publishes the alerts that fire. Rules are reloaded periodically, so changes made through
+/// the gRPC api are picked up without restarting the service.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut subscriber = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// subscriber.with_pattern("quote:*");
+/// let publisher = bus::redis::publisher::<Alert, _>(&redis).await?;
+///
+/// AlertSvc::new(conn).await?.run(subscriber, publisher).await?;
+/// ```
+pub struct AlertSvc {
+    conn: DatabaseConnection,
+    rule_svc: alert_rule::Service,
+    evaluator: AlertEvaluator,
+    reload_interval: Duration,
+}
+
+impl AlertSvc {
+    /// 🐎 » creates the service, loading the active rules from the database
+    pub async fn new(conn: DatabaseConnection) -> Result<Self> {
+        let mut svc = Self {
+            rule_svc: alert_rule::Service::new(conn.clone()).await,
+            conn,
+            evaluator: AlertEvaluator::default(),
+            reload_interval: Duration::from_secs(30),
+        };
+
+        svc.reload().await?;
+        Ok(svc)
+    }
+
+    /// 🐎 » sets the interval between rule reloads (defaults to 30 seconds)
+    pub fn set_reload_interval(&mut self, interval: Duration) -> &mut Self {
+        self.reload_interval = interval;
+        self
+    }
+
+    /// 🐎 » reloads the active rules from the database
+    ///
+    /// rules with an unknown condition or ticker are skipped
+    pub async fn reload(&mut self) -> Result<()> {
+        let markets = market::Service::new(self.conn.clone()).await.get_all_with_tickers().await?;
+        let keys: HashMap<String, String> = markets
+            .iter()
+            .flat_map(|(market, tickers)| {
+                tickers.iter().map(move |t| (t.id.clone(), Ticker::from(t, market).key()))
+            })
+            .collect();
+
+        let rules = self
+            .rule_svc
+            .get_all_active()
+            .await?
+            .iter()
+            .filter_map(|model| {
+                let Some(key) = keys.get(&model.ticker_id) else {
+                    warn!("Skipping alert rule '{}': ticker not found", model.id);
+                    return None;
+                };
+
+                match AlertRule::from_model(model, key.clone()) {
+                    Ok(rule) => Some(rule),
+                    Err(e) => {
+                        warn!("Skipping alert rule '{}': {}", model.id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        self.evaluator.set_rules(rules);
+        Ok(())
+    }
+
+    /// 🐎 » evaluates the rules until the subscriber's stream ends
+    pub async fn run<S, P>(mut self, mut subscriber: S, mut publisher: P) -> Result<()>
+    where
+        S: SubscriberTrait<Quote>,
+        P: PublisherTrait<Alert>,
+    {
+        info!("Evaluating {} alert rules", self.evaluator.len());
+
+        let mut stream = subscriber.stream().await?;
+        let mut reload = tokio::time::interval(self.reload_interval);
+
+        loop {
+            select! {
+                quote = stream.next() => {
+                    let Some(quote) = quote else {
+                        break;
+                    };
+
+                    for alert in self.evaluator.on_quote(&quote) {
+                        self.fired(&alert).await;
+
+                        if let Err(e) = publisher.publish(alert).await {
+                            warn!("Failed to publish alert: {}", e);
+                        }
+                    }
+                }
+                _ = reload.tick() => {
+                    if let Err(e) = self.reload().await {
+                        warn!("Failed to reload alert rules: {}", e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// records the time the rule fired, deactivating one-shot rules
+    async fn fired(&self, alert: &Alert) {
+        let one_shot = match self.rule_svc.get(alert.rule_id.clone()).await {
+            Ok(Some(rule)) => rule.one_shot,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to get alert rule '{}': {}", alert.rule_id, e);
+                return;
+            }
+        };
+
+        let result = self.rule_svc.set_triggered(alert.rule_id.clone(), alert.time, one_shot).await;
+        if let Err(e) = result {
+            warn!("Failed to update alert rule '{}': {}", alert.rule_id, e);
+        }
+    }
+}
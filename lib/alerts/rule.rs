@@ -0,0 +1,107 @@
+use {
+    crate::entities::alert_rule,
+    eyre::Result,
+    lool::fail,
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// #### 🐎 » Alert condition
+///
+/// The condition that triggers an alert rule. Conditions are edge triggered: a rule fires when its
+/// condition becomes true, not on every quote while it stays true.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// the price goes from below the threshold to the threshold or above
+    CrossesAbove,
+    /// the price goes from above the threshold to the threshold or below
+    CrossesBelow,
+    /// the change percent reaches the threshold or above (e.g. `3` for +3%)
+    ChangeAbove,
+    /// the change percent reaches the threshold or below (e.g. `-3` for -3%)
+    ChangeBelow,
+    /// the absolute change percent reaches the threshold (e.g. `3` for ±3%)
+    MovesMoreThan,
+}
+
+impl AlertCondition {
+    /// 🐎 » returns `true` if the condition needs a previous quote to be evaluated
+    pub fn is_crossing(&self) -> bool {
+        matches!(self, Self::CrossesAbove | Self::CrossesBelow)
+    }
+
+    /// 🐎 » returns `true` if the condition holds for the given price and change percent
+    pub fn holds(&self, threshold: f64, price: f64, change_percent: f64) -> bool {
+        match self {
+            Self::CrossesAbove => price >= threshold,
+            Self::CrossesBelow => price <= threshold,
+            Self::ChangeAbove => change_percent >= threshold,
+            Self::ChangeBelow => change_percent <= threshold,
+            Self::MovesMoreThan => change_percent.abs() >= threshold,
+        }
+    }
+}
+
+impl FromStr for AlertCondition {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "crosses_above" => Ok(Self::CrossesAbove),
+            "crosses_below" => Ok(Self::CrossesBelow),
+            "change_above" => Ok(Self::ChangeAbove),
+            "change_below" => Ok(Self::ChangeBelow),
+            "moves_more_than" => Ok(Self::MovesMoreThan),
+            _ => fail!("Unknown alert condition `{}`", s),
+        }
+    }
+}
+
+impl Display for AlertCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::CrossesAbove => "crosses_above",
+            Self::CrossesBelow => "crosses_below",
+            Self::ChangeAbove => "change_above",
+            Self::ChangeBelow => "change_below",
+            Self::MovesMoreThan => "moves_more_than",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// #### 🐎 » Alert rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub id: String,
+    /// key of the ticker (`market:symbol`)
+    pub ticker: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    /// min time between two alerts of the rule, in milliseconds
+    pub cooldown: i64,
+    /// if `true`, the rule is deactivated after it fires
+    pub one_shot: bool,
+    /// time the rule was last triggered (unix timestamp in milliseconds)
+    pub last_triggered_at: Option<i64>,
+}
+
+impl AlertRule {
+    /// 🐎 » creates a rule from its database model, given the key of its ticker
+    pub fn from_model(model: &alert_rule::Model, ticker: String) -> Result<Self> {
+        Ok(Self {
+            id: model.id.clone(),
+            ticker,
+            condition: model.condition.parse()?,
+            threshold: model.threshold,
+            cooldown: model.cooldown.saturating_mul(1000),
+            one_shot: model.one_shot,
+            last_triggered_at: model.last_triggered_at,
+        })
+    }
+}
@@ -1,8 +1,11 @@
 fn main() {
     let proto_files = vec![
+        "./lib/grpc/proto/bus.proto",
         "./lib/grpc/proto/rustler.proto",
         "./lib/grpc/proto/market.proto",
         "./lib/grpc/proto/ticker.proto",
+        "./lib/grpc/proto/alert.proto",
+        "./lib/grpc/proto/history.proto",
     ];
 
     for proto_file in proto_files {
@@ -12,8 +15,14 @@ fn main() {
 
 /// builds .proto files into `Rust` code
 fn compile_proto(proto_file: &str) {
-    tonic_build::configure()
-        .build_server(true)
+    let mut config = tonic_build::configure().build_server(true);
+
+    // the bus messages are generated once, in `bus::codec::proto`, and shared by the other protos
+    if !proto_file.ends_with("/bus.proto") {
+        config = config.extern_path(".bus", "crate::bus::codec::proto");
+    }
+
+    config
         .compile_protos(&[proto_file], &["."])
         .unwrap_or_else(|e| panic!("protobuf compile error: {}", e));
 
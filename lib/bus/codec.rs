@@ -0,0 +1,249 @@
+use {
+    super::{envelope, BusMessage},
+    eyre::Result,
+    lool::fail,
+    prost::Message,
+    std::{
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// protobuf definitions of the bus messages (see `bus.proto`), shared with the gRPC apis
+pub mod proto {
+    tonic::include_proto!("bus");
+}
+
+/// first byte of the payloads that aren't encoded with [`Codec::Legacy`]
+///
+/// legacy payloads are text, so they never start with it
+pub const FRAME_MARKER: u8 = 0x00;
+
+/// #### 🐎 » Bus codec
+///
+/// Format the messages are serialized with when they're published to the bus.
+///
+/// Payloads encoded with [`Codec::Legacy`] are the `¦` separated messages of
+/// [`super::ToFromBusMessage`]. Payloads encoded with any other codec start with a two bytes
+/// header, [`FRAME_MARKER`] followed by the tag of the codec, so subscribers can decode the
+/// messages of every codec and producers can be migrated one at a time.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut publisher = bus::redis::publisher::<Quote, _>(&redis).await?;
+/// publisher.with_codec(Codec::Protobuf);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// `¦` separated text (the format used before codecs were introduced)
+    #[default]
+    Legacy,
+    Json,
+    MsgPack,
+    /// protobuf messages of `bus.proto`
+    Protobuf,
+}
+
+impl Codec {
+    /// 🐎 » returns the byte that identifies the codec in the payload header (`None` for
+    /// [`Codec::Legacy`], which has no header)
+    pub fn tag(&self) -> Option<u8> {
+        match self {
+            Self::Legacy => None,
+            Self::Json => Some(b'j'),
+            Self::MsgPack => Some(b'm'),
+            Self::Protobuf => Some(b'p'),
+        }
+    }
+
+    /// 🐎 » returns the codec a payload was encoded with, along with the payload's body
+    pub fn detect(payload: &[u8]) -> Result<(Self, &[u8])> {
+        match payload {
+            [FRAME_MARKER, tag, body @ ..] => {
+                let codec = match tag {
+                    b'j' => Self::Json,
+                    b'm' => Self::MsgPack,
+                    b'p' => Self::Protobuf,
+                    _ => fail!("Unknown codec tag `{:#04x}`", tag),
+                };
+
+                Ok((codec, body))
+            }
+            [FRAME_MARKER] => fail!("Truncated payload header"),
+            _ => Ok((Self::Legacy, payload)),
+        }
+    }
+
+    /// 🐎 » encodes a message, prepending the header of the codec
+    pub fn encode<RM: BusMessage>(&self, message: &RM) -> Result<Vec<u8>> {
+        let body = match self {
+            Self::Legacy => return Ok(message.as_message().into_bytes()),
+            Self::Json => serde_json::to_vec(message)?,
+            Self::MsgPack => rmp_serde::to_vec_named(message)?,
+            Self::Protobuf => message.to_proto().encode_to_vec(),
+        };
+
+        let mut payload = Vec::with_capacity(body.len() + 2);
+        payload.push(FRAME_MARKER);
+        payload.extend(self.tag());
+        payload.extend(body);
+
+        Ok(payload)
+    }
+
+    /// 🐎 » decodes a payload encoded with any codec, ignoring its envelope if it has one
+    pub fn decode<RM: BusMessage>(payload: &[u8]) -> Result<RM> {
+        let (_, payload) = envelope::open(payload)?;
+        let (codec, body) = Self::detect(payload)?;
+
+        match codec {
+            Self::Legacy => RM::from_message(std::str::from_utf8(body)?),
+            Self::Json => Ok(serde_json::from_slice(body)?),
+            Self::MsgPack => Ok(rmp_serde::from_slice(body)?),
+            Self::Protobuf => RM::from_proto(RM::Proto::decode(body)?),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "legacy" => Ok(Self::Legacy),
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MsgPack),
+            "protobuf" => Ok(Self::Protobuf),
+            _ => fail!("Unknown codec `{}`", s),
+        }
+    }
+}
+
+impl Display for Codec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Legacy => "legacy",
+            Self::Json => "json",
+            Self::MsgPack => "msgpack",
+            Self::Protobuf => "protobuf",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            bus::{envelope::Metadata, ToFromBusMessage},
+            rustlers::{MarketHourType, Quote},
+        },
+    };
+
+    const CODECS: [Codec; 4] = [Codec::Legacy, Codec::Json, Codec::MsgPack, Codec::Protobuf];
+
+    fn quote(source: Option<&str>, volume: Option<f64>) -> Quote {
+        Quote {
+            id: "AAPL".into(),
+            market: "NASDAQ".into(),
+            price: 189.25,
+            change_percent: -1.5,
+            time: 1729252800000,
+            market_hours: MarketHourType::Post,
+            source: source.map(Into::into),
+            volume,
+            stats: None,
+        }
+    }
+
+    fn meta(seq: u64) -> Metadata {
+        Metadata {
+            version: envelope::SCHEMA_VERSION,
+            source: Some("rustler".into()),
+            instance: "instance".into(),
+            published_at: 1729252800000,
+            seq,
+            trace_id: None,
+        }
+    }
+
+    /// compares every field of the quotes the codecs carry (the legacy message has all of them)
+    fn assert_same(actual: &Quote, expected: &Quote) {
+        assert_eq!(actual.as_message(), expected.as_message());
+    }
+
+    #[test]
+    fn round_trip() {
+        for codec in CODECS {
+            for quote in [quote(Some("rustler"), Some(1234.5)), quote(None, None)] {
+                let payload = codec.encode(&quote).unwrap();
+                let (detected, _) = Codec::detect(&payload).unwrap();
+                assert_eq!(detected, codec);
+
+                let decoded: Quote = Codec::decode(&payload).unwrap();
+                assert_same(&decoded, &quote);
+            }
+        }
+    }
+
+    #[test]
+    fn headers() {
+        let quote = quote(None, None);
+
+        assert_eq!(
+            Codec::Legacy.encode(&quote).unwrap(),
+            quote.as_message().into_bytes()
+        );
+        for (codec, tag) in [(Codec::Json, b'j'), (Codec::MsgPack, b'm'), (Codec::Protobuf, b'p')] {
+            assert_eq!(codec.encode(&quote).unwrap()[..2], [FRAME_MARKER, tag]);
+        }
+    }
+
+    #[test]
+    fn mixed_codecs_and_envelopes() {
+        let quote = quote(Some("rustler"), Some(10.0));
+
+        // a subscriber gets the messages of producers using different codecs, with or without
+        // envelopes
+        let payloads: Vec<Vec<u8>> = CODECS
+            .iter()
+            .enumerate()
+            .flat_map(|(i, codec)| {
+                let payload = codec.encode(&quote).unwrap();
+                let sealed = envelope::seal(&meta(i as u64 + 1), payload.clone()).unwrap();
+                [payload, sealed]
+            })
+            .collect();
+
+        for payload in &payloads {
+            let decoded: Quote = Codec::decode(payload).unwrap();
+            assert_same(&decoded, &quote);
+        }
+
+        let (meta, body) = envelope::open(&payloads[5]).unwrap();
+        assert_eq!(meta.map(|m| m.seq), Some(3));
+        assert_eq!(Codec::detect(body).unwrap().0, Codec::MsgPack);
+    }
+
+    #[test]
+    fn invalid_payloads() {
+        assert!(Codec::detect(&[FRAME_MARKER, b'x', 1, 2]).is_err());
+        assert!(Codec::detect(&[FRAME_MARKER]).is_err());
+
+        assert!(Codec::decode::<Quote>(b"AAPL\xC2\xA6NASDAQ").is_err());
+        assert!(Codec::decode::<Quote>(&[FRAME_MARKER, b'j', b'{']).is_err());
+        assert!(Codec::decode::<Quote>(&[FRAME_MARKER, b'e', 0, 0]).is_err());
+    }
+
+    #[test]
+    fn names() {
+        for codec in CODECS {
+            assert_eq!(codec.to_string().parse::<Codec>().unwrap(), codec);
+        }
+
+        assert!("xml".parse::<Codec>().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use {
+    super::{
+        codec::{proto, Codec},
+        envelope::{self, Enveloped, GapDetector},
+        redis::stream::StreamMsg,
+        BusMessage, FromBusVal, PublisherTrait, ToBusKey, ToBusVal, ToFromBusMessage, ToFromProto,
+    },
+    chrono::Utc,
+    eyre::{eyre, Result},
+    lool::{logger::warn, s},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        fmt::Display,
+        marker::PhantomData,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// separator of the fields of a bus message
+pub const SEPARATOR: char = '¦';
+
+/// #### 🐎 » Message fields
+///
+/// The `¦` separated fields of a bus message, with helpers to parse them and fail with a
+/// descriptive error when they're missing or invalid.
+pub struct MessageFields<'a> {
+    parts: Vec<&'a str>,
+}
+
+impl<'a> MessageFields<'a> {
+    /// 🐎 » splits a message into its fields
+    pub fn new(msg: &'a str) -> Self {
+        Self {
+            parts: msg.split(SEPARATOR).collect(),
+        }
+    }
+
+    /// 🐎 » returns a required field
+    pub fn get(&self, index: usize, name: &str) -> Result<&'a str> {
+        self.parts.get(index).copied().ok_or_else(|| eyre!("Missing field `{}`", name))
+    }
+
+    /// 🐎 » returns an optional field (`None` if it's missing or empty)
+    pub fn opt(&self, index: usize) -> Option<&'a str> {
+        self.parts.get(index).copied().filter(|v| !v.is_empty())
+    }
+
+    /// 🐎 » parses a required field
+    pub fn parse<T>(&self, index: usize, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get(index, name)?;
+        value.parse().map_err(|e| eyre!("Invalid field `{}` ({:?}): {}", name, value, e))
+    }
+
+    /// 🐎 » parses an optional field, failing only if it's present and invalid
+    pub fn parse_opt<T>(&self, index: usize, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.opt(index) {
+            Some(_) => self.parse(index, name).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// #### 🐎 » Bus fields
+///
+/// The fields of the hash of a bus key (see [`super::FromBusVal`]), with helpers to parse them
+/// and fail with a descriptive error when they're missing or invalid.
+pub struct BusFields<'a> {
+    fields: &'a HashMap<String, String>,
+}
+
+impl<'a> BusFields<'a> {
+    /// 🐎 » wraps the fields of a hash
+    pub fn new(fields: &'a HashMap<String, String>) -> Self {
+        Self { fields }
+    }
+
+    /// 🐎 » returns a required field
+    pub fn get(&self, name: &str) -> Result<&'a str> {
+        self.fields.get(name).map(String::as_str).ok_or_else(|| eyre!("Missing field `{}`", name))
+    }
+
+    /// 🐎 » returns an optional field (`None` if it's missing or empty)
+    pub fn opt(&self, name: &str) -> Option<&'a str> {
+        self.fields.get(name).map(String::as_str).filter(|v| !v.is_empty())
+    }
+
+    /// 🐎 » parses a required field
+    pub fn parse<T>(&self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get(name)?;
+        value.parse().map_err(|e| eyre!("Invalid field `{}` ({:?}): {}", name, value, e))
+    }
+
+    /// 🐎 » parses an optional field, failing only if it's present and invalid
+    pub fn parse_opt<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.opt(name) {
+            Some(_) => self.parse(name).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// #### 🐎 » Dead letter
+///
+/// A payload that couldn't be decoded by a subscriber, republished with the decode error for
+/// inspection (see the `with_dead_letter` method of the subscribers).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// channel the payload was received from (without the key prefix)
+    pub channel: String,
+    /// time the payload was received (unix timestamp in milliseconds)
+    pub time: i64,
+    pub error: String,
+    /// the payload as received if it's valid utf-8, hex encoded otherwise
+    pub payload: String,
+}
+
+impl ToBusVal for DeadLetter {
+    fn to_bus_val(&self) -> Vec<(String, String)> {
+        vec![
+            (s!("channel"), self.channel.to_owned()),
+            (s!("time"), self.time.to_string()),
+            (s!("error"), self.error.to_owned()),
+            (s!("payload"), self.payload.to_owned()),
+        ]
+    }
+}
+
+impl FromBusVal for DeadLetter {
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self> {
+        let fields = BusFields::new(fields);
+
+        Ok(Self {
+            channel: fields.get("channel")?.to_string(),
+            time: fields.parse::<i64>("time")?,
+            error: fields.get("error")?.to_string(),
+            payload: fields.get("payload")?.to_string(),
+        })
+    }
+}
+
+impl ToBusKey for DeadLetter {
+    fn to_bus_key(&self) -> String {
+        format!("dead-letter:{}", self.channel)
+    }
+}
+
+impl ToFromBusMessage for DeadLetter {
+    /// 🐎 » converts a `DeadLetter` to a serialized message that can be sent over a redis channel
+    ///
+    /// the message is in the format `channel¦time¦error¦payload`, where the payload is kept as
+    /// is (it can contain separators)
+    fn as_message(&self) -> String {
+        format!(
+            "{}¦{}¦{}¦{}",
+            self.channel,
+            self.time,
+            self.error.replace(SEPARATOR, "|"),
+            self.payload,
+        )
+    }
+
+    /// 🐎 » creates a `DeadLetter` from a message
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self> {
+        let mut parts = msg.as_ref().splitn(4, SEPARATOR);
+        let mut next = |name: &str| parts.next().ok_or_else(|| eyre!("Missing field `{}`", name));
+
+        Ok(Self {
+            channel: next("channel")?.to_string(),
+            time: next("time")?.parse()?,
+            error: next("error")?.to_string(),
+            payload: next("payload")?.to_string(),
+        })
+    }
+}
+
+impl ToFromProto for DeadLetter {
+    type Proto = proto::DeadLetter;
+
+    /// 🐎 » converts a `DeadLetter` to its protobuf message
+    fn to_proto(&self) -> proto::DeadLetter {
+        proto::DeadLetter {
+            channel: self.channel.clone(),
+            time: self.time,
+            error: self.error.clone(),
+            payload: self.payload.clone(),
+        }
+    }
+
+    /// 🐎 » creates a `DeadLetter` from its protobuf message
+    fn from_proto(letter: proto::DeadLetter) -> Result<Self> {
+        Ok(Self {
+            channel: letter.channel,
+            time: letter.time,
+            error: letter.error,
+            payload: letter.payload,
+        })
+    }
+}
+
+impl StreamMsg for DeadLetter {}
+impl BusMessage for DeadLetter {
+    fn message_time(&self) -> Option<i64> {
+        Some(self.time)
+    }
+}
+
+/// publisher of the dead letters of a subscriber
+pub type DeadLetterPublisher = Box<dyn PublisherTrait<DeadLetter> + Send + Sync>;
+
+/// decodes the payloads received by a subscriber, counting the ones that can't be decoded and
+/// republishing them to the dead-letter channel if there's one
+pub(crate) struct Decoder<RM: BusMessage> {
+    prefix: String,
+    errors: Arc<AtomicU64>,
+    dead_letter: Option<DeadLetterPublisher>,
+    gaps: Option<GapDetector>,
+    message_type: PhantomData<RM>,
+}
+
+impl<RM: BusMessage> Decoder<RM> {
+    pub(crate) fn new(
+        prefix: String,
+        errors: Arc<AtomicU64>,
+        dead_letter: Option<DeadLetterPublisher>,
+    ) -> Self {
+        Self {
+            prefix,
+            errors,
+            dead_letter,
+            gaps: None,
+            message_type: PhantomData,
+        }
+    }
+
+    /// checks the sequence numbers of the envelopes with the given gap detector
+    pub(crate) fn with_gaps(mut self, gaps: GapDetector) -> Self {
+        self.gaps = Some(gaps);
+        self
+    }
+
+    /// decodes a payload received from the given channel, returning `None` if it's invalid
+    ///
+    /// the payload can be encoded with any [`Codec`], with or without an envelope
+    pub(crate) async fn decode(
+        &mut self,
+        channel: &str,
+        payload: Vec<u8>,
+    ) -> Option<Enveloped<RM>> {
+        let prefix = format!("{}:", self.prefix);
+        let channel = channel.strip_prefix(&prefix).unwrap_or(channel);
+
+        let decoded = envelope::open(&payload).and_then(|(meta, body)| {
+            Ok(Enveloped {
+                message: Codec::decode::<RM>(body)?,
+                meta,
+            })
+        });
+
+        let error = match decoded {
+            Ok(enveloped) => {
+                if let (Some(gaps), Some(meta)) = (self.gaps.as_mut(), &enveloped.meta) {
+                    gaps.check(channel, meta);
+                }
+
+                return Some(enveloped);
+            }
+            Err(e) => e,
+        };
+
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        warn!("Skipping undecodable message from '{}': {}", channel, error);
+
+        // dead letters received by broad patterns aren't republished, to avoid loops
+        if channel.starts_with("dead-letter:") {
+            return None;
+        }
+
+        if let Some(publisher) = self.dead_letter.as_mut() {
+            let letter = DeadLetter {
+                channel: channel.to_string(),
+                time: Utc::now().timestamp_millis(),
+                error: error.to_string(),
+                payload: String::from_utf8(payload).unwrap_or_else(|e| hex(e.as_bytes())),
+            };
+
+            if let Err(e) = publisher.publish(letter).await {
+                warn!("Failed to publish dead letter: {}", e);
+            }
+        }
+
+        None
+    }
+}
+
+/// encodes bytes as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
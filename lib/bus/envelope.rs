@@ -0,0 +1,251 @@
+use {
+    super::{codec::FRAME_MARKER, redis::stream::StreamMsg, BusMessage},
+    chrono::Utc,
+    eyre::Result,
+    lool::{fail, logger::warn},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    },
+    tokio::sync::broadcast::{self, Receiver, Sender},
+};
+
+/// version of the envelope metadata written by the publishers
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// tag that identifies enveloped payloads (after the [`FRAME_MARKER`])
+const ENVELOPE_TAG: u8 = b'e';
+
+/// publisher instances tracked per channel by the gap detectors; every restart of a publisher is
+/// a new instance, so the least recently seen ones are forgotten
+const MAX_INSTANCES: usize = 4;
+
+/// #### 🐎 » Message metadata
+///
+/// Metadata of the envelope the publishers wrap the messages in (when enabled).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// version of the envelope schema (see [`SCHEMA_VERSION`])
+    pub version: u32,
+    /// name of the producer (e.g. the rustler), if the publisher was given one
+    pub source: Option<String>,
+    /// unique id of the publisher instance; sequence numbers are scoped to it
+    pub instance: String,
+    /// time the message was published (unix timestamp in milliseconds)
+    pub published_at: i64,
+    /// sequence number of the message among the ones published by the instance with the same
+    /// bus key, starting at 1
+    pub seq: u64,
+    pub trace_id: Option<String>,
+}
+
+/// 🐎 » a message along with the metadata of its envelope (`None` for payloads published without
+/// an envelope)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enveloped<RM: BusMessage> {
+    pub meta: Option<Metadata>,
+    pub message: RM,
+}
+
+impl<RM: BusMessage> StreamMsg for Enveloped<RM> {}
+
+/// 🐎 » wraps an encoded payload in an envelope
+///
+/// the envelope is the [`FRAME_MARKER`], the envelope tag, the length of the metadata (u32, big
+/// endian), the metadata as json and the payload
+pub fn seal(meta: &Metadata, payload: Vec<u8>) -> Result<Vec<u8>> {
+    let header = serde_json::to_vec(meta)?;
+
+    let mut sealed = Vec::with_capacity(header.len() + payload.len() + 6);
+    sealed.extend([FRAME_MARKER, ENVELOPE_TAG]);
+    sealed.extend(u32::try_from(header.len())?.to_be_bytes());
+    sealed.extend(header);
+    sealed.extend(payload);
+
+    Ok(sealed)
+}
+
+/// 🐎 » returns the metadata of an enveloped payload along with the encoded message, or `None`
+/// and the payload as is if it has no envelope
+pub fn open(payload: &[u8]) -> Result<(Option<Metadata>, &[u8])> {
+    let [FRAME_MARKER, ENVELOPE_TAG, rest @ ..] = payload else {
+        return Ok((None, payload));
+    };
+
+    let Some((len, rest)) = rest.split_first_chunk::<4>() else {
+        fail!("Truncated envelope header");
+    };
+
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        fail!("Truncated envelope metadata");
+    }
+
+    let (header, body) = rest.split_at(len);
+    Ok((Some(serde_json::from_slice(header)?), body))
+}
+
+/// stamps the metadata of the messages of a publisher
+///
+/// envelopes are opt-in (see [`Stamper::enable`]): consumers that predate them read the payloads
+/// as they are, so enveloped messages would break them. Clones of a publisher are the same
+/// instance, so they share the sequence numbers
+#[derive(Debug, Clone)]
+pub(crate) struct Stamper {
+    enabled: bool,
+    source: Option<String>,
+    instance: String,
+    /// last sequence number by bus key
+    seqs: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Default for Stamper {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: None,
+            instance: uuid::Uuid::new_v4().to_string(),
+            seqs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl Stamper {
+    pub(crate) fn set_source(&mut self, source: &str) {
+        self.source = Some(source.to_string());
+    }
+
+    /// wraps the messages in envelopes from now on
+    pub(crate) fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// wraps the encoded payload of a message in an envelope, unless envelopes are disabled
+    pub(crate) fn seal<RM: BusMessage>(
+        &mut self,
+        message: &RM,
+        payload: Vec<u8>,
+        trace_id: Option<String>,
+    ) -> Result<Vec<u8>> {
+        if !self.enabled {
+            return Ok(payload);
+        }
+
+        let seq = match self.seqs.lock() {
+            Ok(mut seqs) => {
+                let seq = seqs.entry(message.to_bus_key()).or_default();
+                *seq += 1;
+                *seq
+            }
+            Err(_) => fail!("Sequence numbers of the publisher are poisoned"),
+        };
+
+        let meta = Metadata {
+            version: SCHEMA_VERSION,
+            source: message.source().map(str::to_string).or_else(|| self.source.clone()),
+            instance: self.instance.clone(),
+            published_at: Utc::now().timestamp_millis(),
+            seq,
+            trace_id,
+        };
+
+        seal(&meta, payload)
+    }
+}
+
+/// 🐎 » a range of messages a subscriber missed, detected from the sequence numbers of the
+/// envelopes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    /// channel of the messages (without the key prefix)
+    pub channel: String,
+    pub source: Option<String>,
+    pub instance: String,
+    /// first missed sequence number
+    pub from: u64,
+    /// last missed sequence number (inclusive)
+    pub to: u64,
+}
+
+impl Gap {
+    /// 🐎 » returns the number of missed messages
+    pub fn missed(&self) -> u64 {
+        self.to - self.from + 1
+    }
+}
+
+/// detects the gaps in the sequence numbers of the messages received by a subscriber, counting
+/// the missed messages and sending the gaps to the subscriber's listeners
+#[derive(Debug, Clone)]
+pub(crate) struct GapDetector {
+    missed: Arc<AtomicU64>,
+    sender: Sender<Gap>,
+    /// last sequence number of the publisher instances of each channel, least recently seen first
+    last: HashMap<String, Vec<(String, u64)>>,
+}
+
+impl Default for GapDetector {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(100);
+
+        Self {
+            missed: Arc::new(AtomicU64::new(0)),
+            sender,
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl GapDetector {
+    /// returns the number of missed messages
+    pub(crate) fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+
+    /// returns a receiver of the detected gaps
+    pub(crate) fn receiver(&self) -> Receiver<Gap> {
+        self.sender.subscribe()
+    }
+
+    /// checks the sequence number of a message received from the given channel
+    ///
+    /// messages at or behind the last sequence number (duplicates or out of order) don't move it.
+    /// Only the last [`MAX_INSTANCES`] instances of a channel are tracked, an instance that was
+    /// forgotten starts its sequence again
+    pub(crate) fn check(&mut self, channel: &str, meta: &Metadata) {
+        let instances = self.last.entry(channel.to_string()).or_default();
+
+        // the most recently seen instance goes last
+        let last = match instances.iter().position(|(instance, _)| *instance == meta.instance) {
+            Some(i) => instances.remove(i).1,
+            None => 0,
+        };
+        if instances.len() >= MAX_INSTANCES {
+            instances.remove(0);
+        }
+        instances.push((meta.instance.clone(), last.max(meta.seq)));
+
+        // the first message of an instance starts the sequence wherever it is
+        if last != 0 && meta.seq > last + 1 {
+            let gap = Gap {
+                channel: channel.to_string(),
+                source: meta.source.clone(),
+                instance: meta.instance.clone(),
+                from: last + 1,
+                to: meta.seq - 1,
+            };
+
+            let missed = gap.missed();
+            warn!(
+                "Missed {} messages from '{}' ({}..={})",
+                missed, channel, gap.from, gap.to
+            );
+            self.missed.fetch_add(missed, Ordering::Relaxed);
+            let _ = self.sender.send(gap);
+        }
+    }
+}
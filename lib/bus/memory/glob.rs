@@ -0,0 +1,186 @@
+/// 🐎 » checks if a channel matches a glob-style pattern, following the semantics of the redis
+/// `PSUBSCRIBE` command:
+///
+/// - `?` matches any single character
+/// - `*` matches any sequence of characters, including none (and `:`)
+/// - `[abc]`, `[a-z]` and `[^a]` match a character in (or not in) the set
+/// - `\` escapes the next character
+pub fn matches(pattern: &str, channel: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let channel: Vec<char> = channel.chars().collect();
+
+    matches_chars(&pattern, &channel)
+}
+
+fn matches_chars(pattern: &[char], channel: &[char]) -> bool {
+    let Some(&first) = pattern.first() else {
+        return channel.is_empty();
+    };
+
+    match first {
+        '*' => {
+            let rest = trim_stars(pattern);
+            if rest.is_empty() {
+                return true;
+            }
+
+            (0..=channel.len()).any(|i| matches_chars(rest, &channel[i..]))
+        }
+        '?' => !channel.is_empty() && matches_chars(&pattern[1..], &channel[1..]),
+        '[' => {
+            let Some(&c) = channel.first() else {
+                return false;
+            };
+
+            let (matched, rest) = match_class(&pattern[1..], c);
+            matched && matches_chars(rest, &channel[1..])
+        }
+        '\\' if pattern.len() > 1 => {
+            channel.first() == Some(&pattern[1]) && matches_chars(&pattern[2..], &channel[1..])
+        }
+        literal => channel.first() == Some(&literal) && matches_chars(&pattern[1..], &channel[1..]),
+    }
+}
+
+/// skips the leading `*`s of a pattern
+fn trim_stars(pattern: &[char]) -> &[char] {
+    let stars = pattern.iter().take_while(|c| **c == '*').count();
+    &pattern[stars..]
+}
+
+/// checks if a character is in the set that starts the given pattern (right after the `[`),
+/// returning the rest of the pattern after the closing `]`
+///
+/// like in redis, an unclosed set extends to the end of the pattern
+fn match_class(pattern: &[char], c: char) -> (bool, &[char]) {
+    let negate = pattern.first() == Some(&'^');
+    let mut i = usize::from(negate);
+    let mut matched = false;
+
+    while i < pattern.len() && pattern[i] != ']' {
+        match pattern[i] {
+            '\\' if i + 1 < pattern.len() => {
+                matched |= pattern[i + 1] == c;
+                i += 2;
+            }
+            start if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' => {
+                let end = pattern[i + 2];
+                let (low, high) = if start <= end { (start, end) } else { (end, start) };
+                matched |= (low..=high).contains(&c);
+                i += 3;
+            }
+            other => {
+                matched |= other == c;
+                i += 1;
+            }
+        }
+    }
+
+    let rest = pattern.get(i + 1..).unwrap_or_default();
+    (matched != negate, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    /// checks every `(pattern, channel, expected)` case
+    fn check(cases: &[(&str, &str, bool)]) {
+        for (pattern, channel, expected) in cases {
+            assert_eq!(
+                matches(pattern, channel),
+                *expected,
+                "`{}` against `{}`",
+                pattern,
+                channel
+            );
+        }
+    }
+
+    #[test]
+    fn literals() {
+        check(&[
+            ("quote:NASDAQ:AAPL", "quote:NASDAQ:AAPL", true),
+            ("quote:NASDAQ:AAPL", "quote:NASDAQ:AAP", false),
+            ("quote:NASDAQ:AAP", "quote:NASDAQ:AAPL", false),
+            ("", "", true),
+            ("", "a", false),
+        ]);
+    }
+
+    #[test]
+    fn question_mark() {
+        check(&[
+            ("quote:?", "quote:a", true),
+            ("quote:?", "quote:", false),
+            ("quote:?", "quote:ab", false),
+            ("q??te", "quote", true),
+            ("quote:?", "quote:é", true),
+        ]);
+    }
+
+    #[test]
+    fn star() {
+        check(&[
+            ("*", "", true),
+            ("*", "quote:NASDAQ:AAPL", true),
+            ("quote:*", "quote:", true),
+            ("quote:*", "quote:NASDAQ:AAPL", true),
+            ("quote:*", "candle:NASDAQ:AAPL", false),
+            ("*:AAPL", "quote:NASDAQ:AAPL", true),
+            ("*:AAPL", "quote:NASDAQ:AAPLX", false),
+            ("quote:*:AAPL", "quote:NASDAQ:AAPL", true),
+            ("quote:**:AAPL", "quote::AAPL", true),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "aXcYb", false),
+        ]);
+    }
+
+    #[test]
+    fn classes() {
+        check(&[
+            ("quote:[AB]", "quote:A", true),
+            ("quote:[AB]", "quote:C", false),
+            ("quote:[a-z]", "quote:m", true),
+            ("quote:[a-z]", "quote:M", false),
+            ("quote:[z-a]", "quote:m", true),
+            ("quote:[a-z0-9]", "quote:7", true),
+            ("quote:[^a]", "quote:b", true),
+            ("quote:[^a]", "quote:a", false),
+            ("quote:[^a-z]", "quote:A", true),
+            ("quote:[^a-z]", "quote:q", false),
+            ("quote:[a]", "quote:", false),
+            ("[\\]]", "]", true),
+            ("[a\\-z]", "-", true),
+            ("[a\\-z]", "m", false),
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        check(&[
+            ("quote:\\*", "quote:*", true),
+            ("quote:\\*", "quote:AAPL", false),
+            ("quote:\\?", "quote:?", true),
+            ("quote:\\?", "quote:a", false),
+            ("\\[a]", "[a]", true),
+            ("\\[a]", "a", false),
+            ("a\\\\b", "a\\b", true),
+            // a trailing backslash is a literal
+            ("a\\", "a\\", true),
+        ]);
+    }
+
+    #[test]
+    fn unclosed_class() {
+        // like in redis, the set extends to the end of the pattern
+        check(&[
+            ("quote:[ab", "quote:a", true),
+            ("quote:[ab", "quote:b", true),
+            ("quote:[ab", "quote:c", false),
+            ("quote:[ab", "quote:ab", false),
+            ("quote:[", "quote:", false),
+            ("quote:[^", "quote:a", true),
+        ]);
+    }
+}
@@ -0,0 +1,297 @@
+//! 🐎 » in-process bus
+//!
+//! A bus backend built on tokio broadcast channels, for single binary deployments and tests that
+//! shouldn't need a redis server. It mirrors the redis backend: messages are published to
+//! prefixed channels, subscribers use `PSUBSCRIBE` glob patterns, and the fields of the last
+//! message published to each key are kept in a last-value store (like the redis hashes).
+//!
+//! **Usage**
+//!
+//! ```rust
+//! let bus = MemoryBus::new();
+//! let (mut publisher, mut subscriber) = bus::memory::pubsub::<Quote>(&bus);
+//!
+//! subscriber.with_pattern("quote:*");
+//! let mut quotes = subscriber.stream().await?;
+//!
+//! publisher.publish(quote).await?;
+//! ```
+
+use {
+    super::{redis::KEY_PREFIX, BusMessage},
+    std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
+    },
+    tokio::sync::broadcast::{self, Receiver, Sender},
+};
+
+mod glob;
+pub mod publish;
+pub mod snapshot;
+pub mod subscribe;
+
+pub use glob::matches;
+
+/// default number of messages a slow subscriber can fall behind before losing messages
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// 🐎 » a message published to the in-process bus: its channel and its encoded payload (see
+/// [`crate::bus::Codec`])
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub channel: String,
+    pub payload: Vec<u8>,
+}
+
+/// fields of the last message published to a key, and when they expire
+#[derive(Debug, Default)]
+struct Stored {
+    fields: HashMap<String, String>,
+    expires_at: Option<Instant>,
+}
+
+impl Stored {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// #### 🐎 » In-process bus
+///
+/// Shared by the publishers and subscribers of the same process; it's cheap to clone, and every
+/// clone refers to the same bus.
+#[derive(Clone)]
+pub struct MemoryBus {
+    sender: Sender<Envelope>,
+    /// fields of the last message published to each key
+    store: Arc<RwLock<HashMap<String, Stored>>>,
+}
+
+impl Default for MemoryBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryBus {
+    /// 🐎 » creates a bus with the default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// 🐎 » creates a bus where subscribers can fall behind by up to `capacity` messages
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self {
+            sender,
+            store: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 🐎 » returns the fields stored for a key (including its prefix), like redis `HGETALL`
+    pub fn get(&self, key: &str) -> Option<HashMap<String, String>> {
+        let store = self.store.read().ok()?;
+        let stored = store.get(key).filter(|s| !s.is_expired(Instant::now()))?;
+        Some(stored.fields.clone())
+    }
+
+    /// 🐎 » returns the stored keys matching a glob pattern (including the prefix), like redis
+    /// `KEYS`
+    ///
+    /// the expired keys are removed from the store while listing them
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        match self.store.write() {
+            Ok(mut store) => {
+                let now = Instant::now();
+                store.retain(|_, s| !s.is_expired(now));
+                store.keys().filter(|k| matches(pattern, k)).cloned().collect()
+            }
+            Err(_) => vec![],
+        }
+    }
+
+    /// 🐎 » deletes the fields stored for a key (including its prefix), like redis `DEL`
+    ///
+    /// returns `true` if the key existed
+    pub fn delete(&self, key: &str) -> bool {
+        let removed = self.store.write().ok().and_then(|mut store| store.remove(key));
+        removed.is_some_and(|s| !s.is_expired(Instant::now()))
+    }
+
+    /// stores the fields of a message and sends it to the subscribers
+    ///
+    /// with a `ttl`, the fields of the key expire after it (like redis `PEXPIRE`); without one,
+    /// the previous expiration of the key is kept
+    pub(crate) fn publish(
+        &self,
+        channel: String,
+        fields: Vec<(String, String)>,
+        payload: Vec<u8>,
+        ttl: Option<Duration>,
+    ) {
+        if let Ok(mut store) = self.store.write() {
+            let now = Instant::now();
+            let stored = store.entry(channel.clone()).or_default();
+
+            // an expired key starts over, like a redis hash that was evicted
+            if stored.is_expired(now) {
+                *stored = Stored::default();
+            }
+
+            stored.fields.extend(fields);
+            if let Some(ttl) = ttl {
+                stored.expires_at = Some(now + ttl);
+            }
+        }
+
+        // sending only fails when there are no subscribers, just like publishing to redis
+        let _ = self.sender.send(Envelope { channel, payload });
+    }
+
+    /// returns a receiver of every message published from now on
+    pub(crate) fn receiver(&self) -> Receiver<Envelope> {
+        self.sender.subscribe()
+    }
+}
+
+/// 🐎 » **publisher**: create in-process bus publisher
+pub fn publisher<RM: BusMessage>(bus: &MemoryBus) -> publish::MemoryPublisher<RM> {
+    publish::MemoryPublisher::new(bus)
+}
+
+/// 🐎 » **subscriber**: create in-process bus subscriber
+pub fn subscriber<RM: BusMessage>(bus: &MemoryBus) -> subscribe::MemorySubscriber<RM> {
+    subscribe::MemorySubscriber::new(bus)
+}
+
+/// 🐎 » **snapshot**: create in-process bus snapshot, to read the last published values
+pub fn snapshot<RM: BusMessage>(bus: &MemoryBus) -> snapshot::MemorySnapshot<RM> {
+    snapshot::MemorySnapshot::new(bus)
+}
+
+/// 🐎 » **pubsub**: create in-process bus publisher and subscriber
+pub fn pubsub<RM: BusMessage>(
+    bus: &MemoryBus,
+) -> (
+    publish::MemoryPublisher<RM>,
+    subscribe::MemorySubscriber<RM>,
+) {
+    (
+        publish::MemoryPublisher::new(bus),
+        subscribe::MemorySubscriber::new(bus),
+    )
+}
+
+/// the default key prefix, shared with the redis backend
+fn default_prefix() -> String {
+    KEY_PREFIX.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn publish(bus: &MemoryBus, key: &str, values: &[(&str, &str)], ttl: Option<Duration>) {
+        bus.publish(key.into(), fields(values), vec![], ttl);
+    }
+
+    #[test]
+    fn the_store_keeps_the_last_fields_of_each_key() {
+        let bus = MemoryBus::new();
+        publish(
+            &bus,
+            "rustler:quote:AAPL",
+            &[("price", "1"), ("volume", "10")],
+            None,
+        );
+        publish(&bus, "rustler:quote:AAPL", &[("price", "2")], None);
+        publish(&bus, "rustler:quote:MSFT", &[("price", "3")], None);
+
+        // like a redis hash, the fields missing from a message are kept
+        let stored = bus.get("rustler:quote:AAPL").unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored["price"], "2");
+        assert_eq!(stored["volume"], "10");
+        assert!(bus.get("rustler:quote:IBM").is_none());
+
+        let mut keys = bus.keys("rustler:quote:*");
+        keys.sort();
+        assert_eq!(keys, ["rustler:quote:AAPL", "rustler:quote:MSFT"]);
+        assert!(bus.keys("other:*").is_empty());
+    }
+
+    #[test]
+    fn deleted_keys_are_removed_from_the_store() {
+        let bus = MemoryBus::new();
+        publish(&bus, "rustler:quote:AAPL", &[("price", "1")], None);
+
+        assert!(bus.delete("rustler:quote:AAPL"));
+        assert!(!bus.delete("rustler:quote:AAPL"));
+        assert!(bus.get("rustler:quote:AAPL").is_none());
+        assert!(bus.keys("*").is_empty());
+    }
+
+    #[test]
+    fn expired_keys_are_not_returned() {
+        let bus = MemoryBus::new();
+        publish(
+            &bus,
+            "rustler:quote:AAPL",
+            &[("price", "1")],
+            Some(Duration::ZERO),
+        );
+        publish(
+            &bus,
+            "rustler:quote:MSFT",
+            &[("price", "2")],
+            Some(Duration::from_secs(60)),
+        );
+
+        assert!(bus.get("rustler:quote:AAPL").is_none());
+        assert!(!bus.delete("rustler:quote:AAPL"));
+        assert_eq!(bus.keys("*"), ["rustler:quote:MSFT"]);
+
+        // an expired key starts over
+        publish(
+            &bus,
+            "rustler:quote:AAPL",
+            &[("price", "1")],
+            Some(Duration::ZERO),
+        );
+        publish(
+            &bus,
+            "rustler:quote:AAPL",
+            &[("volume", "10")],
+            Some(Duration::from_secs(60)),
+        );
+        let stored = bus.get("rustler:quote:AAPL").unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored["volume"], "10");
+    }
+
+    #[test]
+    fn publishing_refreshes_the_expiration_of_a_key() {
+        let bus = MemoryBus::new();
+        let ttl = Some(Duration::from_millis(200));
+        publish(&bus, "rustler:quote:AAPL", &[("price", "1")], ttl);
+
+        std::thread::sleep(Duration::from_millis(120));
+        publish(&bus, "rustler:quote:AAPL", &[("price", "2")], ttl);
+
+        // it would have expired without the second publish
+        std::thread::sleep(Duration::from_millis(120));
+        assert_eq!(bus.get("rustler:quote:AAPL").unwrap()["price"], "2");
+
+        // publishing without a ttl keeps the expiration
+        publish(&bus, "rustler:quote:AAPL", &[("price", "3")], None);
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(bus.get("rustler:quote:AAPL").is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use {
+    super::{default_prefix, MemoryBus},
+    crate::bus::{
+        envelope::Stamper, redis::key, ttl, BusMessage, Codec, PrefixedPubSub, PublisherTrait,
+    },
+    eyre::Result,
+    std::time::Duration,
+    tonic::async_trait,
+};
+
+/// 🐎 » in-process bus **Publisher**
+///
+/// allows to push a message or resource to the in-process bus
+#[derive(Clone)]
+pub struct MemoryPublisher<RM: BusMessage> {
+    bus: MemoryBus,
+    key_prefix: String,
+    codec: Codec,
+    stamper: Stamper,
+    ttl: Option<Duration>,
+    resource_type: std::marker::PhantomData<RM>,
+}
+
+impl<RM: BusMessage> PrefixedPubSub for MemoryPublisher<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+}
+
+impl<RM: BusMessage> MemoryPublisher<RM> {
+    /// 🐎 » create a new in-process bus publisher
+    pub fn new(bus: &MemoryBus) -> Self {
+        Self {
+            bus: bus.clone(),
+            key_prefix: default_prefix(),
+            codec: Codec::default(),
+            stamper: Stamper::default(),
+            ttl: ttl::configured::<RM>(),
+            resource_type: std::marker::PhantomData,
+        }
+    }
+
+    /// 🐎 » set the codec the messages are encoded with (defaults to [`Codec::Legacy`])
+    pub fn with_codec(&mut self, codec: Codec) -> &mut Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 🐎 » set the name of the producer written in the envelopes (e.g. the rustler's name)
+    pub fn with_source(&mut self, source: &str) -> &mut Self {
+        self.stamper.set_source(source);
+        self
+    }
+
+    /// 🐎 » wrap the messages in an envelope with their metadata (off by default)
+    pub fn with_envelope(&mut self) -> &mut Self {
+        self.stamper.enable();
+        self
+    }
+
+    /// 🐎 » expire the fields stored for each key after `ttl` without new messages (the
+    /// expiration is refreshed on every publish)
+    ///
+    /// defaults to the ttl configured for the topic of the messages (see [`ttl::configured`])
+    pub fn with_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// stores the fields of the message and sends it to the subscribers
+    fn send(&mut self, value: RM, trace_id: Option<String>) -> Result<()> {
+        let bus_key = value.to_bus_key();
+        let payload = self.stamper.seal(&value, self.codec.encode(&value)?, trace_id)?;
+        let channel = key(self.get_prefix(), bus_key);
+        self.bus.publish(channel, value.to_bus_val(), payload, self.ttl);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> PublisherTrait<RM> for MemoryPublisher<RM> {
+    /// 🐎 » publish a message to the bus
+    async fn publish(&mut self, value: RM) -> Result<()> {
+        self.send(value, None)
+    }
+
+    /// 🐎 » publish a message to the bus, setting the trace id of its envelope
+    async fn publish_traced(&mut self, value: RM, trace_id: &str) -> Result<()> {
+        self.send(value, Some(trace_id.to_string()))
+    }
+}
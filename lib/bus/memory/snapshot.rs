@@ -0,0 +1,205 @@
+use {
+    super::{default_prefix, MemoryBus},
+    crate::bus::{redis::key, BusMessage, PrefixedPubSub, SnapshotTrait},
+    eyre::Result,
+    lool::logger::warn,
+    tonic::async_trait,
+};
+
+/// 🐎 » in-process bus **Snapshot**
+///
+/// reads the last values published to the in-process bus
+#[derive(Clone)]
+pub struct MemorySnapshot<RM: BusMessage> {
+    bus: MemoryBus,
+    key_prefix: String,
+    resource_type: std::marker::PhantomData<RM>,
+}
+
+impl<RM: BusMessage> PrefixedPubSub for MemorySnapshot<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+}
+
+impl<RM: BusMessage> MemorySnapshot<RM> {
+    /// 🐎 » create a new in-process bus snapshot
+    pub fn new(bus: &MemoryBus) -> Self {
+        Self {
+            bus: bus.clone(),
+            key_prefix: default_prefix(),
+            resource_type: std::marker::PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> SnapshotTrait<RM> for MemorySnapshot<RM> {
+    async fn get(&mut self, bus_key: &str) -> Result<Option<RM>> {
+        match self.bus.get(&key(self.get_prefix(), bus_key)) {
+            Some(fields) => RM::from_bus_val(&fields).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_many(&mut self, pattern: &str) -> Result<Vec<RM>> {
+        let mut keys = self.bus.keys(&key(self.get_prefix(), pattern));
+        keys.sort();
+
+        let values = keys
+            .iter()
+            .filter_map(|hash_key| {
+                let fields = self.bus.get(hash_key)?;
+
+                match RM::from_bus_val(&fields) {
+                    Ok(value) => Some(value),
+                    Err(e) => {
+                        warn!("Skipping undecodable value of '{}': {}", hash_key, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            bus::{memory, PublisherTrait},
+            candles::{Candle, Resolution},
+            rustlers::{stats::SessionStats, MarketHourType, Quote},
+        },
+    };
+
+    fn quote(market: &str, symbol: &str, price: f64) -> Quote {
+        Quote {
+            id: symbol.into(),
+            market: market.into(),
+            price,
+            change_percent: 1.5,
+            time: 1_000,
+            market_hours: MarketHourType::Regular,
+            source: Some("rustler".into()),
+            volume: Some(100.0),
+            stats: None,
+        }
+    }
+
+    fn prices(quotes: &[Quote]) -> Vec<(&str, f64)> {
+        quotes.iter().map(|q| (q.id.as_str(), q.price)).collect()
+    }
+
+    #[tokio::test]
+    async fn get_returns_the_last_value_of_a_key() -> Result<()> {
+        let bus = MemoryBus::new();
+        let mut publisher = memory::publisher::<Quote>(&bus);
+        let mut snapshot = memory::snapshot::<Quote>(&bus);
+
+        assert!(snapshot.get("quote:NASDAQ:AAPL").await?.is_none());
+
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        publisher.publish(quote("NASDAQ", "AAPL", 101.0)).await?;
+
+        let last = snapshot.get("quote:NASDAQ:AAPL").await?.unwrap();
+        assert_eq!((last.id.as_str(), last.market.as_str()), ("AAPL", "NASDAQ"));
+        assert_eq!(
+            (last.price, last.change_percent, last.time),
+            (101.0, 1.5, 1_000)
+        );
+        assert_eq!(last.source.as_deref(), Some("rustler"));
+        assert_eq!(last.volume, Some(100.0));
+        assert!(last.stats.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_returns_the_session_stats_of_a_quote() -> Result<()> {
+        let bus = MemoryBus::new();
+        let mut publisher = memory::publisher::<Quote>(&bus);
+        let mut snapshot = memory::snapshot::<Quote>(&bus);
+
+        let mut published = quote("NASDAQ", "AAPL", 100.0);
+        published.volume = None;
+        published.stats = Some(SessionStats::from(&published));
+        publisher.publish(published.clone()).await?;
+
+        let last = snapshot.get("quote:NASDAQ:AAPL").await?.unwrap();
+        assert_eq!(last.stats, published.stats);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_many_returns_the_values_matching_a_pattern() -> Result<()> {
+        let bus = MemoryBus::new();
+        let mut publisher = memory::publisher::<Quote>(&bus);
+        let mut snapshot = memory::snapshot::<Quote>(&bus);
+
+        publisher.publish(quote("NASDAQ", "MSFT", 300.0)).await?;
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        publisher.publish(quote("NYSE", "IBM", 200.0)).await?;
+        publisher.publish(quote("NASDAQ", "AAPL", 101.0)).await?;
+
+        let nasdaq = snapshot.get_many("quote:NASDAQ:*").await?;
+        assert_eq!(prices(&nasdaq), [("AAPL", 101.0), ("MSFT", 300.0)]);
+        assert_eq!(snapshot.get_many("quote:*").await?.len(), 3);
+        assert!(snapshot.get_many("quote:BCBA:*").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn values_are_read_with_the_prefix_of_the_snapshot() -> Result<()> {
+        let bus = MemoryBus::new();
+        let mut publisher = memory::publisher::<Quote>(&bus);
+        publisher.set_prefix("other");
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+
+        let mut snapshot = memory::snapshot::<Quote>(&bus);
+        assert!(snapshot.get("quote:NASDAQ:AAPL").await?.is_none());
+        assert!(snapshot.get_many("quote:*").await?.is_empty());
+
+        snapshot.set_prefix("other");
+        assert!(snapshot.get("quote:NASDAQ:AAPL").await?.is_some());
+        assert_eq!(snapshot.get_many("quote:*").await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_many_skips_undecodable_values() -> Result<()> {
+        let bus = MemoryBus::new();
+        memory::publisher::<Quote>(&bus).publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        memory::publisher::<Candle>(&bus)
+            .publish(Candle {
+                market: "NASDAQ".into(),
+                symbol: "AAPL".into(),
+                resolution: Resolution::M1,
+                start: 0,
+                open: 1.0,
+                high: 1.0,
+                low: 1.0,
+                close: 1.0,
+                volume: None,
+                closed: false,
+            })
+            .await?;
+
+        let mut snapshot = memory::snapshot::<Quote>(&bus);
+        assert_eq!(prices(&snapshot.get_many("*").await?), [("AAPL", 100.0)]);
+        assert!(snapshot.get("candle:1m:NASDAQ:AAPL").await.is_err());
+
+        Ok(())
+    }
+}
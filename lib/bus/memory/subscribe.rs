@@ -0,0 +1,393 @@
+use {
+    super::{default_prefix, matches, MemoryBus},
+    crate::bus::{
+        decode::Decoder,
+        envelope::GapDetector,
+        redis::{
+            key,
+            stream::{LagPolicy, SourceStream, StreamEvent, DEFAULT_CAPACITY},
+        },
+        BusMessage, DeadLetter, DeadLetterPublisher, Enveloped, Gap, PrefixedPubSub,
+        PublisherTrait, SubscriberTrait,
+    },
+    eyre::Result,
+    futures::{Stream, StreamExt},
+    lool::{
+        fail,
+        logger::{info, warn},
+        s,
+    },
+    std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio::{
+        select,
+        sync::broadcast::{error::RecvError, Receiver, Sender},
+        time::{interval_at, Instant},
+    },
+    tokio_util::sync::CancellationToken,
+    tonic::async_trait,
+};
+
+/// interval between the checks of whether the subscriber's streams are still consumed
+const IDLE_CHECK: Duration = Duration::from_secs(5);
+
+/// 🐎 » in-process bus **Subscriber**
+///
+/// allows to subscribe to a key pattern and receive messages from the in-process bus
+///
+/// the streams outlive the subscriber: it stops reading once it's dropped and none of its streams
+/// are consumed anymore
+pub struct MemorySubscriber<RM: BusMessage> {
+    bus: MemoryBus,
+    key_prefix: String,
+    pattern: String,
+    decode_errors: Arc<AtomicU64>,
+    dead_letter: Option<DeadLetterPublisher>,
+    gaps: GapDetector,
+    buffer: usize,
+    lag_policy: LagPolicy,
+    /// cancelled when the subscriber is dropped
+    dropped: CancellationToken,
+    pub source_stream: Option<SourceStream<Enveloped<RM>>>,
+}
+
+impl<RM: BusMessage> Drop for MemorySubscriber<RM> {
+    fn drop(&mut self) {
+        self.dropped.cancel();
+    }
+}
+
+impl<RM: BusMessage> PrefixedPubSub for MemorySubscriber<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = s!(prefix);
+        self
+    }
+}
+
+impl<RM: BusMessage> MemorySubscriber<RM> {
+    /// 🐎 » create a new in-process bus subscriber
+    pub fn new(bus: &MemoryBus) -> Self {
+        Self {
+            bus: bus.clone(),
+            key_prefix: default_prefix(),
+            pattern: s!("*"),
+            decode_errors: Arc::new(AtomicU64::new(0)),
+            dead_letter: None,
+            gaps: GapDetector::default(),
+            buffer: DEFAULT_CAPACITY,
+            lag_policy: LagPolicy::default(),
+            dropped: CancellationToken::new(),
+            source_stream: None,
+        }
+    }
+
+    /// 🐎 » set the pattern to subscribe to
+    pub fn with_pattern(&mut self, pattern: &str) -> &mut Self {
+        self.pattern = s!(pattern);
+        self
+    }
+
+    /// 🐎 » republish the messages that can't be decoded as [`DeadLetter`]s with the given
+    /// publisher (e.g. `bus::memory::publisher::<DeadLetter>(&bus)`)
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_dead_letter<P>(&mut self, publisher: P) -> &mut Self
+    where
+        P: PublisherTrait<DeadLetter> + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Box::new(publisher));
+        self
+    }
+
+    /// 🐎 » returns the number of messages skipped because they couldn't be decoded
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// 🐎 » returns a receiver of the ranges of messages missed by the subscriber, detected from
+    /// the sequence numbers of their envelopes
+    pub fn gaps(&self) -> Receiver<Gap> {
+        self.gaps.receiver()
+    }
+
+    /// 🐎 » returns the number of messages missed by the subscriber
+    pub fn missed_messages(&self) -> u64 {
+        self.gaps.missed()
+    }
+
+    /// 🐎 » set the number of messages buffered for the slowest consumer of the subscriber's
+    /// streams (defaults to [`DEFAULT_CAPACITY`])
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_buffer(&mut self, capacity: usize) -> &mut Self {
+        self.buffer = capacity;
+        self
+    }
+
+    /// 🐎 » set what the subscriber's streams do when their consumer falls behind and the
+    /// oldest buffered messages are overwritten (defaults to [`LagPolicy::DropOldest`])
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_lag_policy(&mut self, policy: LagPolicy) -> &mut Self {
+        self.lag_policy = policy;
+        self
+    }
+
+    /// 🐎 » returns the number of messages missed by the lagging consumers of the subscriber
+    pub fn lagged_messages(&self) -> u64 {
+        self.source_stream.as_ref().map(SourceStream::lagged).unwrap_or_default()
+    }
+
+    /// 🐎 » returns the pattern used to subscribe to the bus, including the prefix if set
+    pub fn get_pattern(&self) -> String {
+        key(self.get_prefix(), self.pattern.clone())
+    }
+
+    /// subscribe to the bus feed
+    ///
+    /// the bus is never closed, so the feed is read until the subscriber is dropped and its
+    /// streams are gone
+    fn start_streaming(&mut self) {
+        let stream = self.source_stream.get_or_insert_with(|| {
+            SourceStream::with_capacity(self.buffer).with_lag_policy(self.lag_policy)
+        });
+        let Some(sender) = stream.sender() else {
+            return;
+        };
+
+        let lagged = stream.lagged_counter();
+        let pattern = key(&self.key_prefix, &self.pattern);
+        let mut receiver = self.bus.receiver();
+        let dropped = self.dropped.clone();
+        let mut decoder = Decoder::<RM>::new(
+            self.key_prefix.clone(),
+            self.decode_errors.clone(),
+            self.dead_letter.take(),
+        )
+        .with_gaps(self.gaps.clone());
+
+        // nobody can read the messages once the subscriber and all its streams are gone
+        let done = {
+            let dropped = dropped.clone();
+            move |sender: &Sender<_>| dropped.is_cancelled() && sender.receiver_count() == 0
+        };
+
+        tokio::spawn(async move {
+            let mut check = interval_at(Instant::now() + IDLE_CHECK, IDLE_CHECK);
+
+            loop {
+                let received = select! {
+                    received = receiver.recv() => received,
+                    _ = dropped.cancelled(), if !dropped.is_cancelled() => {
+                        if done(&sender) {
+                            break;
+                        }
+                        continue;
+                    }
+                    _ = check.tick() => {
+                        if done(&sender) {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                if done(&sender) {
+                    break;
+                }
+
+                let envelope = match received {
+                    Ok(envelope) => envelope,
+                    Err(RecvError::Lagged(skipped)) => {
+                        lagged.fetch_add(skipped, Ordering::Relaxed);
+                        warn!(
+                            "Subscriber of '{}' lagged, {} messages lost",
+                            pattern, skipped
+                        );
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !matches(&pattern, &envelope.channel) {
+                    continue;
+                }
+
+                if let Some(message) = decoder.decode(&envelope.channel, envelope.payload).await {
+                    let _ = sender.send(message);
+                }
+            }
+
+            info!("Subscriber of '{}' stopped", pattern);
+        });
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> SubscriberTrait<RM> for MemorySubscriber<RM> {
+    async fn stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = RM> + Send + 'static>>> {
+        Ok(Box::pin(
+            self.envelopes().await?.map(|enveloped| enveloped.message),
+        ))
+    }
+
+    async fn envelopes(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Enveloped<RM>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming();
+        }
+
+        match self.source_stream.as_ref() {
+            Some(stream) => stream.subscribe(),
+            None => fail!("Could not start streaming messages from the in-process bus"),
+        }
+    }
+
+    async fn events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEvent<Enveloped<RM>>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming();
+        }
+
+        match self.source_stream.as_ref() {
+            Some(stream) => stream.subscribe_events(),
+            None => fail!("Could not start streaming messages from the in-process bus"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            bus::memory,
+            rustlers::{MarketHourType, Quote},
+        },
+        tokio::time::timeout,
+    };
+
+    fn quote(market: &str, symbol: &str, price: f64) -> Quote {
+        Quote {
+            id: symbol.into(),
+            market: market.into(),
+            price,
+            change_percent: 1.5,
+            time: 1_000,
+            market_hours: MarketHourType::Regular,
+            source: Some("rustler".into()),
+            volume: None,
+            stats: None,
+        }
+    }
+
+    async fn next<S: Stream<Item = Quote> + Unpin>(stream: &mut S) -> Option<(String, f64)> {
+        let quote = timeout(Duration::from_secs(1), stream.next()).await.ok()??;
+        Some((quote.id, quote.price))
+    }
+
+    /// waits for the tasks reading the bus to stop
+    async fn stopped(bus: &MemoryBus) -> bool {
+        for _ in 0..100 {
+            if bus.sender.receiver_count() == 0 {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        false
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_the_messages_matching_their_pattern() -> Result<()> {
+        let bus = MemoryBus::new();
+        let (mut publisher, mut subscriber) = memory::pubsub::<Quote>(&bus);
+        subscriber.with_pattern("quote:NASDAQ:*");
+        let mut quotes = subscriber.stream().await?;
+
+        publisher.publish(quote("NYSE", "IBM", 200.0)).await?;
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        publisher.publish(quote("NASDAQ", "MSFT", 300.0)).await?;
+
+        assert_eq!(next(&mut quotes).await, Some(("AAPL".into(), 100.0)));
+        assert_eq!(next(&mut quotes).await, Some(("MSFT".into(), 300.0)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn subscribers_only_receive_the_messages_of_their_prefix() -> Result<()> {
+        let bus = MemoryBus::new();
+        let mut other = memory::publisher::<Quote>(&bus);
+        other.set_prefix("other");
+        let mut publisher = memory::publisher::<Quote>(&bus);
+
+        let mut subscriber = memory::subscriber::<Quote>(&bus);
+        subscriber.set_prefix("other").with_pattern("quote:*");
+        assert_eq!(subscriber.get_pattern(), "other:quote:*");
+        let mut quotes = subscriber.stream().await?;
+
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        other.publish(quote("NASDAQ", "AAPL", 101.0)).await?;
+
+        assert_eq!(next(&mut quotes).await, Some(("AAPL".into(), 101.0)));
+        assert_eq!(next(&mut quotes).await, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn subscribers_count_the_messages_lost_by_lagging_behind_the_bus() -> Result<()> {
+        let bus = MemoryBus::with_capacity(2);
+        let (mut publisher, mut subscriber) = memory::pubsub::<Quote>(&bus);
+        let mut quotes = subscriber.stream().await?;
+
+        // the subscriber doesn't read the bus until the test yields
+        for i in 0..5 {
+            publisher.publish(quote("NASDAQ", "AAPL", i as f64)).await?;
+        }
+
+        assert_eq!(next(&mut quotes).await, Some(("AAPL".into(), 3.0)));
+        assert_eq!(next(&mut quotes).await, Some(("AAPL".into(), 4.0)));
+        assert_eq!(subscriber.lagged_messages(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn subscribers_stop_once_dropped_and_their_streams_are_gone() -> Result<()> {
+        let bus = MemoryBus::new();
+        let (mut publisher, mut subscriber) = memory::pubsub::<Quote>(&bus);
+        let mut quotes = subscriber.stream().await?;
+
+        // the streams outlive the subscriber
+        drop(subscriber);
+        publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await?;
+        assert_eq!(next(&mut quotes).await, Some(("AAPL".into(), 100.0)));
+        assert_eq!(bus.sender.receiver_count(), 1);
+
+        drop(quotes);
+        publisher.publish(quote("NASDAQ", "AAPL", 101.0)).await?;
+        assert!(stopped(&bus).await);
+
+        // and without any traffic on the bus
+        let mut subscriber = memory::subscriber::<Quote>(&bus);
+        drop(subscriber.stream().await?);
+        drop(subscriber);
+        assert!(stopped(&bus).await);
+
+        Ok(())
+    }
+}
@@ -1,18 +1,38 @@
 use {
     eyre::Result,
     futures::Stream,
-    redis::stream::StreamMsg,
-    std::{fmt::Debug, pin::Pin},
+    redis::stream::{StreamEvent, StreamMsg},
+    serde::{de::DeserializeOwned, Serialize},
+    std::{collections::HashMap, fmt::Debug, pin::Pin},
     tonic::async_trait,
 };
 
+pub mod codec;
+pub mod decode;
+pub mod envelope;
+pub mod memory;
 pub mod redis;
+pub mod redis_streams;
+pub mod ttl;
+
+pub use codec::Codec;
+pub use decode::{BusFields, DeadLetter, DeadLetterPublisher, MessageFields};
+pub use envelope::{Enveloped, Gap, Metadata};
 
 /// 🐎 » represents a value that can be serialized to a bus value
 pub trait ToBusVal {
     fn to_bus_val(&self) -> Vec<(String, String)>;
 }
 
+/// 🐎 » represents a value that can be deserialized from its bus value (the fields stored in the
+/// hash of its key)
+pub trait FromBusVal {
+    /// 🐎 » decodes the fields of a hash, failing if they're not the expected ones
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self>
+    where
+        Self: Sized;
+}
+
 /// 🐎 » represents a value that can be serialized to a bus key
 pub trait ToBusKey {
     fn to_bus_key(&self) -> String;
@@ -21,13 +41,76 @@ pub trait ToBusKey {
 /// 🐎 » represents a value that can be serialized to and from a bus message
 pub trait ToFromBusMessage {
     fn as_message(&self) -> String;
-    fn from_message<T: AsRef<str>>(msg: T) -> Self;
+
+    /// 🐎 » decodes a message, failing if it's not in the expected format
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self>
+    where
+        Self: Sized;
+}
+
+/// 🐎 » represents a value that can be converted to and from its protobuf message (see
+/// `bus.proto`)
+pub trait ToFromProto {
+    type Proto: prost::Message + Default;
+
+    fn to_proto(&self) -> Self::Proto;
+
+    /// 🐎 » converts a protobuf message, failing if one of its fields is invalid
+    fn from_proto(proto: Self::Proto) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 /// 🐎 » supertrait combining all bus object traits + debug + send + sync + 'static
 pub trait BusMessage:
-    ToBusVal + ToBusKey + ToFromBusMessage + Debug + PartialEq + StreamMsg + 'static
+    ToBusVal
+    + FromBusVal
+    + ToBusKey
+    + ToFromBusMessage
+    + ToFromProto
+    + Serialize
+    + DeserializeOwned
+    + Debug
+    + PartialEq
+    + StreamMsg
+    + 'static
 {
+    /// 🐎 » returns the name of the producer of the message (e.g. the rustler of a quote), which
+    /// is written in its envelope instead of the publisher's source
+    fn source(&self) -> Option<&str> {
+        None
+    }
+
+    /// 🐎 » returns the time of the message (unix timestamp in milliseconds), used to drop the
+    /// updates older than the last published one
+    fn message_time(&self) -> Option<i64> {
+        None
+    }
+
+    /// 🐎 » returns the topic of the messages (the first segment of their keys, e.g. `quote`),
+    /// used to look up the ttl configured for their hashes (see [`ttl::configured`])
+    fn topic() -> Option<&'static str>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// 🐎 » represents a pub or sub handler that can be prefixed
+pub trait PrefixedPubSub {
+    fn get_prefix(&self) -> String;
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self;
+
+    fn with_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.set_prefix(prefix);
+        self
+    }
+
+    fn without_prefix(&mut self) -> &mut Self {
+        self.set_prefix("");
+        self
+    }
 }
 
 /// 🐎 » trait for bus `Publisher`s
@@ -35,6 +118,21 @@ pub trait BusMessage:
 pub trait PublisherTrait<RM: BusMessage> {
     /// 🐎 » publish a message to the bus
     async fn publish(&mut self, value: RM) -> Result<()>;
+
+    /// 🐎 » publish a message to the bus, setting the trace id of its envelope
+    async fn publish_traced(&mut self, value: RM, trace_id: &str) -> Result<()>;
+
+    /// 🐎 » publish several messages to the bus
+    ///
+    /// publishers that support it send them in a single round trip; the default implementation
+    /// publishes them one by one
+    async fn publish_batch(&mut self, values: Vec<RM>) -> Result<()> {
+        for value in values {
+            self.publish(value).await?;
+        }
+
+        Ok(())
+    }
 }
 
 /// 🐎 » trait for bus **Publisher**s
@@ -44,4 +142,85 @@ pub trait SubscriberTrait<RM: BusMessage> {
     ///
     /// returns an `Observable` stream of messages from the redis bus
     async fn stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = RM> + Send + 'static>>>;
+
+    /// 🐎 » envelopes
+    ///
+    /// returns an `Observable` stream of messages along with the metadata of their envelopes
+    async fn envelopes(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Enveloped<RM>> + Send + 'static>>>;
+
+    /// 🐎 » events
+    ///
+    /// like [`SubscriberTrait::envelopes`], but the stream also yields a `Lagged` event with the
+    /// number of messages missed every time the consumer falls behind, regardless of the lag
+    /// policy of the subscriber
+    async fn events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEvent<Enveloped<RM>>> + Send + 'static>>>;
+}
+
+/// 🐎 » trait for bus **Snapshot**s, which read the last value published with each key (the
+/// fields stored in its hash), so consumers can initialize their state before streaming updates
+#[async_trait]
+pub trait SnapshotTrait<RM: BusMessage> {
+    /// 🐎 » returns the last value published with the given bus key (e.g. `quote:NASDAQ:AAPL`),
+    /// or `None` if there's none
+    async fn get(&mut self, key: &str) -> Result<Option<RM>>;
+
+    /// 🐎 » returns the last values published with the bus keys matching the given pattern
+    /// (e.g. `quote:NASDAQ:*`)
+    ///
+    /// values that can't be decoded are skipped
+    async fn get_many(&mut self, pattern: &str) -> Result<Vec<RM>>;
+}
+
+/// 🐎 » position in a durable stream of messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamPosition {
+    /// the first message still stored
+    Beginning,
+    /// the messages published from now on
+    Latest,
+    /// the message with the given id (e.g. `1729252800000-0`)
+    Id(String),
+    /// the first message published at or after the given time (unix timestamp in milliseconds)
+    Time(i64),
+}
+
+/// 🐎 » a message of a durable stream, along with the id needed to acknowledge it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry<RM: BusMessage> {
+    pub id: String,
+    pub message: RM,
+    /// metadata of the message's envelope, if it was published with one
+    pub meta: Option<Metadata>,
+}
+
+impl<RM: BusMessage> StreamMsg for StreamEntry<RM> {}
+
+/// 🐎 » trait for bus **Subscriber**s backed by a durable stream, which can acknowledge the
+/// messages they process and replay the stored ones
+#[async_trait]
+pub trait StreamSubscriberTrait<RM: BusMessage>: SubscriberTrait<RM> {
+    /// 🐎 » entries
+    ///
+    /// returns a stream of entries that must be acknowledged with [`Self::ack`] once they're
+    /// processed; the entries that aren't acknowledged are delivered again when the subscriber
+    /// restarts
+    async fn entries(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEntry<RM>> + Send + 'static>>>;
+
+    /// 🐎 » acknowledges the entries with the given ids
+    async fn ack(&mut self, ids: &[String]) -> Result<()>;
+
+    /// 🐎 » replay
+    ///
+    /// returns a stream of the stored entries from the given position up to the last one
+    /// published when it's called; replayed entries don't need to be acknowledged
+    async fn replay(
+        &mut self,
+        from: StreamPosition,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEntry<RM>> + Send + 'static>>>;
 }
@@ -1,6 +1,14 @@
-use {super::BusMessage, eyre::Result, redis::Client};
+use {
+    super::BusMessage,
+    eyre::Result,
+    redis::{aio::ConnectionLike, Client},
+};
+
+pub use super::PrefixedPubSub;
 
 pub mod publish;
+pub mod reconnect;
+pub mod snapshot;
 pub mod stream;
 pub mod subscribe;
 
@@ -17,20 +25,42 @@ pub(crate) fn key<T: AsRef<str>, K: AsRef<str>>(prefix: T, key: K) -> String {
     }
 }
 
-/// represents a pub or sub handler that can be prefixed
-pub trait PrefixedPubSub {
-    fn get_prefix(&self) -> String;
-    fn set_prefix(&mut self, prefix: &str) -> &mut Self;
+/// returns the hash keys matching a pattern (including the prefix), scanning `count` keys per
+/// round trip
+pub(crate) async fn scan_hashes<C: ConnectionLike>(
+    conn: &mut C,
+    pattern: &str,
+    count: usize,
+) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut cursor = 0u64;
 
-    fn with_prefix(&mut self, prefix: &str) -> &mut Self {
-        self.set_prefix(prefix);
-        self
-    }
+    loop {
+        // only hashes: the streams of the redis streams backend share the prefix
+        let (next, page): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(count)
+            .arg("TYPE")
+            .arg("hash")
+            .query_async(conn)
+            .await?;
 
-    fn without_prefix(&mut self) -> &mut Self {
-        self.set_prefix("");
-        self
+        keys.extend(page);
+        if next == 0 {
+            break;
+        }
+
+        cursor = next;
     }
+
+    // scan can return a key more than once
+    keys.sort();
+    keys.dedup();
+
+    Ok(keys)
 }
 
 /// 🐎 » represents a an entity that can provide a redis client
@@ -85,6 +115,19 @@ pub async fn subscriber<RM: BusMessage, RC: RedisClient>(
     subscribe::RedisSubscriber::new(redis).await
 }
 
+/// 🐎 » **snapshot**: create bus snapshot, to read the last published values
+///
+/// **Arguments**
+/// - `redis` - a redis client or a redis connection string
+///
+/// **Returns**
+/// - a new `Snapshot` instance
+pub async fn snapshot<RM: BusMessage, RC: RedisClient>(
+    redis: &RC,
+) -> Result<snapshot::RedisSnapshot<RM>> {
+    snapshot::RedisSnapshot::new(redis).await
+}
+
 /// 🐎 » **pubsub*: create bus publisher and subscriber
 ///
 /// **Arguments**
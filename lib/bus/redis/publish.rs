@@ -1,18 +1,77 @@
 use {
     super::{key, BusMessage, PrefixedPubSub, RedisClient, KEY_PREFIX},
-    crate::bus::PublisherTrait,
+    crate::bus::{envelope::Stamper, ttl, Codec, PublisherTrait},
     eyre::Result,
-    redis::{aio::MultiplexedConnection, AsyncCommands},
+    lool::logger::debug,
+    redis::{aio::ConnectionManager, ErrorKind, Script},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
     tonic::async_trait,
 };
 
+/// sets the hash of a message and publishes it to its channel, atomically
+///
+/// `KEYS[1]`: the key (and channel) of the message
+/// `ARGV[1]`: the payload
+/// `ARGV[2]`: the time of the message (empty if it has none), stored in the `_time` field
+/// `ARGV[3]`: `1` to drop the message if the stored one is newer
+/// `ARGV[4]`: time to live of the hash in milliseconds (`0` to keep it forever)
+/// `ARGV[5..]`: the fields of the hash
+///
+/// returns `1` if the message was published and `0` if it was dropped
+const PUBLISH_SCRIPT: &str = r#"
+local time = ARGV[2]
+
+if ARGV[3] == '1' and time ~= '' then
+    local stored = redis.call('HGET', KEYS[1], '_time')
+    if stored and tonumber(stored) > tonumber(time) then
+        return 0
+    end
+end
+
+if #ARGV > 4 then
+    redis.call('HSET', KEYS[1], unpack(ARGV, 5))
+end
+
+if time ~= '' then
+    redis.call('HSET', KEYS[1], '_time', time)
+end
+
+local ttl = tonumber(ARGV[4])
+if ttl > 0 then
+    redis.call('PEXPIRE', KEYS[1], ttl)
+end
+
+redis.call('PUBLISH', KEYS[1], ARGV[1])
+return 1
+"#;
+
 /// 🐎 » bus **Publisher**
 ///
 /// allows to push a message or resource to the bus
+///
+/// the connection is restored automatically when it's lost (e.g. when redis restarts); the
+/// messages published while it's down fail
+///
+/// the hash of a message is set and the message is published atomically, by a lua script, so
+/// readers never see one without the other. The script is loaded when the publisher is created,
+/// and again when redis loses it (e.g. after a restart)
 #[derive(Clone)]
 pub struct RedisPublisher<RM: BusMessage> {
-    conn: MultiplexedConnection,
+    conn: ConnectionManager,
     key_prefix: String,
+    codec: Codec,
+    stamper: Stamper,
+    script: Script,
+    stale_check: bool,
+    stale: Arc<AtomicU64>,
+    ttl: Option<Duration>,
     resource_type: std::marker::PhantomData<RM>,
 }
 
@@ -34,26 +93,180 @@ impl<RM: BusMessage> RedisPublisher<RM> {
         RC: RedisClient,
     {
         let redis = redis.get_client()?;
-        let conn = redis.get_multiplexed_tokio_connection().await?;
+        let mut conn = ConnectionManager::new(redis).await?;
+        load_script(&mut conn).await?;
 
         Ok(Self {
             conn,
             key_prefix: KEY_PREFIX.to_string(),
+            codec: Codec::default(),
+            stamper: Stamper::default(),
+            script: Script::new(PUBLISH_SCRIPT),
+            stale_check: false,
+            stale: Arc::new(AtomicU64::new(0)),
+            ttl: ttl::configured::<RM>(),
             resource_type: std::marker::PhantomData,
         })
     }
+
+    /// 🐎 » set the codec the messages are encoded with (defaults to [`Codec::Legacy`])
+    pub fn with_codec(&mut self, codec: Codec) -> &mut Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 🐎 » set the name of the producer written in the envelopes (e.g. the rustler's name)
+    pub fn with_source(&mut self, source: &str) -> &mut Self {
+        self.stamper.set_source(source);
+        self
+    }
+
+    /// 🐎 » wrap the messages in an envelope with their metadata (see [`Metadata`]), which lets
+    /// subscribers detect the messages they missed
+    ///
+    /// messages are published without one by default, since consumers that split the payloads
+    /// by `¦` can't read enveloped messages
+    ///
+    /// [`Metadata`]: crate::bus::Metadata
+    pub fn with_envelope(&mut self) -> &mut Self {
+        self.stamper.enable();
+        self
+    }
+
+    /// 🐎 » drop the messages older than the last one published with the same key (see
+    /// [`BusMessage::message_time`]), so late updates don't overwrite newer ones
+    ///
+    /// the stored times are read before the messages are stamped (one more round trip per
+    /// publish), so the dropped ones don't take a sequence number and subscribers don't see them
+    /// as gaps. The script checks them again, in case another publisher updated the same keys
+    pub fn with_stale_check(&mut self, enabled: bool) -> &mut Self {
+        self.stale_check = enabled;
+        self
+    }
+
+    /// 🐎 » expire the hash of each key after `ttl` without new messages (the expiration is
+    /// refreshed on every publish), so the last values of inactive keys don't look current
+    ///
+    /// defaults to the ttl configured for the topic of the messages (see [`ttl::configured`])
+    pub fn with_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// 🐎 » returns the number of messages dropped because they were older than the stored ones
+    pub fn stale_messages(&self) -> u64 {
+        self.stale.load(Ordering::Relaxed)
+    }
+
+    /// sets the hashes of the messages and publishes them to their channels, in a single round
+    /// trip
+    async fn send(&mut self, values: Vec<(RM, Option<String>)>) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let values = match self.stale_check {
+            true => self.drop_stale(values).await?,
+            false => values,
+        };
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let ttl = self.ttl.map(|ttl| ttl.as_millis() as u64).unwrap_or_default();
+        let mut pipe = redis::pipe();
+        for (value, trace_id) in &values {
+            let payload = self.stamper.seal(value, self.codec.encode(value)?, trace_id.clone())?;
+            let time = value.message_time().map(|t| t.to_string()).unwrap_or_default();
+
+            pipe.cmd("EVALSHA")
+                .arg(self.script.get_hash())
+                .arg(1)
+                .arg(key(self.get_prefix(), value.to_bus_key()))
+                .arg(payload)
+                .arg(time)
+                .arg(if self.stale_check { "1" } else { "0" })
+                .arg(ttl)
+                .arg(value.to_bus_val());
+        }
+
+        let published: Vec<i64> = match pipe.query_async(&mut self.conn).await {
+            Ok(published) => published,
+            // redis restarted (or its script cache was flushed), so the script is loaded again
+            // and the batch is retried once. None of its commands ran, since they all call the
+            // missing script, and its payloads were already stamped, so they keep their sequence
+            Err(e) if e.kind() == ErrorKind::NoScriptError => {
+                load_script(&mut self.conn).await?;
+                pipe.query_async(&mut self.conn).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let stale = published.iter().filter(|p| **p == 0).count();
+        self.count_stale(stale);
+
+        Ok(())
+    }
+
+    /// drops the messages older than the stored ones or than a message of the batch with the
+    /// same key, before they're stamped
+    async fn drop_stale(
+        &mut self,
+        values: Vec<(RM, Option<String>)>,
+    ) -> Result<Vec<(RM, Option<String>)>> {
+        let mut pipe = redis::pipe();
+        for (value, _) in &values {
+            pipe.hget(key(self.get_prefix(), value.to_bus_key()), "_time");
+        }
+        let stored: Vec<Option<i64>> = pipe.query_async(&mut self.conn).await?;
+
+        let count = values.len();
+        let mut newest = HashMap::new();
+        let mut fresh = Vec::with_capacity(values.len());
+        for ((value, trace_id), stored) in values.into_iter().zip(stored) {
+            if let Some(time) = value.message_time() {
+                let newest = newest.entry(value.to_bus_key()).or_insert(stored);
+                if newest.is_some_and(|newest| newest > time) {
+                    continue;
+                }
+                *newest = Some(time);
+            }
+
+            fresh.push((value, trace_id));
+        }
+
+        self.count_stale(count - fresh.len());
+        Ok(fresh)
+    }
+
+    fn count_stale(&self, stale: usize) {
+        if stale > 0 {
+            debug!("Dropped {} stale messages", stale);
+            self.stale.fetch_add(stale as u64, Ordering::Relaxed);
+        }
+    }
 }
 
 #[async_trait]
 impl<RM: BusMessage> PublisherTrait<RM> for RedisPublisher<RM> {
     /// 🐎 » publish a message to the bus
     async fn publish(&mut self, value: RM) -> Result<()> {
-        let obj_key = key(self.get_prefix(), value.to_bus_key());
-        // set hash key
-        () = self.conn.hset_multiple(&obj_key, value.to_bus_val().as_slice()).await?;
+        self.send(vec![(value, None)]).await
+    }
 
-        // publish to the appropriate channel
-        () = self.conn.publish(&obj_key, value.as_message()).await?;
-        Ok(())
+    /// 🐎 » publish a message to the bus, setting the trace id of its envelope
+    async fn publish_traced(&mut self, value: RM, trace_id: &str) -> Result<()> {
+        self.send(vec![(value, Some(trace_id.to_string()))]).await
+    }
+
+    /// 🐎 » publish several messages to the bus in a single round trip
+    async fn publish_batch(&mut self, values: Vec<RM>) -> Result<()> {
+        self.send(values.into_iter().map(|value| (value, None)).collect()).await
     }
 }
+
+/// loads the publish script into the script cache of redis
+async fn load_script(conn: &mut ConnectionManager) -> Result<()> {
+    let _: String = redis::cmd("SCRIPT").arg("LOAD").arg(PUBLISH_SCRIPT).query_async(conn).await?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// 🐎 » connection status of a subscriber, sent through its status channel
+///
+/// messages published between a [`ConnectionStatus::Disconnected`] and the following
+/// [`ConnectionStatus::Reconnected`] are missed by the subscriber
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    /// the subscriber is connected and subscribed to its pattern
+    Connected,
+    /// the connection was lost
+    Disconnected {
+        /// time the connection was lost (unix timestamp in milliseconds)
+        since: i64,
+    },
+    /// a reconnection attempt failed, the next one is made after the backoff delay
+    Retrying { attempt: u32, error: String },
+    /// the connection was restored and the subscriber resubscribed to its pattern
+    Reconnected {
+        /// time the connection was lost (unix timestamp in milliseconds)
+        since: i64,
+        /// time the connection was restored (unix timestamp in milliseconds)
+        until: i64,
+        /// number of attempts it took to reconnect
+        attempts: u32,
+    },
+}
+
+/// #### 🐎 » Backoff
+///
+/// Exponential backoff between reconnection attempts: the delay starts at `min` and doubles
+/// after every failed attempt, up to `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    /// 🐎 » creates a backoff with the given min and max delays
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+            attempt: 0,
+        }
+    }
+
+    /// 🐎 » returns the number of attempts made since the last reset
+    pub fn attempts(&self) -> u32 {
+        self.attempt
+    }
+
+    /// 🐎 » returns the delay before the next attempt
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.min.saturating_mul(2u32.saturating_pow(self.attempt)).min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    /// 🐎 » resets the delay to `min`, once the connection is restored
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
@@ -0,0 +1,97 @@
+use {
+    super::{key, scan_hashes, BusMessage, PrefixedPubSub, RedisClient, KEY_PREFIX},
+    crate::bus::SnapshotTrait,
+    eyre::Result,
+    lool::logger::warn,
+    redis::{aio::ConnectionManager, AsyncCommands},
+    std::collections::HashMap,
+    tonic::async_trait,
+};
+
+/// 🐎 » bus **Snapshot**
+///
+/// reads the last values published to the redis bus from the hashes of their keys
+#[derive(Clone)]
+pub struct RedisSnapshot<RM: BusMessage> {
+    conn: ConnectionManager,
+    key_prefix: String,
+    batch: usize,
+    resource_type: std::marker::PhantomData<RM>,
+}
+
+impl<RM: BusMessage> PrefixedPubSub for RedisSnapshot<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+}
+
+impl<RM: BusMessage> RedisSnapshot<RM> {
+    /// 🐎 » create a new bus snapshot
+    pub async fn new<RC>(redis: &RC) -> Result<Self>
+    where
+        RC: RedisClient,
+    {
+        let redis = redis.get_client()?;
+        let conn = ConnectionManager::new(redis).await?;
+
+        Ok(Self {
+            conn,
+            key_prefix: KEY_PREFIX.to_string(),
+            batch: 500,
+            resource_type: std::marker::PhantomData,
+        })
+    }
+
+    /// 🐎 » set the number of keys scanned and read per round trip (defaults to 500)
+    pub fn with_batch(&mut self, batch: usize) -> &mut Self {
+        self.batch = batch.max(1);
+        self
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> SnapshotTrait<RM> for RedisSnapshot<RM> {
+    async fn get(&mut self, bus_key: &str) -> Result<Option<RM>> {
+        let fields: HashMap<String, String> =
+            self.conn.hgetall(key(self.get_prefix(), bus_key)).await?;
+
+        match fields.is_empty() {
+            true => Ok(None),
+            false => RM::from_bus_val(&fields).map(Some),
+        }
+    }
+
+    async fn get_many(&mut self, pattern: &str) -> Result<Vec<RM>> {
+        let pattern = key(self.get_prefix(), pattern);
+        let keys = scan_hashes(&mut self.conn, &pattern, self.batch).await?;
+        let mut values = Vec::with_capacity(keys.len());
+
+        for chunk in keys.chunks(self.batch) {
+            let mut pipe = redis::pipe();
+            for hash_key in chunk {
+                pipe.hgetall(hash_key);
+            }
+
+            let hashes: Vec<HashMap<String, String>> = pipe.query_async(&mut self.conn).await?;
+
+            for (hash_key, fields) in chunk.iter().zip(hashes) {
+                // the key can be deleted between the scan and the read
+                if fields.is_empty() {
+                    continue;
+                }
+
+                match RM::from_bus_val(&fields) {
+                    Ok(value) => values.push(value),
+                    Err(e) => warn!("Skipping undecodable value of '{}': {}", hash_key, e),
+                }
+            }
+        }
+
+        Ok(values)
+    }
+}
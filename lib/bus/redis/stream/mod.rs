@@ -1,11 +1,20 @@
 use {
     eyre::Result,
     futures::Stream,
-    lool::fail,
-    std::pin::Pin,
-    tokio::sync::broadcast::{self, Sender},
+    lool::{fail, logger::warn},
+    std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    },
+    tokio::sync::broadcast::{self, error::RecvError, Sender},
 };
 
+/// default number of messages buffered for the slowest consumer of a `SourceStream`
+pub const DEFAULT_CAPACITY: usize = 100;
+
 pub trait StreamMsg: Clone + Send + Sync + 'static {}
 
 impl StreamMsg for String {}
@@ -13,9 +22,30 @@ impl StreamMsg for Vec<u8> {}
 impl StreamMsg for serde_json::Value {}
 impl StreamMsg for serde_json::Map<String, serde_json::Value> {}
 
+/// 🐎 » what a stream does when its consumer falls so far behind that the oldest buffered
+/// messages are overwritten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LagPolicy {
+    /// skip the overwritten messages and continue with the oldest one still buffered
+    #[default]
+    DropOldest,
+    /// end the stream
+    Disconnect,
+}
+
+/// 🐎 » an item of a stream of events: a message, or the number of messages a lagging consumer
+/// missed
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent<RM> {
+    Message(RM),
+    Lagged(u64),
+}
+
 // TODO: move this to a separate module, or maybe to the lool library
 pub struct SourceStream<RM: StreamMsg> {
     sender: Option<Sender<RM>>,
+    policy: LagPolicy,
+    lagged: Arc<AtomicU64>,
 }
 
 impl<RM: StreamMsg> Default for SourceStream<RM> {
@@ -27,30 +57,94 @@ impl<RM: StreamMsg> Default for SourceStream<RM> {
 impl<RM: StreamMsg> SourceStream<RM> {
     // Create a new SourceStream with a broadcast channel
     pub fn new() -> Self {
-        let (sender, _) = broadcast::channel(100); // Adjust the buffer size as needed
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // Create a new SourceStream buffering up to `capacity` messages for its slowest consumer
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
         SourceStream {
             sender: Some(sender),
+            policy: LagPolicy::default(),
+            lagged: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    // Set what the streams returned by `subscribe` do when their consumer lags
+    pub fn with_lag_policy(mut self, policy: LagPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn sender(&self) -> Option<Sender<RM>> {
         self.sender.clone()
     }
 
+    // Number of messages missed by the lagging consumers of the stream
+    pub fn lagged(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
+
+    // Counter of the missed messages, for the producers that lag behind their own source
+    pub(crate) fn lagged_counter(&self) -> Arc<AtomicU64> {
+        self.lagged.clone()
+    }
+
     // Subscribe to the stream
     pub fn subscribe(&self) -> Result<Pin<Box<dyn Stream<Item = RM> + Send + 'static>>> {
-        if let Some(sender) = &self.sender {
-            let mut receiver = sender.subscribe();
+        let Some(sender) = &self.sender else {
+            fail!("SourceStream has been consumed");
+        };
+
+        let mut receiver = sender.subscribe();
+        let policy = self.policy;
+        let lagged = self.lagged.clone();
 
-            let stream = async_stream::stream! {
-                while let Ok(item) = receiver.recv().await {
-                    yield item;
+        let stream = async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(item) => yield item,
+                    Err(RecvError::Lagged(skipped)) => {
+                        lagged.fetch_add(skipped, Ordering::Relaxed);
+                        warn!("Stream consumer lagged, {} messages lost", skipped);
+
+                        if policy == LagPolicy::Disconnect {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
                 }
-            };
+            }
+        };
 
-            Ok(Box::pin(stream))
-        } else {
-            fail!("SourceStream has been consumed")
-        }
+        Ok(Box::pin(stream))
+    }
+
+    // Subscribe to the stream, receiving a `Lagged` event every time the consumer lags
+    // (regardless of the lag policy)
+    pub fn subscribe_events(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEvent<RM>> + Send + 'static>>> {
+        let Some(sender) = &self.sender else {
+            fail!("SourceStream has been consumed");
+        };
+
+        let mut receiver = sender.subscribe();
+        let lagged = self.lagged.clone();
+
+        let stream = async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(item) => yield StreamEvent::Message(item),
+                    Err(RecvError::Lagged(skipped)) => {
+                        lagged.fetch_add(skipped, Ordering::Relaxed);
+                        yield StreamEvent::Lagged(skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
     }
 }
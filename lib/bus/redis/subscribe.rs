@@ -1,19 +1,57 @@
 use {
-    super::{key, stream::SourceStream, PrefixedPubSub, RedisClient, KEY_PREFIX},
-    crate::bus::{BusMessage, SubscriberTrait},
+    super::{
+        key,
+        reconnect::{Backoff, ConnectionStatus},
+        stream::{LagPolicy, SourceStream, StreamEvent, DEFAULT_CAPACITY},
+        PrefixedPubSub, RedisClient, KEY_PREFIX,
+    },
+    crate::bus::{
+        decode::Decoder, envelope::GapDetector, BusMessage, DeadLetter, DeadLetterPublisher,
+        Enveloped, Gap, PublisherTrait, SubscriberTrait,
+    },
+    chrono::Utc,
     eyre::Result,
     futures::{Stream, StreamExt},
-    lool::{fail, s},
-    std::pin::Pin,
+    lool::{
+        fail,
+        logger::{info, warn},
+        s,
+    },
+    redis::aio::PubSub,
+    std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio::{
+        select,
+        sync::broadcast::{self, Receiver, Sender},
+        time::{interval_at, timeout, Instant},
+    },
+    tokio_util::sync::CancellationToken,
     tonic::async_trait,
 };
 
-// IDEA: create another version using tokio broadcast channels
-// https://github.com/exein-io/pulsar/blob/99ad35c8d13eaf1a37d7b6a9dcb812a5a1231d00/crates/pulsar-core/src/bus.rs
+/// default interval between the pings of the subscribers' connections
+const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// default time a subscriber waits for a pong before reconnecting
+const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// 🐎 » bus **Subscriber**
 ///
 /// allows to subscribe to a redis key pattern and receive messages from the redis bus
+///
+/// when the connection is lost, the subscriber reconnects with an exponential backoff and
+/// subscribes to its pattern again, reporting it through its status channel (see
+/// [`RedisSubscriber::status`]). The connection is pinged periodically (see
+/// [`RedisSubscriber::with_keepalive`]), so half-open connections are detected as lost too.
+///
+/// the streams outlive the subscriber: it stops reading once it's dropped and none of its streams
+/// are consumed anymore
 pub struct RedisSubscriber<RM: BusMessage> {
     // TODO: replace with storing tokio multiplexed connection like in publish.rs when redis@0.26.0
     //       is released see https://github.com/redis-rs/redis-rs/issues/1137.
@@ -22,7 +60,24 @@ pub struct RedisSubscriber<RM: BusMessage> {
     client: redis::Client,
     key_prefix: String,
     pattern: String,
-    pub source_stream: Option<SourceStream<RM>>,
+    decode_errors: Arc<AtomicU64>,
+    dead_letter: Option<DeadLetterPublisher>,
+    gaps: GapDetector,
+    backoff: Backoff,
+    keepalive: Duration,
+    keepalive_timeout: Duration,
+    buffer: usize,
+    lag_policy: LagPolicy,
+    status: Sender<ConnectionStatus>,
+    /// cancelled when the subscriber is dropped
+    dropped: CancellationToken,
+    pub source_stream: Option<SourceStream<Enveloped<RM>>>,
+}
+
+impl<RM: BusMessage> Drop for RedisSubscriber<RM> {
+    fn drop(&mut self) {
+        self.dropped.cancel();
+    }
 }
 
 impl<RM: BusMessage> PrefixedPubSub for RedisSubscriber<RM> {
@@ -46,6 +101,16 @@ impl<RM: BusMessage> RedisSubscriber<RM> {
             pattern: s!("*"),
             client: redis.get_client()?,
             key_prefix: s!(KEY_PREFIX),
+            decode_errors: Arc::new(AtomicU64::new(0)),
+            dead_letter: None,
+            gaps: GapDetector::default(),
+            backoff: Backoff::default(),
+            keepalive: DEFAULT_KEEPALIVE,
+            keepalive_timeout: DEFAULT_KEEPALIVE_TIMEOUT,
+            buffer: DEFAULT_CAPACITY,
+            lag_policy: LagPolicy::default(),
+            status: broadcast::channel(16).0,
+            dropped: CancellationToken::new(),
             source_stream: None,
         })
     }
@@ -56,48 +121,212 @@ impl<RM: BusMessage> RedisSubscriber<RM> {
         self
     }
 
+    /// 🐎 » republish the messages that can't be decoded as [`DeadLetter`]s with the given
+    /// publisher (e.g. `bus::redis::publisher::<DeadLetter, _>(&redis)`)
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_dead_letter<P>(&mut self, publisher: P) -> &mut Self
+    where
+        P: PublisherTrait<DeadLetter> + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Box::new(publisher));
+        self
+    }
+
+    /// 🐎 » returns the number of messages skipped because they couldn't be decoded
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// 🐎 » returns a receiver of the ranges of messages missed by the subscriber, detected from
+    /// the sequence numbers of their envelopes
+    pub fn gaps(&self) -> Receiver<Gap> {
+        self.gaps.receiver()
+    }
+
+    /// 🐎 » returns the number of messages missed by the subscriber
+    pub fn missed_messages(&self) -> u64 {
+        self.gaps.missed()
+    }
+
+    /// 🐎 » set the min and max delays between reconnection attempts (defaults to 250ms and 30s)
+    pub fn with_backoff(&mut self, min: Duration, max: Duration) -> &mut Self {
+        self.backoff = Backoff::new(min, max);
+        self
+    }
+
+    /// 🐎 » set how often the connection is pinged and how long the pong is awaited before the
+    /// connection is considered lost (defaults to 30s and 5s)
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_keepalive(&mut self, interval: Duration, timeout: Duration) -> &mut Self {
+        self.keepalive = interval;
+        self.keepalive_timeout = timeout;
+        self
+    }
+
+    /// 🐎 » set the number of messages buffered for the slowest consumer of the subscriber's
+    /// streams (defaults to [`DEFAULT_CAPACITY`])
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_buffer(&mut self, capacity: usize) -> &mut Self {
+        self.buffer = capacity;
+        self
+    }
+
+    /// 🐎 » set what the subscriber's streams do when their consumer falls behind and the
+    /// oldest buffered messages are overwritten (defaults to [`LagPolicy::DropOldest`])
+    ///
+    /// it must be set before calling `stream`
+    pub fn with_lag_policy(&mut self, policy: LagPolicy) -> &mut Self {
+        self.lag_policy = policy;
+        self
+    }
+
+    /// 🐎 » returns the number of messages missed by the lagging consumers of the subscriber
+    pub fn lagged_messages(&self) -> u64 {
+        self.source_stream.as_ref().map(SourceStream::lagged).unwrap_or_default()
+    }
+
+    /// 🐎 » returns a receiver of the connection status changes of the subscriber
+    pub fn status(&self) -> Receiver<ConnectionStatus> {
+        self.status.subscribe()
+    }
+
     /// 🐎 » returns the pattern used to subscribe to the redis bus, including the prefix if set
     pub fn get_pattern(&self) -> String {
         key(self.get_prefix(), self.pattern.clone())
     }
 
     /// subscribe to the redis feed
+    ///
+    /// the first connection is made before returning, so its errors are reported to the caller;
+    /// the following ones are retried until the subscriber is dropped and its streams are gone
     async fn start_streaming(&mut self) -> Result<()> {
-        if self.source_stream.is_none() {
-            self.source_stream = Some(SourceStream::new());
-        }
+        let pattern = self.get_pattern();
+        let mut conn = connect(&self.client, &pattern).await?;
+
+        let stream = self.source_stream.get_or_insert_with(|| {
+            SourceStream::with_capacity(self.buffer).with_lag_policy(self.lag_policy)
+        });
+        let Some(sender) = stream.sender() else {
+            fail!("SourceStream has been consumed");
+        };
+
+        let client = self.client.clone();
+        let status = self.status.clone();
+        let dropped = self.dropped.clone();
+        let mut backoff = self.backoff.clone();
+        let (keepalive, keepalive_timeout) = (self.keepalive, self.keepalive_timeout);
+        let mut decoder = Decoder::<RM>::new(
+            self.get_prefix(),
+            self.decode_errors.clone(),
+            self.dead_letter.take(),
+        )
+        .with_gaps(self.gaps.clone());
+
+        let _ = status.send(ConnectionStatus::Connected);
+
+        // nobody can read the messages once the subscriber and all its streams are gone
+        let done = move |sender: &Sender<_>| dropped.is_cancelled() && sender.receiver_count() == 0;
+
+        tokio::spawn(async move {
+            loop {
+                let (mut sink, mut msg_stream) = conn.split();
+                let mut ping = interval_at(Instant::now() + keepalive, keepalive);
 
-        let pattern = self.pattern.clone();
-        let mut conn = self.client.get_async_pubsub().await?;
-        let prefix = self.get_prefix();
+                loop {
+                    let msg = select! {
+                        msg = msg_stream.next() => msg,
+                        _ = ping.tick() => {
+                            if done(&sender) {
+                                return;
+                            }
 
-        if let Some(stream) = self.source_stream.as_mut() {
-            let sender = stream.sender().unwrap();
+                            match timeout(keepalive_timeout, sink.ping::<redis::Value>()).await {
+                                Ok(Ok(_)) => continue,
+                                _ => break,
+                            }
+                        }
+                    };
 
-            tokio::spawn(async move {
-                conn.psubscribe(key(prefix, pattern)).await?;
+                    // the stream ends when the connection is lost
+                    let Some(msg) = msg else {
+                        break;
+                    };
 
-                let mut msg_stream = conn.on_message();
-                while let Some(msg) = msg_stream.next().await {
-                    if let Ok(payload) = msg.get_payload::<String>() {
-                        // TODO: handle possible panic when parsing message
-                        //       using catch_unwind
-                        let message = RM::from_message(payload);
+                    if done(&sender) {
+                        return;
+                    }
+
+                    let Ok(payload) = msg.get_payload::<Vec<u8>>() else {
+                        continue;
+                    };
+
+                    let channel = msg.get_channel_name();
+                    if let Some(message) = decoder.decode(channel, payload).await {
                         let _ = sender.send(message);
                     }
                 }
 
-                Result::<()>::Ok(())
-            });
-        }
+                let since = Utc::now().timestamp_millis();
+                warn!(
+                    "Lost connection to redis, subscriber of '{}' reconnecting",
+                    pattern
+                );
+                let _ = status.send(ConnectionStatus::Disconnected { since });
+
+                conn = loop {
+                    tokio::time::sleep(backoff.next_delay()).await;
+                    if done(&sender) {
+                        return;
+                    }
+
+                    match connect(&client, &pattern).await {
+                        Ok(conn) => break conn,
+                        Err(e) => {
+                            let attempt = backoff.attempts();
+                            let error = e.to_string();
+                            warn!("Failed to reconnect '{}' ({}): {}", pattern, attempt, error);
+                            let _ = status.send(ConnectionStatus::Retrying { attempt, error });
+                        }
+                    }
+                };
+
+                info!("Subscriber of '{}' reconnected", pattern);
+                let _ = status.send(ConnectionStatus::Reconnected {
+                    since,
+                    until: Utc::now().timestamp_millis(),
+                    attempts: backoff.attempts(),
+                });
+
+                backoff.reset();
+            }
+        });
 
         Ok(())
     }
 }
 
+/// opens a pub/sub connection and subscribes to the given pattern
+async fn connect(client: &redis::Client, pattern: &str) -> Result<PubSub> {
+    let mut conn = client.get_async_pubsub().await?;
+    conn.psubscribe(pattern).await?;
+
+    Ok(conn)
+}
+
 #[async_trait]
 impl<RM: BusMessage> SubscriberTrait<RM> for RedisSubscriber<RM> {
     async fn stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = RM> + Send + 'static>>> {
+        Ok(Box::pin(
+            self.envelopes().await?.map(|enveloped| enveloped.message),
+        ))
+    }
+
+    async fn envelopes(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Enveloped<RM>> + Send + 'static>>> {
         if self.source_stream.is_none() {
             self.start_streaming().await?;
         }
@@ -107,4 +336,17 @@ impl<RM: BusMessage> SubscriberTrait<RM> for RedisSubscriber<RM> {
             None => fail!("Could not start streaming messages from redis bus"),
         }
     }
+
+    async fn events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEvent<Enveloped<RM>>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming().await?;
+        }
+
+        match self.source_stream.as_ref() {
+            Some(stream) => stream.subscribe_events(),
+            None => fail!("Could not start streaming messages from redis bus"),
+        }
+    }
 }
@@ -0,0 +1,105 @@
+//! 🐎 » redis streams bus
+//!
+//! A durable redis backend: messages are appended to capped redis streams (`XADD`) and read by
+//! consumer groups (`XREADGROUP`), so a subscriber that restarts gets the messages published in
+//! between, and unacknowledged messages are delivered again. Stored messages can also be
+//! replayed from an id or a timestamp (see [`StreamSubscriberTrait`]).
+//!
+//! Subscribers stop reading while their consumers are behind instead of dropping entries, claim
+//! the entries left pending for too long, and reconnect when the connection is lost (see
+//! [`subscribe::RedisStreamSubscriber`]).
+//!
+//! Every message type is appended to the stream of its topic (the first segment of its bus key,
+//! e.g. `rustler:stream:quote` for the `quote:NASDAQ:AAPL` key), and subscribers filter the
+//! entries of the topic by key using the same glob patterns as the pub/sub backend. Like the
+//! pub/sub backend, the publisher also stores the fields of the last message of each key in a
+//! hash.
+//!
+//! **Usage**
+//!
+//! ```rust
+//! let mut publisher = bus::redis_streams::publisher::<Quote, _>(&redis).await?;
+//! publisher.with_max_len(100_000);
+//!
+//! let mut subscriber = bus::redis_streams::subscriber::<Quote, _>(&redis, "quote").await?;
+//! subscriber.with_group("history", "history-1").with_pattern("quote:NASDAQ:*");
+//!
+//! let mut entries = subscriber.entries().await?;
+//! while let Some(entry) = entries.next().await {
+//!     // process entry.message
+//!     subscriber.ack(&[entry.id]).await?;
+//! }
+//! ```
+//!
+//! [`StreamSubscriberTrait`]: super::StreamSubscriberTrait
+
+use {
+    super::{
+        redis::{key, RedisClient},
+        BusMessage, StreamPosition,
+    },
+    eyre::Result,
+};
+
+pub mod publish;
+pub mod subscribe;
+
+/// default max number of entries kept in each stream (approximately)
+pub const DEFAULT_MAX_LEN: usize = 10_000;
+
+/// field of the stream entries holding the bus key of the message
+pub(crate) const KEY_FIELD: &str = "key";
+/// field of the stream entries holding the serialized message
+pub(crate) const MSG_FIELD: &str = "msg";
+
+/// returns the redis key of the stream of a topic
+pub(crate) fn stream_key<T: AsRef<str>, K: AsRef<str>>(prefix: T, topic: K) -> String {
+    key(prefix, format!("stream:{}", topic.as_ref()))
+}
+
+/// returns the topic of a bus key (its first segment)
+pub(crate) fn topic_of(bus_key: &str) -> &str {
+    bus_key.split(':').next().unwrap_or(bus_key)
+}
+
+impl StreamPosition {
+    /// 🐎 » returns the redis stream id of the position
+    ///
+    /// [`StreamPosition::Latest`] is `$`, which is only meaningful when creating consumer groups
+    pub fn to_stream_id(&self) -> String {
+        match self {
+            Self::Beginning => "0".to_string(),
+            Self::Latest => "$".to_string(),
+            Self::Id(id) => id.clone(),
+            Self::Time(time) => format!("{}-0", time),
+        }
+    }
+}
+
+/// 🐎 » **publisher**: create redis streams bus publisher
+///
+/// **Arguments**
+/// - `redis` - a redis client or a redis connection string
+///
+/// **Returns**
+/// - a new `Publisher` instance
+pub async fn publisher<RM: BusMessage, RC: RedisClient>(
+    redis: &RC,
+) -> Result<publish::RedisStreamPublisher<RM>> {
+    publish::RedisStreamPublisher::new(redis).await
+}
+
+/// 🐎 » **subscriber**: create redis streams bus subscriber
+///
+/// **Arguments**
+/// - `redis` - a redis client or a redis connection string
+/// - `topic` - the topic to read (the first segment of the bus keys, e.g. `quote`)
+///
+/// **Returns**
+/// - a new `Subscriber` instance
+pub async fn subscriber<RM: BusMessage, RC: RedisClient>(
+    redis: &RC,
+    topic: &str,
+) -> Result<subscribe::RedisStreamSubscriber<RM>> {
+    subscribe::RedisStreamSubscriber::new(redis, topic).await
+}
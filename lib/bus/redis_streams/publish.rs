@@ -0,0 +1,135 @@
+use {
+    super::{stream_key, topic_of, DEFAULT_MAX_LEN, KEY_FIELD, MSG_FIELD},
+    crate::bus::{
+        envelope::Stamper,
+        redis::{key, RedisClient, KEY_PREFIX},
+        ttl, BusMessage, Codec, PrefixedPubSub, PublisherTrait,
+    },
+    eyre::Result,
+    redis::{aio::ConnectionManager, streams::StreamMaxlen},
+    std::time::Duration,
+    tonic::async_trait,
+};
+
+/// 🐎 » redis streams bus **Publisher**
+///
+/// appends messages to the capped stream of their topic
+///
+/// the connection is restored automatically when it's lost (e.g. when redis restarts); the
+/// messages published while it's down fail
+#[derive(Clone)]
+pub struct RedisStreamPublisher<RM: BusMessage> {
+    conn: ConnectionManager,
+    key_prefix: String,
+    max_len: usize,
+    codec: Codec,
+    stamper: Stamper,
+    ttl: Option<Duration>,
+    resource_type: std::marker::PhantomData<RM>,
+}
+
+impl<RM: BusMessage> PrefixedPubSub for RedisStreamPublisher<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+}
+
+impl<RM: BusMessage> RedisStreamPublisher<RM> {
+    /// 🐎 » create a new redis streams bus publisher
+    pub async fn new<RC>(redis: &RC) -> Result<Self>
+    where
+        RC: RedisClient,
+    {
+        let redis = redis.get_client()?;
+        let conn = ConnectionManager::new(redis).await?;
+
+        Ok(Self {
+            conn,
+            key_prefix: KEY_PREFIX.to_string(),
+            max_len: DEFAULT_MAX_LEN,
+            codec: Codec::default(),
+            stamper: Stamper::default(),
+            ttl: ttl::configured::<RM>(),
+            resource_type: std::marker::PhantomData,
+        })
+    }
+
+    /// 🐎 » sets the max number of entries kept in each stream (defaults to [`DEFAULT_MAX_LEN`])
+    ///
+    /// streams are trimmed approximately (`MAXLEN ~`), so they can be slightly longer
+    pub fn with_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// 🐎 » set the codec the messages are encoded with (defaults to [`Codec::Legacy`])
+    pub fn with_codec(&mut self, codec: Codec) -> &mut Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 🐎 » set the name of the producer written in the envelopes (e.g. the rustler's name)
+    pub fn with_source(&mut self, source: &str) -> &mut Self {
+        self.stamper.set_source(source);
+        self
+    }
+
+    /// 🐎 » wrap the messages in an envelope with their metadata (off by default, for the
+    /// consumers that don't support envelopes)
+    pub fn with_envelope(&mut self) -> &mut Self {
+        self.stamper.enable();
+        self
+    }
+
+    /// 🐎 » expire the hash of each key after `ttl` without new messages (the expiration is
+    /// refreshed on every publish); the streams are capped by their length instead
+    ///
+    /// defaults to the ttl configured for the topic of the messages (see [`ttl::configured`])
+    pub fn with_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// sets the hash of the message and appends it to the stream of its topic
+    async fn send(&mut self, value: RM, trace_id: Option<String>) -> Result<()> {
+        let bus_key = value.to_bus_key();
+        let obj_key = key(self.get_prefix(), &bus_key);
+        let stream = stream_key(self.get_prefix(), topic_of(&bus_key));
+        let payload = self.stamper.seal(&value, self.codec.encode(&value)?, trace_id)?;
+        let fields = [(KEY_FIELD, bus_key.as_bytes().to_vec()), (MSG_FIELD, payload)];
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+
+        // set hash key
+        pipe.hset_multiple(&obj_key, value.to_bus_val().as_slice()).ignore();
+        if let Some(ttl) = self.ttl {
+            pipe.pexpire(&obj_key, ttl.as_millis() as i64).ignore();
+        }
+
+        // append to the stream of the topic
+        pipe.xadd_maxlen(&stream, StreamMaxlen::Approx(self.max_len), "*", &fields).ignore();
+
+        () = pipe.query_async(&mut self.conn).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> PublisherTrait<RM> for RedisStreamPublisher<RM> {
+    /// 🐎 » publish a message to the bus
+    async fn publish(&mut self, value: RM) -> Result<()> {
+        self.send(value, None).await
+    }
+
+    /// 🐎 » publish a message to the bus, setting the trace id of its envelope
+    async fn publish_traced(&mut self, value: RM, trace_id: &str) -> Result<()> {
+        self.send(value, Some(trace_id.to_string())).await
+    }
+}
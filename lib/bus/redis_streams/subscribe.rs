@@ -0,0 +1,691 @@
+use {
+    super::{stream_key, KEY_FIELD, MSG_FIELD},
+    crate::bus::{
+        decode::Decoder,
+        memory::matches,
+        redis::{
+            reconnect::Backoff,
+            stream::{LagPolicy, SourceStream, StreamEvent, DEFAULT_CAPACITY},
+            RedisClient, KEY_PREFIX,
+        },
+        BusMessage, DeadLetter, DeadLetterPublisher, Enveloped, PrefixedPubSub, PublisherTrait,
+        StreamEntry, StreamPosition, StreamSubscriberTrait, SubscriberTrait,
+    },
+    eyre::Result,
+    futures::{Stream, StreamExt},
+    lool::{
+        fail,
+        logger::{info, warn},
+        s,
+    },
+    redis::{
+        aio::MultiplexedConnection,
+        streams::{
+            StreamClaimReply, StreamId, StreamPendingCountReply, StreamRangeReply,
+            StreamReadOptions, StreamReadReply,
+        },
+        AsyncCommands, AsyncConnectionConfig, RedisResult,
+    },
+    std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+    tokio::sync::broadcast::Sender,
+    tonic::async_trait,
+};
+
+/// default consumer group of the subscribers
+pub const DEFAULT_GROUP: &str = "rustler";
+
+/// interval between checks of the buffer of a subscriber applying backpressure
+const BACKPRESSURE_POLL: Duration = Duration::from_millis(10);
+
+/// 🐎 » redis streams bus **Subscriber**
+///
+/// reads the stream of a topic as a member of a consumer group: every entry is delivered to a
+/// single consumer of each group, so subscribers of the same group share the load, and
+/// subscribers of different groups get every entry
+///
+/// all the subscribers of a group should use the same pattern, since the entries that don't
+/// match it are acknowledged and skipped
+///
+/// there's no gap detection: the consumers of a group share the entries, so each one sees gaps
+/// in the sequence numbers of the envelopes, and unacknowledged entries are delivered again
+///
+/// by default the subscriber applies backpressure: it stops reading the stream while the buffer
+/// of its slowest consumer is full, so no entry is dropped (they wait in redis instead). Entries
+/// that stay pending for too long (see [`Self::with_pending`]) are claimed and delivered again,
+/// and when the connection is lost the subscriber reconnects with an exponential backoff,
+/// recreating its consumer group if it was deleted
+pub struct RedisStreamSubscriber<RM: BusMessage> {
+    client: redis::Client,
+    key_prefix: String,
+    topic: String,
+    pattern: String,
+    group: String,
+    consumer: String,
+    group_start: StreamPosition,
+    count: usize,
+    block: Duration,
+    ack_conn: Option<MultiplexedConnection>,
+    decode_errors: Arc<AtomicU64>,
+    dead_letter: Option<DeadLetterPublisher>,
+    buffer: usize,
+    /// `None` to apply backpressure
+    lag_policy: Option<LagPolicy>,
+    backoff: Backoff,
+    pending_interval: Duration,
+    min_idle: Duration,
+    pub source_stream: Option<SourceStream<StreamEntry<RM>>>,
+}
+
+impl<RM: BusMessage> PrefixedPubSub for RedisStreamSubscriber<RM> {
+    fn get_prefix(&self) -> String {
+        self.key_prefix.clone()
+    }
+
+    fn set_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.key_prefix = s!(prefix);
+        self
+    }
+}
+
+impl<RM: BusMessage> RedisStreamSubscriber<RM> {
+    /// 🐎 » create a new redis streams bus subscriber for the given topic
+    pub async fn new<RC>(redis: &RC, topic: &str) -> Result<Self>
+    where
+        RC: RedisClient,
+    {
+        Ok(Self {
+            client: redis.get_client()?,
+            key_prefix: s!(KEY_PREFIX),
+            topic: s!(topic),
+            pattern: s!("*"),
+            group: s!(DEFAULT_GROUP),
+            consumer: uuid::Uuid::new_v4().to_string(),
+            group_start: StreamPosition::Latest,
+            count: 100,
+            block: Duration::from_secs(5),
+            ack_conn: None,
+            decode_errors: Arc::new(AtomicU64::new(0)),
+            dead_letter: None,
+            buffer: DEFAULT_CAPACITY,
+            lag_policy: None,
+            backoff: Backoff::default(),
+            pending_interval: Duration::from_secs(30),
+            min_idle: Duration::from_secs(60),
+            source_stream: None,
+        })
+    }
+
+    /// 🐎 » set the pattern the bus keys of the messages must match (e.g. `quote:NASDAQ:*`)
+    pub fn with_pattern(&mut self, pattern: &str) -> &mut Self {
+        self.pattern = s!(pattern);
+        self
+    }
+
+    /// 🐎 » set the consumer group and the name of this consumer in the group
+    ///
+    /// the consumer name must be stable across restarts for the subscriber to get the entries
+    /// it received but didn't acknowledge before restarting
+    pub fn with_group(&mut self, group: &str, consumer: &str) -> &mut Self {
+        self.group = s!(group);
+        self.consumer = s!(consumer);
+        self
+    }
+
+    /// 🐎 » set the position a new consumer group starts reading from (defaults to
+    /// [`StreamPosition::Latest`])
+    ///
+    /// it has no effect on groups that already exist
+    pub fn with_group_start(&mut self, start: StreamPosition) -> &mut Self {
+        self.group_start = start;
+        self
+    }
+
+    /// 🐎 » set the max number of entries read at once and how long a read waits for new entries
+    pub fn with_batch(&mut self, count: usize, block: Duration) -> &mut Self {
+        self.count = count.max(1);
+        self.block = block;
+        self
+    }
+
+    /// 🐎 » republish the messages that can't be decoded as [`DeadLetter`]s with the given
+    /// publisher (e.g. `bus::redis::publisher::<DeadLetter, _>(&redis)`)
+    ///
+    /// undecodable entries are acknowledged, so they're not delivered again; it must be set
+    /// before calling `stream` or `entries`
+    pub fn with_dead_letter<P>(&mut self, publisher: P) -> &mut Self
+    where
+        P: PublisherTrait<DeadLetter> + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Box::new(publisher));
+        self
+    }
+
+    /// 🐎 » returns the number of messages skipped because they couldn't be decoded
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// 🐎 » set the number of messages buffered for the slowest consumer of the subscriber's
+    /// streams (defaults to [`DEFAULT_CAPACITY`])
+    ///
+    /// it must be set before calling `stream` or `entries`
+    pub fn with_buffer(&mut self, capacity: usize) -> &mut Self {
+        self.buffer = capacity;
+        self
+    }
+
+    /// 🐎 » set what the subscriber's streams do when their consumer falls behind and the
+    /// oldest buffered messages are overwritten
+    ///
+    /// by default the subscriber applies backpressure instead, so its consumers never fall
+    /// behind. With a lag policy the stream is read as fast as it's written, and the overwritten
+    /// entries are lost when they're acknowledged automatically (see [`SubscriberTrait::stream`]).
+    ///
+    /// it must be set before calling `stream` or `entries`
+    pub fn with_lag_policy(&mut self, policy: LagPolicy) -> &mut Self {
+        self.lag_policy = Some(policy);
+        self
+    }
+
+    /// 🐎 » set the min and max delays between reconnection attempts (defaults to 250ms and 30s)
+    pub fn with_backoff(&mut self, min: Duration, max: Duration) -> &mut Self {
+        self.backoff = Backoff::new(min, max);
+        self
+    }
+
+    /// 🐎 » set how often the pending entries of the group are checked, and how long an entry
+    /// must be pending before it's claimed and delivered again (defaults to 30s and 1 minute)
+    ///
+    /// the entries are claimed from any consumer of the group, so the entries of consumers that
+    /// are gone aren't lost, but consumers that take longer than `min_idle` to acknowledge an
+    /// entry get it twice
+    pub fn with_pending(&mut self, check_interval: Duration, min_idle: Duration) -> &mut Self {
+        self.pending_interval = check_interval;
+        self.min_idle = min_idle;
+        self
+    }
+
+    /// 🐎 » returns the number of messages missed by the lagging consumers of the subscriber
+    pub fn lagged_messages(&self) -> u64 {
+        self.source_stream.as_ref().map(SourceStream::lagged).unwrap_or_default()
+    }
+
+    /// 🐎 » returns the redis key of the stream read by the subscriber
+    pub fn get_stream_key(&self) -> String {
+        stream_key(self.get_prefix(), &self.topic)
+    }
+
+    /// returns the connection used to acknowledge entries
+    async fn ack_conn(&mut self) -> Result<MultiplexedConnection> {
+        if let Some(conn) = &self.ack_conn {
+            return Ok(conn.clone());
+        }
+
+        let conn = self.client.get_multiplexed_tokio_connection().await?;
+        self.ack_conn = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// read the stream as a member of the consumer group
+    async fn start_streaming(&mut self, auto_ack: bool) -> Result<()> {
+        let stream_key = self.get_stream_key();
+        let group_start = self.group_start.to_stream_id();
+        create_group(
+            &mut self.ack_conn().await?,
+            &stream_key,
+            &self.group,
+            &group_start,
+        )
+        .await?;
+
+        let errors = self.decode_errors.clone();
+        let decoder = Decoder::new(String::new(), errors, self.dead_letter.take());
+        let reader = Reader {
+            client: self.client.clone(),
+            read_conn: None,
+            ack_conn: Some(self.ack_conn().await?),
+            stream_key,
+            pattern: self.pattern.clone(),
+            group: self.group.clone(),
+            group_start,
+            consumer: self.consumer.clone(),
+            count: self.count,
+            block: self.block,
+            auto_ack,
+            backpressure: self.lag_policy.is_none().then_some(self.buffer.max(1)),
+            backoff: self.backoff.clone(),
+            pending_interval: self.pending_interval,
+            min_idle: self.min_idle,
+            decoder,
+        };
+
+        let lag_policy = self.lag_policy.unwrap_or_default();
+        let stream = self.source_stream.get_or_insert_with(|| {
+            SourceStream::with_capacity(self.buffer).with_lag_policy(lag_policy)
+        });
+        let Some(sender) = stream.sender() else {
+            fail!("SourceStream has been consumed");
+        };
+
+        tokio::spawn(reader.run(sender));
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> SubscriberTrait<RM> for RedisStreamSubscriber<RM> {
+    /// 🐎 » stream
+    ///
+    /// returns a stream of messages, acknowledging them as soon as they're received (use
+    /// [`StreamSubscriberTrait::entries`] to acknowledge them after processing them)
+    async fn stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = RM> + Send + 'static>>> {
+        Ok(Box::pin(
+            self.envelopes().await?.map(|enveloped| enveloped.message),
+        ))
+    }
+
+    /// 🐎 » envelopes
+    ///
+    /// returns a stream of messages along with the metadata of their envelopes, acknowledging
+    /// them as soon as they're received, like [`SubscriberTrait::stream`]
+    async fn envelopes(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Enveloped<RM>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming(true).await?;
+        }
+
+        match self.source_stream.as_ref() {
+            Some(stream) => Ok(Box::pin(stream.subscribe()?.map(|entry| Enveloped {
+                meta: entry.meta,
+                message: entry.message,
+            }))),
+            None => fail!("Could not start streaming messages from redis stream"),
+        }
+    }
+
+    /// 🐎 » events
+    ///
+    /// like [`Self::envelopes`], acknowledging the messages as soon as they're received
+    async fn events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEvent<Enveloped<RM>>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming(true).await?;
+        }
+
+        let Some(stream) = self.source_stream.as_ref() else {
+            fail!("Could not start streaming messages from redis stream");
+        };
+
+        Ok(Box::pin(stream.subscribe_events()?.map(
+            |event| match event {
+                StreamEvent::Message(entry) => StreamEvent::Message(Enveloped {
+                    meta: entry.meta,
+                    message: entry.message,
+                }),
+                StreamEvent::Lagged(skipped) => StreamEvent::Lagged(skipped),
+            },
+        )))
+    }
+}
+
+#[async_trait]
+impl<RM: BusMessage> StreamSubscriberTrait<RM> for RedisStreamSubscriber<RM> {
+    async fn entries(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEntry<RM>> + Send + 'static>>> {
+        if self.source_stream.is_none() {
+            self.start_streaming(false).await?;
+        }
+
+        match self.source_stream.as_ref() {
+            Some(stream) => stream.subscribe(),
+            None => fail!("Could not start streaming messages from redis stream"),
+        }
+    }
+
+    async fn ack(&mut self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let stream_key = self.get_stream_key();
+        let group = self.group.clone();
+        let result: RedisResult<usize> = self.ack_conn().await?.xack(stream_key, group, ids).await;
+
+        // the next call connects again
+        if result.is_err() {
+            self.ack_conn = None;
+        }
+
+        result?;
+        Ok(())
+    }
+
+    async fn replay(
+        &mut self,
+        from: StreamPosition,
+    ) -> Result<Pin<Box<dyn Stream<Item = StreamEntry<RM>> + Send + 'static>>> {
+        let mut conn = self.ack_conn().await?;
+        let stream_key = self.get_stream_key();
+        let pattern = self.pattern.clone();
+        let count = self.count;
+        let mut decoder = Decoder::<RM>::new(String::new(), self.decode_errors.clone(), None);
+
+        let mut start = match from {
+            StreamPosition::Latest => return Ok(Box::pin(futures::stream::empty())),
+            StreamPosition::Beginning => s!("-"),
+            position => position.to_stream_id(),
+        };
+
+        // the replay ends at the last entry published when it starts
+        let last: StreamRangeReply = conn.xrevrange_count(&stream_key, "+", "-", 1).await?;
+        let Some(end) = last.ids.first().map(|entry| entry.id.clone()) else {
+            return Ok(Box::pin(futures::stream::empty()));
+        };
+
+        let stream = async_stream::stream! {
+            loop {
+                let page: StreamRangeReply =
+                    match conn.xrange_count(&stream_key, &start, &end, count).await {
+                        Ok(page) => page,
+                        Err(e) => {
+                            warn!("Failed to replay redis stream '{}': {}", stream_key, e);
+                            break;
+                        }
+                    };
+
+                let Some(last) = page.ids.last() else {
+                    break;
+                };
+
+                start = format!("({}", last.id);
+                let full = page.ids.len() == count;
+
+                for entry in &page.ids {
+                    let Some((key, msg)) = matching(&pattern, entry) else {
+                        continue;
+                    };
+
+                    if let Some(Enveloped { meta, message }) = decoder.decode(&key, msg).await {
+                        yield StreamEntry { id: entry.id.clone(), message, meta };
+                    }
+                }
+
+                if !full {
+                    break;
+                }
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// creates a consumer group (and its stream) if it doesn't exist yet
+async fn create_group(
+    conn: &mut MultiplexedConnection,
+    stream_key: &str,
+    group: &str,
+    start: &str,
+) -> RedisResult<()> {
+    let result: RedisResult<()> = conn.xgroup_create_mkstream(stream_key, group, start).await;
+
+    match result {
+        Err(e) if e.code() != Some("BUSYGROUP") => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// reads the entries of a consumer group and sends them to a `SourceStream`
+struct Reader<RM: BusMessage> {
+    client: redis::Client,
+    read_conn: Option<MultiplexedConnection>,
+    ack_conn: Option<MultiplexedConnection>,
+    stream_key: String,
+    pattern: String,
+    group: String,
+    group_start: String,
+    consumer: String,
+    count: usize,
+    block: Duration,
+    auto_ack: bool,
+    /// capacity of the buffer of the `SourceStream`, if the reader waits for it to have room
+    backpressure: Option<usize>,
+    backoff: Backoff,
+    pending_interval: Duration,
+    min_idle: Duration,
+    decoder: Decoder<RM>,
+}
+
+impl<RM: BusMessage> Reader<RM> {
+    /// reads the stream forever, reconnecting when the connection is lost
+    async fn run(mut self, sender: Sender<StreamEntry<RM>>) {
+        info!(
+            "Reading redis stream '{}' as '{}'",
+            self.stream_key, self.consumer
+        );
+
+        // the entries delivered to this consumer but never acknowledged (e.g. before a restart)
+        // are read first, then the new ones (`>`)
+        let mut last_id = s!("0");
+        let mut last_check = Instant::now();
+
+        loop {
+            if let Err(e) = self.read(&sender, &mut last_id).await {
+                let delay = self.backoff.next_delay();
+                warn!(
+                    "Failed to read redis stream '{}' (attempt {}), retrying in {:?}: {}",
+                    self.stream_key,
+                    self.backoff.attempts(),
+                    delay,
+                    e
+                );
+
+                tokio::time::sleep(delay).await;
+                self.recover(e).await;
+
+                // entries might have been delivered but not received before the error
+                last_id = s!("0");
+                continue;
+            }
+
+            if self.backoff.attempts() > 0 {
+                info!("Reading redis stream '{}' again", self.stream_key);
+                self.backoff.reset();
+            }
+
+            if last_check.elapsed() >= self.pending_interval {
+                if let Err(e) = self.claim_pending(&sender).await {
+                    warn!(
+                        "Failed to claim the pending entries of redis stream '{}': {}",
+                        self.stream_key, e
+                    );
+                }
+
+                last_check = Instant::now();
+            }
+        }
+    }
+
+    /// drops the connections after an error, so the next read connects again, and recreates the
+    /// consumer group if it no longer exists
+    async fn recover(&mut self, error: redis::RedisError) {
+        self.read_conn = None;
+        self.ack_conn = None;
+
+        if error.code() != Some("NOGROUP") {
+            return;
+        }
+
+        let result = match self.ack_conn().await {
+            Ok(mut conn) => {
+                create_group(&mut conn, &self.stream_key, &self.group, &self.group_start).await
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => info!("Recreated consumer group '{}'", self.group),
+            Err(e) => warn!("Failed to recreate consumer group '{}': {}", self.group, e),
+        }
+    }
+
+    /// returns the connection used to read the stream, connecting if needed
+    async fn read_conn(&mut self) -> RedisResult<MultiplexedConnection> {
+        if let Some(conn) = &self.read_conn {
+            return Ok(conn.clone());
+        }
+
+        // reads block, so they get their own connection
+        let config =
+            AsyncConnectionConfig::new().set_response_timeout(self.block + Duration::from_secs(5));
+        let conn = self.client.get_multiplexed_async_connection_with_config(&config).await?;
+        self.read_conn = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// returns the connection used to acknowledge and claim entries, connecting if needed
+    async fn ack_conn(&mut self) -> RedisResult<MultiplexedConnection> {
+        if let Some(conn) = &self.ack_conn {
+            return Ok(conn.clone());
+        }
+
+        let conn = self.client.get_multiplexed_tokio_connection().await?;
+        self.ack_conn = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// reads the next entries of the consumer and delivers them
+    async fn read(
+        &mut self,
+        sender: &Sender<StreamEntry<RM>>,
+        last_id: &mut String,
+    ) -> RedisResult<()> {
+        let opts = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(self.count)
+            .block(self.block.as_millis() as usize);
+
+        let reply: Option<StreamReadReply> =
+            self.read_conn().await?.xread_options(&[&self.stream_key], &[&*last_id], &opts).await?;
+
+        let ids: Vec<StreamId> =
+            reply.map(|r| r.keys.into_iter().flat_map(|k| k.ids).collect()).unwrap_or_default();
+
+        if *last_id != ">" {
+            match ids.last() {
+                Some(last) => *last_id = last.id.clone(),
+                None => *last_id = s!(">"),
+            }
+        }
+
+        self.deliver(sender, ids).await
+    }
+
+    /// claims the entries of the group that have been pending for longer than `min_idle` (e.g.
+    /// entries this consumer didn't acknowledge, or entries of consumers that are gone) and
+    /// delivers them again
+    async fn claim_pending(&mut self, sender: &Sender<StreamEntry<RM>>) -> RedisResult<()> {
+        let min_idle = self.min_idle.as_millis() as usize;
+        let mut conn = self.ack_conn().await?;
+
+        let pending: StreamPendingCountReply =
+            conn.xpending_count(&self.stream_key, &self.group, "-", "+", self.count).await?;
+
+        let idle: Vec<String> = pending
+            .ids
+            .into_iter()
+            .filter(|entry| entry.last_delivered_ms >= min_idle)
+            .map(|entry| entry.id)
+            .collect();
+
+        if idle.is_empty() {
+            return Ok(());
+        }
+
+        let claimed: StreamClaimReply = conn
+            .xclaim(
+                &self.stream_key,
+                &self.group,
+                &self.consumer,
+                min_idle,
+                &idle,
+            )
+            .await?;
+
+        info!(
+            "Claimed {} pending entries of redis stream '{}'",
+            claimed.ids.len(),
+            self.stream_key
+        );
+        self.deliver(sender, claimed.ids).await
+    }
+
+    /// sends the entries matching the pattern to the `SourceStream`, acknowledging the skipped
+    /// ones (and the sent ones if `auto_ack` is set)
+    async fn deliver(
+        &mut self,
+        sender: &Sender<StreamEntry<RM>>,
+        ids: Vec<StreamId>,
+    ) -> RedisResult<()> {
+        let mut ack = vec![];
+        for entry in &ids {
+            let id = entry.id.clone();
+            let decoded = match matching(&self.pattern, entry) {
+                Some((key, msg)) => self.decoder.decode(&key, msg).await,
+                None => None,
+            };
+
+            match decoded {
+                Some(Enveloped { meta, message }) => {
+                    self.wait_for_room(sender).await;
+                    if self.auto_ack {
+                        ack.push(id.clone());
+                    }
+                    let _ = sender.send(StreamEntry { id, message, meta });
+                }
+                // entries of other keys and undecodable ones are skipped
+                None => ack.push(id),
+            }
+        }
+
+        if !ack.is_empty() {
+            let _: usize = self.ack_conn().await?.xack(&self.stream_key, &self.group, &ack).await?;
+        }
+
+        Ok(())
+    }
+
+    /// with backpressure, waits until there's a consumer and the buffer of the slowest one has
+    /// room, so no entry is dropped
+    async fn wait_for_room(&self, sender: &Sender<StreamEntry<RM>>) {
+        let Some(capacity) = self.backpressure else {
+            return;
+        };
+
+        while sender.receiver_count() == 0 || sender.len() >= capacity {
+            tokio::time::sleep(BACKPRESSURE_POLL).await;
+        }
+    }
+}
+
+/// returns the key and the message of a stream entry, or `None` if its key doesn't match the
+/// pattern
+fn matching(pattern: &str, entry: &StreamId) -> Option<(String, Vec<u8>)> {
+    let key: String = entry.get(KEY_FIELD)?;
+    if !matches(pattern, &key) {
+        return None;
+    }
+
+    Some((key, entry.get(MSG_FIELD)?))
+}
@@ -0,0 +1,72 @@
+//! 🐎 » expiration of the last-value hashes
+//!
+//! The hashes of the keys that stop receiving messages (e.g. the quotes of a deactivated ticker)
+//! would keep their last values forever, so consumers would read them as current. The ttl of the
+//! hashes of each message topic is read from the `RUSTLER_{TOPIC}_TTL` env var (in seconds, e.g.
+//! `RUSTLER_QUOTE_TTL=86400`) when a publisher is created, and `with_ttl` overrides it.
+
+use {
+    super::BusMessage,
+    eyre::Result,
+    lool::{fail, logger::warn},
+    std::time::Duration,
+};
+
+/// 🐎 » returns the ttl configured for the hashes of the messages of type `RM`, if any
+pub fn configured<RM: BusMessage>() -> Option<Duration> {
+    let var = env_var(RM::topic()?);
+    let value = std::env::var(&var).ok()?;
+
+    match parse(&value) {
+        Ok(ttl) => Some(ttl),
+        Err(e) => {
+            warn!("{} in `{}`, the keys won't expire", e, var);
+            None
+        }
+    }
+}
+
+/// returns the env var holding the ttl of a topic
+fn env_var(topic: &str) -> String {
+    format!("RUSTLER_{}_TTL", topic.to_uppercase().replace('-', "_"))
+}
+
+/// parses a ttl in seconds
+fn parse(value: &str) -> Result<Duration> {
+    match value.trim().parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => fail!("Invalid ttl `{}`", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{bus::DeadLetter, candles::Candle, indicators::IndicatorValue, rustlers::Quote},
+    };
+
+    #[test]
+    fn every_topic_has_its_own_env_var() {
+        assert_eq!(env_var(Quote::topic().unwrap()), "RUSTLER_QUOTE_TTL");
+        assert_eq!(env_var(Candle::topic().unwrap()), "RUSTLER_CANDLE_TTL");
+        assert_eq!(
+            env_var(IndicatorValue::topic().unwrap()),
+            "RUSTLER_INDICATOR_TTL"
+        );
+        assert_eq!(env_var("dead-letter"), "RUSTLER_DEAD_LETTER_TTL");
+
+        // messages without a topic never expire
+        assert!(DeadLetter::topic().is_none());
+        assert!(configured::<DeadLetter>().is_none());
+    }
+
+    #[test]
+    fn ttls_are_parsed_in_seconds() {
+        assert_eq!(parse("86400").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse(" 60 ").unwrap(), Duration::from_secs(60));
+        assert!(parse("0").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse("1d").is_err());
+    }
+}
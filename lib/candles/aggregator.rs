@@ -0,0 +1,302 @@
+use {
+    super::{Candle, Resolution},
+    crate::rustlers::{session::MarketSession, MarketHourType, Quote},
+    std::collections::HashMap,
+};
+
+/// a candle being built
+#[derive(Debug, Clone)]
+struct Building {
+    candle: Candle,
+    /// session volume before the candle started
+    base_volume: Option<f64>,
+    /// end of the candle (see [`Resolution::bounds`])
+    end: i64,
+}
+
+/// #### 🐎 » Candle aggregator
+///
+/// Builds candles of the configured resolutions from a stream of quotes. It doesn't do any i/o,
+/// so it can be fed with live or replayed quotes (see [`super::CandleSvc`] for the bus based
+/// service).
+///
+/// Candles are aligned to the session of their market (see [`Resolution::bounds`]), and only
+/// regular hours quotes are aggregated unless extended hours are included.
+///
+/// The volume of a candle is computed from the session volume reported in the quotes
+/// ([`Quote::volume`]), so it's only available for providers that report it.
+#[derive(Debug)]
+pub struct CandleAggregator {
+    resolutions: Vec<Resolution>,
+    include_extended_hours: bool,
+    /// session of each market, by market name
+    sessions: HashMap<String, MarketSession>,
+    /// candles in progress by ticker key and resolution
+    building: HashMap<(String, Resolution), Building>,
+    /// last session volume of each ticker
+    volumes: HashMap<String, f64>,
+}
+
+impl CandleAggregator {
+    /// 🐎 » creates an aggregator for the given resolutions
+    pub fn new(resolutions: Vec<Resolution>, include_extended_hours: bool) -> Self {
+        Self {
+            resolutions,
+            include_extended_hours,
+            sessions: HashMap::new(),
+            building: HashMap::new(),
+            volumes: HashMap::new(),
+        }
+    }
+
+    /// 🐎 » sets the session of a market
+    ///
+    /// markets without a session are aligned to utc days
+    pub fn set_session(&mut self, market: &str, session: MarketSession) {
+        self.sessions.insert(market.to_owned(), session);
+    }
+
+    /// 🐎 » adds a quote to the candles of its ticker
+    ///
+    /// returns the candles closed by the quote (flagged as closed), followed by the candles
+    /// updated by it (in progress)
+    pub fn on_quote(&mut self, quote: &Quote) -> Vec<Candle> {
+        if !self.include_extended_hours && !matches!(quote.market_hours, MarketHourType::Regular) {
+            return vec![];
+        }
+
+        let key = format!("{}:{}", quote.market, quote.id);
+        let session = self.sessions.get(&quote.market).copied().unwrap_or_default();
+
+        // the volume before this quote is the base volume of the candles it opens
+        let previous_volume = match quote.volume {
+            Some(volume) => self.volumes.insert(key.clone(), volume),
+            None => None,
+        };
+
+        let mut closed = vec![];
+        let mut updated = vec![];
+
+        for resolution in &self.resolutions {
+            let (start, end) = resolution.bounds(quote.time, &session);
+            let slot = (key.clone(), *resolution);
+
+            match self.building.get_mut(&slot) {
+                // late quote for an already closed candle
+                Some(building) if start < building.candle.start => continue,
+                Some(building) if start == building.candle.start && building.candle.closed => {
+                    continue
+                }
+                Some(building) if start == building.candle.start => {
+                    update(building, quote);
+                    updated.push(building.candle.clone());
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mut building = Building {
+                candle: Candle {
+                    market: quote.market.clone(),
+                    symbol: quote.id.clone(),
+                    resolution: *resolution,
+                    start,
+                    open: quote.price,
+                    high: quote.price,
+                    low: quote.price,
+                    close: quote.price,
+                    volume: None,
+                    closed: false,
+                },
+                // without a previous quote, the volume is counted from this quote on
+                base_volume: previous_volume.or(quote.volume),
+                end,
+            };
+
+            update(&mut building, quote);
+            updated.push(building.candle.clone());
+
+            // candles closed by `flush` were already returned
+            if let Some(mut previous) = self.building.insert(slot, building) {
+                if !previous.candle.closed {
+                    previous.candle.closed = true;
+                    closed.push(previous.candle);
+                }
+            }
+        }
+
+        closed.extend(updated);
+        closed
+    }
+
+    /// 🐎 » closes the candles that ended before the given time (unix timestamp in milliseconds)
+    ///
+    /// used to close the candles of tickers that stop receiving quotes (e.g. at market close)
+    pub fn flush(&mut self, now: i64) -> Vec<Candle> {
+        self.building
+            .values_mut()
+            .filter(|b| !b.candle.closed && b.end <= now)
+            .map(|b| {
+                b.candle.closed = true;
+                b.candle.clone()
+            })
+            .collect()
+    }
+}
+
+/// updates a candle with a quote
+fn update(building: &mut Building, quote: &Quote) {
+    let candle = &mut building.candle;
+
+    candle.high = candle.high.max(quote.price);
+    candle.low = candle.low.min(quote.price);
+    candle.close = quote.price;
+
+    if let Some(volume) = quote.volume {
+        // the session volume is reset when a new session starts
+        let base = building.base_volume.filter(|base| *base <= volume).unwrap_or_default();
+        candle.volume = Some(volume - base);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        chrono::{NaiveDateTime, TimeZone},
+        chrono_tz::America::New_York,
+    };
+
+    /// milliseconds of a new york local time (`YYYY-MM-DD HH:MM`)
+    fn ny(time: &str) -> i64 {
+        let local = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap();
+        New_York.from_local_datetime(&local).unwrap().timestamp_millis()
+    }
+
+    fn quote(time: &str, price: f64, volume: Option<f64>) -> Quote {
+        Quote {
+            id: "AAPL".into(),
+            market: "NASDAQ".into(),
+            price,
+            change_percent: 0.0,
+            time: ny(time),
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume,
+            stats: None,
+        }
+    }
+
+    fn aggregator(resolutions: Vec<Resolution>, open: &str, close: &str) -> CandleAggregator {
+        let session =
+            MarketSession::new(Some("America/New_York"), Some(open), Some(close), 0).unwrap();
+
+        let mut aggregator = CandleAggregator::new(resolutions, false);
+        aggregator.set_session("NASDAQ", session);
+        aggregator
+    }
+
+    #[test]
+    fn candles_are_counted_from_the_session_open() {
+        let mut aggregator = aggregator(vec![Resolution::M15, Resolution::H1], "09:30", "16:00");
+
+        let candles = aggregator.on_quote(&quote("2026-03-06 09:50", 100.0, None));
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].start, ny("2026-03-06 09:45"));
+        assert_eq!(candles[1].start, ny("2026-03-06 09:30"));
+
+        // the last hourly candle ends with the trading day
+        let candles = aggregator.on_quote(&quote("2026-03-06 15:40", 100.0, None));
+        assert_eq!(candles[3].resolution, Resolution::H1);
+        assert_eq!(candles[3].start, ny("2026-03-06 15:30"));
+        assert!(aggregator.flush(ny("2026-03-06 15:59")).len() == 1);
+        assert!(aggregator.flush(ny("2026-03-06 16:00")).len() == 1);
+    }
+
+    #[test]
+    fn quotes_of_a_new_candle_close_the_previous_one() {
+        let mut aggregator = aggregator(vec![Resolution::M15], "09:30", "16:00");
+
+        aggregator.on_quote(&quote("2026-03-06 09:31", 100.0, Some(1000.0)));
+        aggregator.on_quote(&quote("2026-03-06 09:40", 110.0, Some(1200.0)));
+        let candles = aggregator.on_quote(&quote("2026-03-06 09:44", 90.0, Some(1500.0)));
+
+        assert_eq!(candles.len(), 1);
+        let building = &candles[0];
+        assert_eq!(
+            (building.open, building.high, building.low),
+            (100.0, 110.0, 90.0)
+        );
+        assert_eq!(building.volume, Some(500.0));
+        assert!(!building.closed);
+
+        let candles = aggregator.on_quote(&quote("2026-03-06 09:46", 95.0, Some(1600.0)));
+        assert_eq!(candles.len(), 2);
+
+        let closed = &candles[0];
+        assert!(closed.closed);
+        assert_eq!(closed.start, ny("2026-03-06 09:30"));
+        assert_eq!(closed.close, 90.0);
+
+        let opened = &candles[1];
+        assert!(!opened.closed);
+        assert_eq!(opened.start, ny("2026-03-06 09:45"));
+        assert_eq!((opened.open, opened.close), (95.0, 95.0));
+        assert_eq!(opened.volume, Some(100.0));
+
+        // late quotes of a closed candle are ignored
+        assert!(aggregator.on_quote(&quote("2026-03-06 09:35", 80.0, None)).is_empty());
+    }
+
+    #[test]
+    fn flush_closes_the_candles_that_ended() {
+        let mut aggregator = aggregator(vec![Resolution::M15], "09:30", "16:00");
+        aggregator.on_quote(&quote("2026-03-06 09:46", 95.0, None));
+
+        assert!(aggregator.flush(ny("2026-03-06 09:59")).is_empty());
+
+        let closed = aggregator.flush(ny("2026-03-06 10:00"));
+        assert_eq!(closed.len(), 1);
+        assert!(closed[0].closed);
+        assert_eq!(closed[0].start, ny("2026-03-06 09:45"));
+
+        // they're closed once, and new quotes don't close them again
+        assert!(aggregator.flush(ny("2026-03-06 10:30")).is_empty());
+        let candles = aggregator.on_quote(&quote("2026-03-06 10:31", 96.0, None));
+        assert_eq!(candles.len(), 1);
+        assert!(!candles[0].closed);
+    }
+
+    #[test]
+    fn sessions_crossing_midnight_are_a_single_daily_candle() {
+        let mut aggregator = aggregator(vec![Resolution::D1], "18:00", "17:00");
+
+        aggregator.on_quote(&quote("2026-03-05 20:00", 100.0, None));
+        let candles = aggregator.on_quote(&quote("2026-03-06 02:00", 105.0, None));
+
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].start, ny("2026-03-05 18:00"));
+        assert_eq!((candles[0].open, candles[0].close), (100.0, 105.0));
+
+        assert!(aggregator.flush(ny("2026-03-06 16:59")).is_empty());
+        assert_eq!(aggregator.flush(ny("2026-03-06 17:00")).len(), 1);
+
+        // the next session opens a new candle
+        let candles = aggregator.on_quote(&quote("2026-03-06 18:00", 110.0, None));
+        assert_eq!(candles[0].start, ny("2026-03-06 18:00"));
+    }
+
+    #[test]
+    fn extended_hours_quotes_are_skipped_unless_included() {
+        let mut aggregator = aggregator(vec![Resolution::M1], "09:30", "16:00");
+        let pre = Quote {
+            market_hours: MarketHourType::Pre,
+            ..quote("2026-03-06 09:00", 100.0, None)
+        };
+
+        assert!(aggregator.on_quote(&pre).is_empty());
+
+        aggregator.include_extended_hours = true;
+        assert_eq!(aggregator.on_quote(&pre)[0].start, ny("2026-03-06 09:00"));
+    }
+}
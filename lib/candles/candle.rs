@@ -0,0 +1,307 @@
+use {
+    crate::{
+        bus::{
+            codec::proto, redis::stream::StreamMsg, BusFields, BusMessage, FromBusVal,
+            MessageFields, ToBusKey, ToBusVal, ToFromBusMessage, ToFromProto,
+        },
+        entities::candle,
+        rustlers::session::MarketSession,
+    },
+    eyre::Result,
+    lool::{fail, s},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// #### 🐎 » Candle resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "1m")]
+    M1,
+    #[serde(rename = "5m")]
+    M5,
+    #[serde(rename = "15m")]
+    M15,
+    #[serde(rename = "1h")]
+    H1,
+    #[serde(rename = "1d")]
+    D1,
+}
+
+impl Resolution {
+    /// 🐎 » every supported resolution, from the smallest to the largest
+    pub const ALL: [Resolution; 5] = [Self::M1, Self::M5, Self::M15, Self::H1, Self::D1];
+
+    /// 🐎 » returns the length of a candle of this resolution in milliseconds
+    pub fn millis(&self) -> i64 {
+        const MINUTE: i64 = 60_000;
+
+        match self {
+            Self::M1 => MINUTE,
+            Self::M5 => 5 * MINUTE,
+            Self::M15 => 15 * MINUTE,
+            Self::H1 => 60 * MINUTE,
+            Self::D1 => 24 * 60 * MINUTE,
+        }
+    }
+
+    /// 🐎 » returns the start and end (exclusive) of the candle containing the given time
+    ///
+    /// candles are aligned to the session of the market: daily candles span a trading day,
+    /// starting at the session open, and intraday candles are counted from the open too (so a
+    /// session opening at 9:30 gets hourly candles starting at :30). Intraday candles don't cross
+    /// the limits of the trading day, even if that makes the first or last one shorter.
+    pub fn bounds(&self, time: i64, session: &MarketSession) -> (i64, i64) {
+        let day = session.trading_day(time);
+        let (open, end) = (session.start(day), session.end(day));
+
+        match self {
+            Self::D1 => (open, end),
+            _ => {
+                let start = open + (time - open).div_euclid(self.millis()) * self.millis();
+                let start = start.max(session.begin(day));
+                (start, (start + self.millis()).min(end))
+            }
+        }
+    }
+
+    /// 🐎 » returns the start of the candle containing the given time (see
+    /// [`Resolution::bounds`])
+    pub fn align(&self, time: i64, session: &MarketSession) -> i64 {
+        self.bounds(time, session).0
+    }
+
+    /// 🐎 » returns the string representation of the resolution (e.g. `5m`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::M1 => "1m",
+            Self::M5 => "5m",
+            Self::M15 => "15m",
+            Self::H1 => "1h",
+            Self::D1 => "1d",
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "1m" => Ok(Self::M1),
+            "5m" => Ok(Self::M5),
+            "15m" => Ok(Self::M15),
+            "1h" => Ok(Self::H1),
+            "1d" => Ok(Self::D1),
+            _ => fail!("Unknown candle resolution `{}`", s),
+        }
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// #### 🐎 » Candle
+///
+/// OHLCV bar of a ticker. Candles are published to the bus while they're in progress and once
+/// more when they're closed (see [`Candle::closed`]).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Candle {
+    pub market: String,
+    pub symbol: String,
+    pub resolution: Resolution,
+    /// start time of the candle (unix timestamp in milliseconds)
+    pub start: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// volume traded during the candle, if the provider reports the session volume
+    pub volume: Option<f64>,
+    /// `false` while the candle is in progress
+    pub closed: bool,
+}
+
+impl Candle {
+    /// 🐎 » returns the nominal end time of the candle (exclusive)
+    ///
+    /// the last candles of a trading day may end earlier, and daily candles end with the trading
+    /// day (see [`Resolution::bounds`])
+    pub fn end(&self) -> i64 {
+        self.start + self.resolution.millis()
+    }
+
+    /// 🐎 » converts the candle to its database model
+    pub fn to_model(&self) -> candle::Model {
+        candle::Model {
+            market: self.market.clone(),
+            symbol: self.symbol.clone(),
+            resolution: self.resolution.to_string(),
+            start: self.start,
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+        }
+    }
+
+    /// 🐎 » creates a closed candle from its database model
+    pub fn from_model(model: candle::Model) -> Result<Self> {
+        Ok(Self {
+            resolution: model.resolution.parse()?,
+            market: model.market,
+            symbol: model.symbol,
+            start: model.start,
+            open: model.open,
+            high: model.high,
+            low: model.low,
+            close: model.close,
+            volume: model.volume,
+            closed: true,
+        })
+    }
+}
+
+impl ToBusVal for Candle {
+    fn to_bus_val(&self) -> Vec<(String, String)> {
+        vec![
+            (s!("market"), self.market.to_owned()),
+            (s!("symbol"), self.symbol.to_owned()),
+            (s!("resolution"), self.resolution.to_string()),
+            (s!("start"), self.start.to_string()),
+            (s!("open"), self.open.to_string()),
+            (s!("high"), self.high.to_string()),
+            (s!("low"), self.low.to_string()),
+            (s!("close"), self.close.to_string()),
+            (
+                s!("volume"),
+                self.volume.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+            (s!("closed"), self.closed.to_string()),
+        ]
+    }
+}
+
+impl FromBusVal for Candle {
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self> {
+        let fields = BusFields::new(fields);
+
+        Ok(Self {
+            market: fields.get("market")?.to_string(),
+            symbol: fields.get("symbol")?.to_string(),
+            resolution: fields.parse("resolution")?,
+            start: fields.parse::<i64>("start")?,
+            open: fields.parse::<f64>("open")?,
+            high: fields.parse::<f64>("high")?,
+            low: fields.parse::<f64>("low")?,
+            close: fields.parse::<f64>("close")?,
+            volume: fields.parse_opt::<f64>("volume")?,
+            closed: fields.parse::<bool>("closed")?,
+        })
+    }
+}
+
+impl ToBusKey for Candle {
+    fn to_bus_key(&self) -> String {
+        format!("candle:{}:{}:{}", self.resolution, self.market, self.symbol)
+    }
+}
+
+impl ToFromBusMessage for Candle {
+    /// 🐎 » converts a `Candle` to a serialized message that can be sent over a redis channel
+    ///
+    /// the message is in the format
+    /// `market¦symbol¦resolution¦start¦open¦high¦low¦close¦volume¦closed`
+    fn as_message(&self) -> String {
+        format!(
+            "{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}",
+            self.market,
+            self.symbol,
+            self.resolution,
+            self.start,
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume.map(|v| v.to_string()).unwrap_or_default(),
+            self.closed,
+        )
+    }
+
+    /// 🐎 » creates a `Candle` from a message
+    ///
+    /// fails if the message is not in the correct format
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self> {
+        let fields = MessageFields::new(msg.as_ref());
+
+        Ok(Self {
+            market: fields.get(0, "market")?.to_string(),
+            symbol: fields.get(1, "symbol")?.to_string(),
+            resolution: fields.parse(2, "resolution")?,
+            start: fields.parse::<i64>(3, "start")?,
+            open: fields.parse::<f64>(4, "open")?,
+            high: fields.parse::<f64>(5, "high")?,
+            low: fields.parse::<f64>(6, "low")?,
+            close: fields.parse::<f64>(7, "close")?,
+            volume: fields.parse_opt::<f64>(8, "volume")?,
+            closed: fields.parse::<bool>(9, "closed")?,
+        })
+    }
+}
+
+impl ToFromProto for Candle {
+    type Proto = proto::Candle;
+
+    /// 🐎 » converts a `Candle` to its protobuf message
+    fn to_proto(&self) -> proto::Candle {
+        proto::Candle {
+            market: self.market.clone(),
+            symbol: self.symbol.clone(),
+            resolution: self.resolution.to_string(),
+            start: self.start,
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume,
+            closed: self.closed,
+        }
+    }
+
+    /// 🐎 » creates a `Candle` from its protobuf message
+    ///
+    /// fails if the resolution is unknown
+    fn from_proto(candle: proto::Candle) -> Result<Self> {
+        Ok(Self {
+            resolution: candle.resolution.parse()?,
+            market: candle.market,
+            symbol: candle.symbol,
+            start: candle.start,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            closed: candle.closed,
+        })
+    }
+}
+
+impl StreamMsg for Candle {}
+impl BusMessage for Candle {
+    fn message_time(&self) -> Option<i64> {
+        Some(self.start)
+    }
+
+    fn topic() -> Option<&'static str> {
+        Some("candle")
+    }
+}
@@ -0,0 +1,147 @@
+//! 🐎 » candle aggregation
+//!
+//! Builds OHLCV candles (1m, 5m, 15m, 1h and 1d) from the quotes of the bus, aligned to the
+//! session of each market, and publishes them as [`Candle`] bus messages. Closed candles can also
+//! be stored in the `candle` table.
+//!
+//! Candles follow the trading days of the market (see [`MarketSession`]): a daily candle starts
+//! at the session open and ends with the post market hours, even if the session crosses
+//! midnight, and intraday candles are counted from the open (see [`Resolution::bounds`]). Markets
+//! whose `time_zone_offset` is a zone name (e.g. `America/New_York`) follow its daylight saving
+//! changes. The sessions of the markets are read when the service starts.
+
+use {
+    crate::{
+        bus::{PublisherTrait, SubscriberTrait},
+        entities::{candle, market, sea_orm::DatabaseConnection},
+        rustlers::{session::MarketSession, Quote},
+    },
+    chrono::Utc,
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::time::Duration,
+    tokio::select,
+};
+
+mod aggregator;
+mod candle;
+
+pub use {aggregator::CandleAggregator, candle::*};
+
+/// #### 🐎 » Candle service options
+#[derive(Debug, Clone)]
+pub struct CandleOpts {
+    /// resolutions of the candles to build
+    pub resolutions: Vec<Resolution>,
+    /// aggregate pre, post and extended hours quotes too
+    pub include_extended_hours: bool,
+    /// publish the candles in progress on every quote, not only when they're closed
+    pub publish_in_progress: bool,
+    /// store the closed candles in the database
+    pub persist: bool,
+    /// interval between checks for candles that ended without receiving new quotes
+    pub flush_interval: Duration,
+}
+
+impl Default for CandleOpts {
+    fn default() -> Self {
+        Self {
+            resolutions: Resolution::ALL.to_vec(),
+            include_extended_hours: false,
+            publish_in_progress: true,
+            persist: false,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// #### 🐎 » Candle service
+///
+/// Subscribes to the quotes of the bus, aggregates them into candles and publishes them.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut subscriber = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// subscriber.with_pattern("quote:*");
+/// let publisher = bus::redis::publisher::<Candle, _>(&redis).await?;
+///
+/// let opts = CandleOpts { persist: true, ..Default::default() };
+/// CandleSvc::new(conn, opts).await?.run(subscriber, publisher).await?;
+/// ```
+pub struct CandleSvc {
+    opts: CandleOpts,
+    aggregator: CandleAggregator,
+    candle_svc: candle::Service,
+}
+
+impl CandleSvc {
+    /// 🐎 » creates the service, loading the session of every market from the database
+    pub async fn new(conn: DatabaseConnection, opts: CandleOpts) -> Result<Self> {
+        let mut aggregator =
+            CandleAggregator::new(opts.resolutions.clone(), opts.include_extended_hours);
+
+        for market in market::Service::new(conn.clone()).await.get_all().await? {
+            let name = market.pub_name.as_ref().unwrap_or(&market.short_name);
+            aggregator.set_session(name, MarketSession::of(&market));
+        }
+
+        Ok(Self {
+            opts,
+            aggregator,
+            candle_svc: candle::Service::new(conn).await,
+        })
+    }
+
+    /// 🐎 » aggregates the quotes of the subscriber until its stream ends
+    pub async fn run<S, P>(mut self, mut subscriber: S, mut publisher: P) -> Result<()>
+    where
+        S: SubscriberTrait<Quote>,
+        P: PublisherTrait<Candle>,
+    {
+        info!(
+            "Aggregating candles of {} resolutions",
+            self.opts.resolutions.len()
+        );
+
+        let mut stream = subscriber.stream().await?;
+        let mut flush = tokio::time::interval(self.opts.flush_interval);
+
+        loop {
+            let candles = select! {
+                quote = stream.next() => match quote {
+                    Some(quote) => self.aggregator.on_quote(&quote),
+                    None => break,
+                },
+                _ = flush.tick() => self.aggregator.flush(Utc::now().timestamp_millis()),
+            };
+
+            self.handle(candles, &mut publisher).await;
+        }
+
+        Ok(())
+    }
+
+    /// publishes the candles and stores the closed ones
+    async fn handle<P: PublisherTrait<Candle>>(&self, candles: Vec<Candle>, publisher: &mut P) {
+        let closed: Vec<candle::Model> =
+            candles.iter().filter(|c| c.closed).map(Candle::to_model).collect();
+
+        for candle in candles {
+            if !candle.closed && !self.opts.publish_in_progress {
+                continue;
+            }
+
+            if let Err(e) = publisher.publish(candle).await {
+                warn!("Failed to publish candle: {}", e);
+            }
+        }
+
+        if self.opts.persist {
+            if let Err(e) = self.candle_svc.upsert_many(closed).await {
+                warn!("Failed to store closed candles: {}", e);
+            }
+        }
+    }
+}
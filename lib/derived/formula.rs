@@ -0,0 +1,328 @@
+use {
+    eyre::Result,
+    lool::fail,
+    std::{collections::HashSet, iter::Peekable, str::Chars},
+};
+
+/// max number of nested parentheses and unary minus of a formula, so formulas stored in the
+/// database can't overflow the stack when they're parsed
+const MAX_NESTING: usize = 64;
+
+/// max depth of the expression tree of a formula, so it can't overflow the stack when it's
+/// evaluated (long chains of operators are left deep)
+const MAX_DEPTH: usize = 1024;
+
+/// #### 🐎 » Formula
+///
+/// An arithmetic expression over the prices of other tickers.
+///
+/// Tickers are referenced by their key between braces, and can be combined with numbers, the
+/// `+`, `-`, `*` and `/` operators and parentheses:
+///
+/// ```rust
+/// // ARS CCL implied rate
+/// let ccl = Formula::parse("{BCBA:GGAL} / {NASDAQ:GGAL} * 10")?;
+/// // weighted basket
+/// let basket = Formula::parse("0.5 * {NASDAQ:AAPL} + 0.3 * {NASDAQ:MSFT} + 0.2 * {NYSE:KO}")?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    expr: Expr,
+    inputs: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64),
+    Ref(String),
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn depth(&self) -> usize {
+        match self {
+            Expr::Num(_) | Expr::Ref(_) => 1,
+            Expr::Neg(e) => e.depth() + 1,
+            Expr::Bin(_, l, r) => l.depth().max(r.depth()) + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Formula {
+    /// 🐎 » parses a formula
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: src.chars().peekable(),
+            inputs: vec![],
+            nesting: 0,
+        };
+
+        let expr = parser.expr()?;
+        parser.skip_ws();
+        if let Some(c) = parser.chars.peek() {
+            fail!("Unexpected `{}` in formula `{}`", c, src);
+        }
+
+        let mut seen = HashSet::new();
+        let inputs = parser.inputs.into_iter().filter(|i| seen.insert(i.clone())).collect();
+
+        Ok(Self { expr, inputs })
+    }
+
+    /// 🐎 » returns the keys (`market:symbol`) of the tickers referenced by the formula
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    /// 🐎 » evaluates the formula using the given function to get the value of each input
+    ///
+    /// returns `None` if any input is missing or the result is not a finite number (e.g. a
+    /// division by zero)
+    pub fn eval<F: Fn(&str) -> Option<f64>>(&self, value_of: F) -> Option<f64> {
+        eval(&self.expr, &value_of).filter(|v| v.is_finite())
+    }
+}
+
+fn eval<F: Fn(&str) -> Option<f64>>(expr: &Expr, value_of: &F) -> Option<f64> {
+    match expr {
+        Expr::Num(n) => Some(*n),
+        Expr::Ref(key) => value_of(key),
+        Expr::Neg(e) => Some(-eval(e, value_of)?),
+        Expr::Bin(op, l, r) => {
+            let (l, r) = (eval(l, value_of)?, eval(r, value_of)?);
+            Some(match op {
+                Op::Add => l + r,
+                Op::Sub => l - r,
+                Op::Mul => l * r,
+                Op::Div => l / r,
+            })
+        }
+    }
+}
+
+/// recursive descent parser for formulas
+///
+/// ```text
+/// expr   := term (('+' | '-') term)*
+/// term   := factor (('*' | '/') factor)*
+/// factor := '-' factor | number | '{' key '}' | '(' expr ')'
+/// ```
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    inputs: Vec<String>,
+    /// number of nested factors being parsed
+    nesting: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.chars.peek().copied()
+    }
+
+    /// parses a nested part of the formula, failing if it's nested too deeply
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr>) -> Result<Expr> {
+        if self.nesting >= MAX_NESTING {
+            fail!(
+                "Formula is nested too deeply (max nesting is {})",
+                MAX_NESTING
+            );
+        }
+
+        self.nesting += 1;
+        let expr = parse(self);
+        self.nesting -= 1;
+
+        expr
+    }
+
+    /// checks the depth of a new node of the expression tree
+    fn node(&self, expr: Expr) -> Result<Expr> {
+        if expr.depth() > MAX_DEPTH {
+            fail!("Formula is too long (max depth is {})", MAX_DEPTH);
+        }
+
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+
+        while let Some(op) = match self.peek() {
+            Some('+') => Some(Op::Add),
+            Some('-') => Some(Op::Sub),
+            _ => None,
+        } {
+            self.chars.next();
+            let rhs = self.term()?;
+            lhs = self.node(Expr::Bin(op, Box::new(lhs), Box::new(rhs)))?;
+        }
+
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.factor()?;
+
+        while let Some(op) = match self.peek() {
+            Some('*') => Some(Op::Mul),
+            Some('/') => Some(Op::Div),
+            _ => None,
+        } {
+            self.chars.next();
+            let rhs = self.factor()?;
+            lhs = self.node(Expr::Bin(op, Box::new(lhs), Box::new(rhs)))?;
+        }
+
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some('-') => {
+                self.chars.next();
+                let expr = self.nested(Self::factor)?;
+                self.node(Expr::Neg(Box::new(expr)))
+            }
+            Some('(') => {
+                self.chars.next();
+                let expr = self.nested(Self::expr)?;
+                match self.peek() {
+                    Some(')') => {
+                        self.chars.next();
+                        Ok(expr)
+                    }
+                    _ => fail!("Missing `)` in formula"),
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut key = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('}') => break,
+                        Some(c) => key.push(c),
+                        None => fail!("Missing `}}` in formula"),
+                    }
+                }
+
+                let key = key.trim();
+                if !key.contains(':') {
+                    fail!(
+                        "Invalid ticker `{{{}}}` in formula, expected `{{MARKET:SYMBOL}}`",
+                        key
+                    );
+                }
+
+                self.inputs.push(key.to_owned());
+                Ok(Expr::Ref(key.to_owned()))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut num = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    num.push(c);
+                }
+
+                match num.parse() {
+                    Ok(n) => Ok(Expr::Num(n)),
+                    Err(_) => fail!("Invalid number `{}` in formula", num),
+                }
+            }
+            Some(c) => fail!("Unexpected `{}` in formula", c),
+            None => fail!("Unexpected end of formula"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str) -> Option<f64> {
+        let prices = [("BCBA:GGAL", 5000.0), ("NASDAQ:GGAL", 25.0), ("NYSE:ZERO", 0.0)];
+        let formula = Formula::parse(src).unwrap();
+        formula.eval(|key| prices.iter().find(|(k, _)| *k == key).map(|(_, v)| *v))
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("10 - 4 - 3", 3.0),
+            ("8 / 4 / 2", 1.0),
+            ("2 * 3 + 4 * 5", 26.0),
+            ("1.5 + .5", 2.0),
+            ("{BCBA:GGAL} / {NASDAQ:GGAL} * 10", 2000.0),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(eval(src), Some(expected), "{}", src);
+        }
+    }
+
+    #[test]
+    fn unary_minus() {
+        let cases = [
+            ("-2 * 3", -6.0),
+            ("2 * -3", -6.0),
+            ("--2", 2.0),
+            ("-(1 + 2)", -3.0),
+            ("1 - -1", 2.0),
+            ("-{NASDAQ:GGAL}", -25.0),
+        ];
+
+        for (src, expected) in cases {
+            assert_eq!(eval(src), Some(expected), "{}", src);
+        }
+    }
+
+    #[test]
+    fn missing_inputs_and_non_finite_results() {
+        assert_eq!(eval("{NASDAQ:AAPL} * 2"), None);
+        assert_eq!(eval("1 / {NYSE:ZERO}"), None);
+        assert_eq!(eval("0 / 0"), None);
+    }
+
+    #[test]
+    fn inputs_are_deduplicated_in_order() {
+        let formula = Formula::parse("{B:Y} + { A:X } * {B:Y}").unwrap();
+        assert_eq!(formula.inputs(), ["B:Y", "A:X"]);
+    }
+
+    #[test]
+    fn invalid_formulas() {
+        let cases =
+            ["", "1 +", "(1 + 2", "1 + 2)", "1 2", "{NASDAQ:AAPL", "{AAPL}", "1..2", "x * 2"];
+
+        for src in cases {
+            assert!(Formula::parse(src).is_err(), "`{}` should be invalid", src);
+        }
+    }
+
+    #[test]
+    fn depth_is_limited() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_NESTING)), Some(1.0));
+        assert!(Formula::parse(&nested(MAX_NESTING + 1)).is_err());
+        assert!(Formula::parse(&nested(100_000)).is_err());
+        assert!(Formula::parse(&format!("{}1", "-".repeat(100_000))).is_err());
+
+        let chain = |terms: usize| format!("1{}", " + 1".repeat(terms - 1));
+        assert_eq!(eval(&chain(500)), Some(500.0));
+        assert!(Formula::parse(&chain(100_000)).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! 🐎 » derived instruments
+//!
+//! Synthetic instruments (spreads, ratios, weighted baskets, implied rates...) computed from the
+//! live quotes of other tickers. Each instrument is a [`Formula`] over its input tickers, stored
+//! in the `derived_instrument` table, and its quotes are published as regular [`Quote`]s under a
+//! virtual market, so bus and socket consumers can use them like any other ticker.
+
+use {
+    crate::{
+        bus::{PublisherTrait, SubscriberTrait},
+        entities::{derived_instrument, sea_orm::DatabaseConnection},
+        rustlers::Quote,
+    },
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::collections::{HashMap, HashSet},
+};
+
+mod formula;
+
+pub use formula::Formula;
+
+/// value of the [`Quote::source`] of the derived quotes
+pub const SOURCE: &str = "derived";
+
+/// #### 🐎 » Derived instrument
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedInstrument {
+    /// virtual market the quotes are published under
+    pub market: String,
+    pub symbol: String,
+    pub formula: Formula,
+}
+
+impl DerivedInstrument {
+    /// 🐎 » returns the key of the instrument (`market:symbol`)
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.market, self.symbol)
+    }
+}
+
+impl TryFrom<&derived_instrument::Model> for DerivedInstrument {
+    type Error = eyre::Report;
+
+    fn try_from(model: &derived_instrument::Model) -> Result<Self> {
+        Ok(Self {
+            market: model.market.clone(),
+            symbol: model.symbol.clone(),
+            formula: Formula::parse(&model.formula)?,
+        })
+    }
+}
+
+/// last known state of an input ticker
+#[derive(Debug, Clone)]
+struct Input {
+    price: f64,
+    /// previous close implied by the quote's change percent
+    reference: Option<f64>,
+    time: i64,
+}
+
+/// #### 🐎 » Derived instruments engine
+///
+/// Keeps the last price of every input ticker and re-evaluates the instruments that depend on a
+/// ticker whenever a quote for it arrives. It doesn't do any i/o, so it can be fed with quotes
+/// from any source (see [`DerivedSvc`] for the bus based service).
+///
+/// The change percent of a derived quote is computed by evaluating the formula over the
+/// reference prices implied by the change percent of the inputs.
+///
+/// Inputs can't be other derived instruments, which rules out cycles.
+pub struct DerivedEngine {
+    instruments: Vec<DerivedInstrument>,
+    /// indexes of the instruments that depend on each input key
+    dependents: HashMap<String, Vec<usize>>,
+    inputs: HashMap<String, Input>,
+}
+
+impl DerivedEngine {
+    /// 🐎 » creates the engine for the given instruments
+    ///
+    /// instruments using other derived instruments as inputs are discarded
+    pub fn new(instruments: Vec<DerivedInstrument>) -> Self {
+        let keys: HashSet<String> = instruments.iter().map(DerivedInstrument::key).collect();
+
+        let instruments: Vec<DerivedInstrument> = instruments
+            .into_iter()
+            .filter(
+                |i| match i.formula.inputs().iter().find(|k| keys.contains(*k)) {
+                    Some(input) => {
+                        warn!(
+                            "Derived instrument '{}' can't use '{}' as input",
+                            i.key(),
+                            input
+                        );
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .collect();
+
+        let mut dependents: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, instrument) in instruments.iter().enumerate() {
+            for input in instrument.formula.inputs() {
+                dependents.entry(input.clone()).or_default().push(idx);
+            }
+        }
+
+        Self {
+            instruments,
+            dependents,
+            inputs: HashMap::new(),
+        }
+    }
+
+    /// 🐎 » returns the instruments computed by the engine
+    pub fn instruments(&self) -> &[DerivedInstrument] {
+        &self.instruments
+    }
+
+    /// 🐎 » handles a quote and returns the quotes of the derived instruments that depend on it
+    ///
+    /// instruments with inputs that haven't been quoted yet are not published
+    pub fn on_quote(&mut self, quote: &Quote) -> Vec<Quote> {
+        let key = format!("{}:{}", quote.market, quote.id);
+        let Some(dependents) = self.dependents.get(&key) else {
+            return vec![];
+        };
+
+        let reference = 1.0 + quote.change_percent / 100.0;
+        self.inputs.insert(
+            key,
+            Input {
+                price: quote.price,
+                reference: Some(quote.price / reference).filter(|r| r.is_finite()),
+                time: quote.time,
+            },
+        );
+
+        dependents.iter().filter_map(|idx| self.evaluate(&self.instruments[*idx], quote)).collect()
+    }
+
+    fn evaluate(&self, instrument: &DerivedInstrument, trigger: &Quote) -> Option<Quote> {
+        let formula = &instrument.formula;
+        let price = formula.eval(|key| self.inputs.get(key).map(|i| i.price))?;
+
+        let change_percent = formula
+            .eval(|key| self.inputs.get(key).and_then(|i| i.reference))
+            .filter(|r| *r != 0.0)
+            .map(|r| (price - r) / r.abs() * 100.0)
+            .unwrap_or_default();
+
+        let time = formula.inputs().iter().filter_map(|k| self.inputs.get(k)).map(|i| i.time).max();
+
+        Some(Quote {
+            id: instrument.symbol.clone(),
+            market: instrument.market.clone(),
+            price,
+            change_percent,
+            time: time.unwrap_or(trigger.time),
+            market_hours: trigger.market_hours.clone(),
+            source: Some(SOURCE.to_owned()),
+            volume: None,
+            stats: None,
+        })
+    }
+}
+
+/// #### 🐎 » Derived instruments service
+///
+/// Subscribes to the quotes of the bus and publishes the quotes of the derived instruments
+/// stored in the database.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut subscriber = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// subscriber.with_pattern("quote:*");
+/// let publisher = bus::redis::publisher::<Quote, _>(&redis).await?;
+///
+/// DerivedSvc::new(conn).await?.run(subscriber, publisher).await?;
+/// ```
+pub struct DerivedSvc {
+    engine: DerivedEngine,
+}
+
+impl DerivedSvc {
+    /// 🐎 » creates the service, loading the active derived instruments from the database
+    ///
+    /// instruments with an invalid formula are skipped
+    pub async fn new(conn: DatabaseConnection) -> Result<Self> {
+        let models = derived_instrument::Service::new(conn).await.get_all_active().await?;
+
+        let instruments = models
+            .iter()
+            .filter_map(|model| match DerivedInstrument::try_from(model) {
+                Ok(instrument) => Some(instrument),
+                Err(e) => {
+                    warn!("Skipping derived instrument '{}': {}", model.id, e);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Self::with_engine(DerivedEngine::new(instruments)))
+    }
+
+    /// 🐎 » creates the service from an existing engine
+    pub fn with_engine(engine: DerivedEngine) -> Self {
+        Self { engine }
+    }
+
+    /// 🐎 » computes the derived instruments until the subscriber's stream ends
+    pub async fn run<S, P>(mut self, mut subscriber: S, mut publisher: P) -> Result<()>
+    where
+        S: SubscriberTrait<Quote>,
+        P: PublisherTrait<Quote>,
+    {
+        info!(
+            "Computing {} derived instruments",
+            self.engine.instruments().len()
+        );
+
+        let mut stream = subscriber.stream().await?;
+        while let Some(quote) = stream.next().await {
+            for derived in self.engine.on_quote(&quote) {
+                if let Err(e) = publisher.publish(derived).await {
+                    warn!("Failed to publish derived quote: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
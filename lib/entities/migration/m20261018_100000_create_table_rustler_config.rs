@@ -0,0 +1,34 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `rustler_config`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RustlerConfig::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RustlerConfig::Rustler).string().not_null().primary_key())
+                    .col(ColumnDef::new(RustlerConfig::Config).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(RustlerConfig::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RustlerConfig {
+    Table,
+    /// Name of the rustler the configuration belongs to (e.g. "BinanceRustler")
+    Rustler,
+    /// JSON serialized rustler configuration
+    Config,
+}
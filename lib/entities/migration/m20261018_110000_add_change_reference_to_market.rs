@@ -0,0 +1,35 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » add column `change_reference` to table `market`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Market::Table)
+                    .add_column(ColumnDef::new(Market::ChangeReference).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter().table(Market::Table).drop_column(Market::ChangeReference).to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Market {
+    Table,
+    /// Reference price used to compute the change percent of the quotes of the market
+    /// (`provider`, `previous_close` or `session_open`). Null means `provider`
+    ChangeReference,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `reference_price`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReferencePrice::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ReferencePrice::Market).string().not_null())
+                    .col(ColumnDef::new(ReferencePrice::Symbol).string().not_null())
+                    .col(ColumnDef::new(ReferencePrice::PreviousClose).double().null())
+                    .col(ColumnDef::new(ReferencePrice::SessionOpen).double().null())
+                    .col(ColumnDef::new(ReferencePrice::LastPrice).double().null())
+                    .col(ColumnDef::new(ReferencePrice::UpdatedAt).big_integer().not_null())
+                    .primary_key(
+                        Index::create().col(ReferencePrice::Market).col(ReferencePrice::Symbol),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(ReferencePrice::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReferencePrice {
+    Table,
+    /// Market of the ticker, as used in the published quotes (e.g. "NASDAQ")
+    Market,
+    /// Ticker symbol (e.g. "GOOGL")
+    Symbol,
+    /// Last price of the previous session
+    PreviousClose,
+    /// First price of the current session
+    SessionOpen,
+    /// Last known price
+    LastPrice,
+    /// Time of the last update (unix timestamp in milliseconds)
+    UpdatedAt,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `ticker_alias`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TickerAlias::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TickerAlias::TickerId).string().not_null())
+                    .col(ColumnDef::new(TickerAlias::Rustler).string().not_null())
+                    .col(ColumnDef::new(TickerAlias::Symbol).string().not_null())
+                    .primary_key(
+                        Index::create().col(TickerAlias::TickerId).col(TickerAlias::Rustler),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_ticker_alias_ticker_id")
+                            .from(TickerAlias::Table, TickerAlias::TickerId)
+                            .to(Ticker::Table, Ticker::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(TickerAlias::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Ticker {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum TickerAlias {
+    Table,
+    /// Ticker ID
+    TickerId,
+    /// Name of the rustler that uses the alias (e.g. "Binance")
+    Rustler,
+    /// Symbol used by the rustler's provider for the ticker (e.g. "BTCUSDT")
+    Symbol,
+}
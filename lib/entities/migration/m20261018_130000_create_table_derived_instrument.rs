@@ -0,0 +1,54 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `derived_instrument`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DerivedInstrument::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(DerivedInstrument::Id).string().not_null().primary_key())
+                    .col(ColumnDef::new(DerivedInstrument::Market).string().not_null())
+                    .col(ColumnDef::new(DerivedInstrument::Symbol).string().not_null())
+                    .col(ColumnDef::new(DerivedInstrument::Formula).text().not_null())
+                    .col(
+                        ColumnDef::new(DerivedInstrument::Active)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_derived_instrument_market_symbol")
+                            .col(DerivedInstrument::Market)
+                            .col(DerivedInstrument::Symbol)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(DerivedInstrument::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DerivedInstrument {
+    Table,
+    Id,
+    /// Virtual market the derived quotes are published under (e.g. "DERIVED")
+    Market,
+    /// Symbol of the derived instrument (e.g. "CCL")
+    Symbol,
+    /// Formula over other tickers (e.g. "{BCBA:GGAL} / {NASDAQ:GGAL} * 10")
+    Formula,
+    /// Active status of the instrument. This defines if quotes are generated for it or not.
+    Active,
+}
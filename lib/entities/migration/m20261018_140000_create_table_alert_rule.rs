@@ -0,0 +1,65 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `alert_rule`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AlertRule::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AlertRule::Id).string().not_null().primary_key())
+                    .col(ColumnDef::new(AlertRule::TickerId).string().not_null())
+                    .col(ColumnDef::new(AlertRule::Condition).string().not_null())
+                    .col(ColumnDef::new(AlertRule::Threshold).double().not_null())
+                    .col(ColumnDef::new(AlertRule::Cooldown).big_integer().not_null().default(0))
+                    .col(ColumnDef::new(AlertRule::OneShot).boolean().not_null().default(false))
+                    .col(ColumnDef::new(AlertRule::Active).boolean().not_null().default(true))
+                    .col(ColumnDef::new(AlertRule::LastTriggeredAt).big_integer().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_alert_rule_ticker_id")
+                            .from(AlertRule::Table, AlertRule::TickerId)
+                            .to(Ticker::Table, Ticker::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(AlertRule::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Ticker {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum AlertRule {
+    Table,
+    Id,
+    /// Ticker ID
+    TickerId,
+    /// Condition type (e.g. "crosses_above", "moves_more_than")
+    Condition,
+    /// Threshold of the condition: a price or a change percent, depending on the condition
+    Threshold,
+    /// Min time between two alerts of the rule (seconds)
+    Cooldown,
+    /// If true, the rule is deactivated after it's triggered
+    OneShot,
+    /// Active status of the rule. This defines if the rule is evaluated or not.
+    Active,
+    /// Time the rule was last triggered (unix timestamp in milliseconds)
+    LastTriggeredAt,
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `candle`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Candle::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Candle::Market).string().not_null())
+                    .col(ColumnDef::new(Candle::Symbol).string().not_null())
+                    .col(ColumnDef::new(Candle::Resolution).string().not_null())
+                    .col(ColumnDef::new(Candle::Start).big_integer().not_null())
+                    .col(ColumnDef::new(Candle::Open).double().not_null())
+                    .col(ColumnDef::new(Candle::High).double().not_null())
+                    .col(ColumnDef::new(Candle::Low).double().not_null())
+                    .col(ColumnDef::new(Candle::Close).double().not_null())
+                    .col(ColumnDef::new(Candle::Volume).double().null())
+                    .primary_key(
+                        Index::create()
+                            .col(Candle::Market)
+                            .col(Candle::Symbol)
+                            .col(Candle::Resolution)
+                            .col(Candle::Start),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Candle::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Candle {
+    Table,
+    /// Market of the ticker, as used in the published quotes (e.g. "NASDAQ")
+    Market,
+    /// Ticker symbol (e.g. "GOOGL")
+    Symbol,
+    /// Resolution of the candle (e.g. "1m", "1d")
+    Resolution,
+    /// Start time of the candle (unix timestamp in milliseconds)
+    Start,
+    /// First price of the candle
+    Open,
+    /// Highest price of the candle
+    High,
+    /// Lowest price of the candle
+    Low,
+    /// Last price of the candle
+    Close,
+    /// Volume traded during the candle, if known
+    Volume,
+}
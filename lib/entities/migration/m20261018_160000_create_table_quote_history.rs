@@ -0,0 +1,66 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `quote_history`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(QuoteHistory::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(QuoteHistory::Market).string().not_null())
+                    .col(ColumnDef::new(QuoteHistory::Symbol).string().not_null())
+                    .col(ColumnDef::new(QuoteHistory::Time).big_integer().not_null())
+                    .col(ColumnDef::new(QuoteHistory::Price).double().not_null())
+                    .col(ColumnDef::new(QuoteHistory::ChangePercent).double().not_null())
+                    .col(ColumnDef::new(QuoteHistory::Volume).double().null())
+                    .col(ColumnDef::new(QuoteHistory::MarketHours).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(QuoteHistory::Market)
+                            .col(QuoteHistory::Symbol)
+                            .col(QuoteHistory::Time),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // used by the retention and downsampling jobs, which work on the oldest rows
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_quote_history_time")
+                    .table(QuoteHistory::Table)
+                    .col(QuoteHistory::Time)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(QuoteHistory::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum QuoteHistory {
+    Table,
+    /// Market of the ticker, as used in the published quotes (e.g. "NASDAQ")
+    Market,
+    /// Ticker symbol (e.g. "GOOGL")
+    Symbol,
+    /// Time of the quote (unix timestamp in milliseconds)
+    Time,
+    /// Price of the ticker
+    Price,
+    /// Change percent of the quote
+    ChangePercent,
+    /// Volume traded in the session so far, if known
+    Volume,
+    /// Market hours of the quote (0: pre, 1: regular, 2: post, 3: extended)
+    MarketHours,
+}
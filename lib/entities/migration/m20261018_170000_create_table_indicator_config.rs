@@ -0,0 +1,63 @@
+use sea_orm_migration::{async_trait::async_trait, prelude::*};
+
+#[derive(DeriveMigrationName)]
+/// 🐎 » create table `indicator_config`
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(IndicatorConfig::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(IndicatorConfig::Id).string().not_null().primary_key())
+                    .col(ColumnDef::new(IndicatorConfig::TickerId).string().not_null())
+                    .col(ColumnDef::new(IndicatorConfig::Kind).string().not_null())
+                    .col(ColumnDef::new(IndicatorConfig::Period).integer().not_null())
+                    .col(ColumnDef::new(IndicatorConfig::Resolution).string().null())
+                    .col(ColumnDef::new(IndicatorConfig::Multiplier).double().null())
+                    .col(ColumnDef::new(IndicatorConfig::Active).boolean().not_null().default(true))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_indicator_config_ticker_id")
+                            .from(IndicatorConfig::Table, IndicatorConfig::TickerId)
+                            .to(Ticker::Table, Ticker::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(IndicatorConfig::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Ticker {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum IndicatorConfig {
+    Table,
+    Id,
+    /// Ticker ID
+    TickerId,
+    /// Indicator type (e.g. "sma", "ema", "rsi", "bollinger")
+    Kind,
+    /// Number of values (quotes or candles) the indicator is computed over
+    Period,
+    /// Resolution of the candles the indicator is computed from (e.g. "5m"); if null, the
+    /// indicator is computed from the quotes
+    Resolution,
+    /// Standard deviation multiplier of the Bollinger bands (defaults to 2)
+    Multiplier,
+    /// Active status of the configuration. This defines if the indicator is computed or not.
+    Active,
+}
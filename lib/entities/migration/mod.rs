@@ -1,2 +1,11 @@
 pub mod m20220101_000001_create_table_market;
 pub mod m20240325_200049_create_table_ticker;
+pub mod m20261018_100000_create_table_rustler_config;
+pub mod m20261018_110000_add_change_reference_to_market;
+pub mod m20261018_110100_create_table_reference_price;
+pub mod m20261018_120000_create_table_ticker_alias;
+pub mod m20261018_130000_create_table_derived_instrument;
+pub mod m20261018_140000_create_table_alert_rule;
+pub mod m20261018_150000_create_table_candle;
+pub mod m20261018_160000_create_table_quote_history;
+pub mod m20261018_170000_create_table_indicator_config;
@@ -2,17 +2,49 @@ pub mod migration;
 pub use sea_orm;
 
 mod orm {
+    #[path = "alert_rule.rs"]
+    pub mod alert_rule;
+    #[path = "candle.rs"]
+    pub mod candle;
+    #[path = "derived_instrument.rs"]
+    pub mod derived_instrument;
+    #[path = "indicator_config.rs"]
+    pub mod indicator_config;
     #[path = "market.rs"]
     pub mod market;
+    #[path = "quote_history.rs"]
+    pub mod quote_history;
+    #[path = "reference_price.rs"]
+    pub mod reference_price;
+    #[path = "rustler_config.rs"]
+    pub mod rustler_config;
     #[path = "ticker.rs"]
     pub mod ticker;
+    #[path = "ticker_alias.rs"]
+    pub mod ticker_alias;
 }
 
 mod services {
+    #[path = "alert_rule.rs"]
+    pub mod alert_rule;
+    #[path = "candle.rs"]
+    pub mod candle;
+    #[path = "derived_instrument.rs"]
+    pub mod derived_instrument;
+    #[path = "indicator_config.rs"]
+    pub mod indicator_config;
     #[path = "market.rs"]
     pub mod market;
+    #[path = "quote_history.rs"]
+    pub mod quote_history;
+    #[path = "reference_price.rs"]
+    pub mod reference_price;
+    #[path = "rustler_config.rs"]
+    pub mod rustler_config;
     #[path = "ticker.rs"]
     pub mod ticker;
+    #[path = "ticker_alias.rs"]
+    pub mod ticker_alias;
 }
 
 /// market entities and services
@@ -25,6 +57,46 @@ pub mod ticker {
     pub use super::{orm::ticker::*, services::ticker::*};
 }
 
+/// ticker alias entities and services
+pub mod ticker_alias {
+    pub use super::{orm::ticker_alias::*, services::ticker_alias::*};
+}
+
+/// quote history entities and services
+pub mod quote_history {
+    pub use super::{orm::quote_history::*, services::quote_history::*};
+}
+
+/// reference price entities and services
+pub mod reference_price {
+    pub use super::{orm::reference_price::*, services::reference_price::*};
+}
+
+/// rustler configuration entities and services
+pub mod rustler_config {
+    pub use super::{orm::rustler_config::*, services::rustler_config::*};
+}
+
+/// alert rule entities and services
+pub mod alert_rule {
+    pub use super::{orm::alert_rule::*, services::alert_rule::*};
+}
+
+/// candle entities and services
+pub mod candle {
+    pub use super::{orm::candle::*, services::candle::*};
+}
+
+/// derived instrument entities and services
+pub mod derived_instrument {
+    pub use super::{orm::derived_instrument::*, services::derived_instrument::*};
+}
+
+/// indicator configuration entities and services
+pub mod indicator_config {
+    pub use super::{orm::indicator_config::*, services::indicator_config::*};
+}
+
 /// database connection stuff
 pub mod db {
     use {
@@ -67,4 +139,39 @@ pub mod db {
         info!("Database to {} connection established", db_conn_str.green());
         Ok(conn)
     }
+
+    /// creates an in-memory database with every table, for the tests
+    #[cfg(test)]
+    pub(crate) async fn in_memory() -> DatabaseConnection {
+        use {
+            super::migration::*,
+            sea_orm_migration::{MigrationTrait, SchemaManager},
+        };
+
+        // every connection to `sqlite::memory:` opens a new database, so the pool keeps just one
+        let mut conn_opts = ConnectOptions::new("sqlite::memory:");
+        conn_opts.max_connections(1).min_connections(1).sqlx_logging(false);
+        let conn = Database::connect(conn_opts).await.unwrap();
+
+        let migrations: Vec<Box<dyn MigrationTrait>> = vec![
+            Box::new(m20220101_000001_create_table_market::Migration),
+            Box::new(m20240325_200049_create_table_ticker::Migration),
+            Box::new(m20261018_100000_create_table_rustler_config::Migration),
+            Box::new(m20261018_110000_add_change_reference_to_market::Migration),
+            Box::new(m20261018_110100_create_table_reference_price::Migration),
+            Box::new(m20261018_120000_create_table_ticker_alias::Migration),
+            Box::new(m20261018_130000_create_table_derived_instrument::Migration),
+            Box::new(m20261018_140000_create_table_alert_rule::Migration),
+            Box::new(m20261018_150000_create_table_candle::Migration),
+            Box::new(m20261018_160000_create_table_quote_history::Migration),
+            Box::new(m20261018_170000_create_table_indicator_config::Migration),
+        ];
+
+        let manager = SchemaManager::new(&conn);
+        for migration in migrations {
+            migration.up(&manager).await.unwrap();
+        }
+
+        conn
+    }
 }
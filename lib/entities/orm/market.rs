@@ -18,6 +18,7 @@ pub struct Model {
     pub pre_market_offset: Option<u32>,
     pub post_market_offset: Option<u32>,
     pub time_zone_offset: Option<String>,
+    pub change_reference: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,77 @@
+use {
+    crate::entities::alert_rule::{
+        ActiveModel, Column, Entity as AlertRule, Model as AlertRuleModel,
+    },
+    eyre::Result,
+    sea_orm::{
+        ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+        QueryFilter, Set,
+    },
+};
+
+/// 🐎 » service for the `AlertRule` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `AlertRule` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all alert rules from the database
+    pub async fn get_all(&self) -> Result<Vec<AlertRuleModel>, DbErr> {
+        let rules = AlertRule::find().all(&self.conn).await?;
+        Ok(rules)
+    }
+
+    /// 🐎 » retrieves all active alert rules from the database
+    pub async fn get_all_active(&self) -> Result<Vec<AlertRuleModel>, DbErr> {
+        let rules = AlertRule::find().filter(Column::Active.eq(true)).all(&self.conn).await?;
+        Ok(rules)
+    }
+
+    /// 🐎 » retrieves an alert rule from the database, given its id
+    pub async fn get(&self, id: String) -> Result<Option<AlertRuleModel>, DbErr> {
+        let rule = AlertRule::find_by_id(id).one(&self.conn).await?;
+        Ok(rule)
+    }
+
+    /// 🐎 » creates a new alert rule in the database
+    pub async fn create(&self, rule: AlertRuleModel) -> Result<AlertRuleModel, DbErr> {
+        AlertRule::insert(rule.clone().into_active_model()).exec(&self.conn).await?;
+        Ok(rule)
+    }
+
+    /// 🐎 » updates an existing alert rule in the database
+    pub async fn update(&self, rule: AlertRuleModel) -> Result<AlertRuleModel, DbErr> {
+        let rule = rule.into_active_model().reset_all().update(&self.conn).await?;
+        Ok(rule)
+    }
+
+    /// 🐎 » deletes an alert rule from the database, given its id
+    ///
+    /// returns `false` if the rule didn't exist
+    pub async fn delete(&self, id: String) -> Result<bool, DbErr> {
+        let result = AlertRule::delete_by_id(id).exec(&self.conn).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// 🐎 » records that a rule was triggered at the given time (unix timestamp in milliseconds),
+    /// deactivating it if it's a one-shot rule
+    pub async fn set_triggered(&self, id: String, time: i64, one_shot: bool) -> Result<(), DbErr> {
+        let mut rule = ActiveModel {
+            id: Set(id),
+            last_triggered_at: Set(Some(time)),
+            ..Default::default()
+        };
+
+        if one_shot {
+            rule.active = Set(false);
+        }
+
+        rule.update(&self.conn).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use {
+    crate::entities::candle::{Column, Entity as Candle, Model as CandleModel},
+    eyre::Result,
+    sea_orm::{
+        sea_query::OnConflict, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+        IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+    },
+};
+
+/// 🐎 » service for the `Candle` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `Candle` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves the candles of a ticker for the given resolution, starting in the given
+    /// time range (unix timestamps in milliseconds), sorted by start time
+    pub async fn get_range(
+        &self,
+        market: &str,
+        symbol: &str,
+        resolution: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<CandleModel>, DbErr> {
+        let candles = Candle::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Resolution.eq(resolution))
+            .filter(Column::Start.between(from, to))
+            .order_by_asc(Column::Start)
+            .all(&self.conn)
+            .await?;
+
+        Ok(candles)
+    }
+
+    /// 🐎 » retrieves up to `limit` candles of a ticker for the given resolution, starting in
+    /// the given time range, sorted by start time; use the start of the last candle + 1 as `from`
+    /// to get the next page
+    pub async fn get_page(
+        &self,
+        market: &str,
+        symbol: &str,
+        resolution: &str,
+        from: i64,
+        to: i64,
+        limit: u64,
+    ) -> Result<Vec<CandleModel>, DbErr> {
+        let candles = Candle::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Resolution.eq(resolution))
+            .filter(Column::Start.between(from, to))
+            .order_by_asc(Column::Start)
+            .limit(limit)
+            .all(&self.conn)
+            .await?;
+
+        Ok(candles)
+    }
+
+    /// 🐎 » retrieves the last `limit` candles of a ticker for the given resolution, sorted by
+    /// start time
+    pub async fn get_last(
+        &self,
+        market: &str,
+        symbol: &str,
+        resolution: &str,
+        limit: u64,
+    ) -> Result<Vec<CandleModel>, DbErr> {
+        let mut candles = Candle::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Resolution.eq(resolution))
+            .order_by_desc(Column::Start)
+            .limit(limit)
+            .all(&self.conn)
+            .await?;
+
+        candles.reverse();
+        Ok(candles)
+    }
+
+    /// 🐎 » creates or updates the given candles
+    pub async fn upsert_many(&self, candles: Vec<CandleModel>) -> Result<(), DbErr> {
+        if candles.is_empty() {
+            return Ok(());
+        }
+
+        let on_conflict = OnConflict::columns([
+            Column::Market,
+            Column::Symbol,
+            Column::Resolution,
+            Column::Start,
+        ])
+        .update_columns([Column::Open, Column::High, Column::Low, Column::Close, Column::Volume])
+        .to_owned();
+
+        Candle::insert_many(candles.into_iter().map(|c| c.into_active_model()))
+            .on_conflict(on_conflict)
+            .exec(&self.conn)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use {
+    crate::entities::derived_instrument::{
+        Column, Entity as DerivedInstrument, Model as DerivedInstrumentModel,
+    },
+    eyre::Result,
+    sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter},
+};
+
+/// 🐎 » service for the `DerivedInstrument` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `DerivedInstrument` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all derived instruments from the database
+    pub async fn get_all(&self) -> Result<Vec<DerivedInstrumentModel>, DbErr> {
+        let instruments = DerivedInstrument::find().all(&self.conn).await?;
+        Ok(instruments)
+    }
+
+    /// 🐎 » retrieves all active derived instruments from the database
+    pub async fn get_all_active(&self) -> Result<Vec<DerivedInstrumentModel>, DbErr> {
+        let instruments =
+            DerivedInstrument::find().filter(Column::Active.eq(true)).all(&self.conn).await?;
+        Ok(instruments)
+    }
+}
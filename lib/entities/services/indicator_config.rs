@@ -0,0 +1,32 @@
+use {
+    crate::entities::indicator_config::{
+        Column, Entity as IndicatorConfig, Model as IndicatorConfigModel,
+    },
+    eyre::Result,
+    sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter},
+};
+
+/// 🐎 » service for the `IndicatorConfig` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `IndicatorConfig` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all indicator configurations from the database
+    pub async fn get_all(&self) -> Result<Vec<IndicatorConfigModel>, DbErr> {
+        let configs = IndicatorConfig::find().all(&self.conn).await?;
+        Ok(configs)
+    }
+
+    /// 🐎 » retrieves all active indicator configurations from the database
+    pub async fn get_all_active(&self) -> Result<Vec<IndicatorConfigModel>, DbErr> {
+        let configs =
+            IndicatorConfig::find().filter(Column::Active.eq(true)).all(&self.conn).await?;
+        Ok(configs)
+    }
+}
@@ -0,0 +1,169 @@
+use {
+    crate::entities::quote_history::{Column, Entity as QuoteHistory, Model as QuoteHistoryModel},
+    eyre::Result,
+    sea_orm::{
+        sea_query::OnConflict, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+        IntoActiveModel, QueryFilter, QueryOrder, QuerySelect,
+    },
+    std::collections::HashMap,
+};
+
+/// max number of rows inserted by a single statement (sqlite limits the number of variables)
+const INSERT_CHUNK_SIZE: usize = 500;
+
+/// 🐎 » service for the `QuoteHistory` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `QuoteHistory` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves the history of a ticker in the given time range (unix timestamps in
+    /// milliseconds), sorted by time
+    pub async fn get_range(
+        &self,
+        market: &str,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<QuoteHistoryModel>, DbErr> {
+        let quotes = QuoteHistory::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Time.between(from, to))
+            .order_by_asc(Column::Time)
+            .all(&self.conn)
+            .await?;
+
+        Ok(quotes)
+    }
+
+    /// 🐎 » retrieves up to `limit` quotes of a ticker in the given time range (unix timestamps
+    /// in milliseconds), sorted by time; use the time of the last quote + 1 as `from` to get the
+    /// next page
+    pub async fn get_page(
+        &self,
+        market: &str,
+        symbol: &str,
+        from: i64,
+        to: i64,
+        limit: u64,
+    ) -> Result<Vec<QuoteHistoryModel>, DbErr> {
+        let quotes = QuoteHistory::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Time.between(from, to))
+            .order_by_asc(Column::Time)
+            .limit(limit)
+            .all(&self.conn)
+            .await?;
+
+        Ok(quotes)
+    }
+
+    /// 🐎 » retrieves the last `limit` quotes of a ticker, sorted by time
+    pub async fn get_last(
+        &self,
+        market: &str,
+        symbol: &str,
+        limit: u64,
+    ) -> Result<Vec<QuoteHistoryModel>, DbErr> {
+        let mut quotes = QuoteHistory::find()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .order_by_desc(Column::Time)
+            .limit(limit)
+            .all(&self.conn)
+            .await?;
+
+        quotes.reverse();
+        Ok(quotes)
+    }
+
+    /// 🐎 » retrieves the tickers (`(market, symbol)`) with history older than the given time
+    pub async fn get_tickers_before(&self, time: i64) -> Result<Vec<(String, String)>, DbErr> {
+        let tickers = QuoteHistory::find()
+            .select_only()
+            .column(Column::Market)
+            .column(Column::Symbol)
+            .filter(Column::Time.lt(time))
+            .distinct()
+            .into_tuple()
+            .all(&self.conn)
+            .await?;
+
+        Ok(tickers)
+    }
+
+    /// 🐎 » stores the given quotes in batches
+    ///
+    /// the history keeps a single quote per ticker and millisecond: quotes of a ticker with the
+    /// same time are conflated into the last one, whether they're in this batch or already stored
+    pub async fn insert_many(&self, quotes: Vec<QuoteHistoryModel>) -> Result<(), DbErr> {
+        let on_conflict = OnConflict::columns([Column::Market, Column::Symbol, Column::Time])
+            .update_columns([
+                Column::Price,
+                Column::ChangePercent,
+                Column::Volume,
+                Column::MarketHours,
+            ])
+            .to_owned();
+
+        // an upsert can't touch the same row twice, so only the last quote of each time is kept
+        let mut last = HashMap::new();
+        for (i, quote) in quotes.iter().enumerate() {
+            last.insert((&quote.market, &quote.symbol, quote.time), i);
+        }
+        let quotes: Vec<&QuoteHistoryModel> = quotes
+            .iter()
+            .enumerate()
+            .filter(|(i, q)| last[&(&q.market, &q.symbol, q.time)] == *i)
+            .map(|(_, q)| q)
+            .collect();
+
+        for chunk in quotes.chunks(INSERT_CHUNK_SIZE) {
+            QuoteHistory::insert_many(chunk.iter().map(|q| (*q).clone().into_active_model()))
+                .on_conflict(on_conflict.clone())
+                .exec_without_returning(&self.conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// 🐎 » deletes the history of a market older than the given time
+    ///
+    /// returns the number of deleted rows
+    pub async fn delete_market_before(&self, market: &str, time: i64) -> Result<u64, DbErr> {
+        let result = QuoteHistory::delete_many()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Time.lt(time))
+            .exec(&self.conn)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// 🐎 » deletes the history of a ticker older than the given time
+    ///
+    /// returns the number of deleted rows
+    pub async fn delete_ticker_before(
+        &self,
+        market: &str,
+        symbol: &str,
+        time: i64,
+    ) -> Result<u64, DbErr> {
+        let result = QuoteHistory::delete_many()
+            .filter(Column::Market.eq(market))
+            .filter(Column::Symbol.eq(symbol))
+            .filter(Column::Time.lt(time))
+            .exec(&self.conn)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
@@ -0,0 +1,48 @@
+use {
+    crate::entities::reference_price::{
+        Column, Entity as ReferencePrice, Model as ReferencePriceModel,
+    },
+    eyre::Result,
+    sea_orm::{sea_query::OnConflict, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel},
+};
+
+/// 🐎 » service for the `ReferencePrice` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `ReferencePrice` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all reference prices from the database
+    pub async fn get_all(&self) -> Result<Vec<ReferencePriceModel>, DbErr> {
+        let prices = ReferencePrice::find().all(&self.conn).await?;
+        Ok(prices)
+    }
+
+    /// 🐎 » creates or updates the given reference prices
+    pub async fn upsert_many(&self, prices: Vec<ReferencePriceModel>) -> Result<(), DbErr> {
+        if prices.is_empty() {
+            return Ok(());
+        }
+
+        let on_conflict = OnConflict::columns([Column::Market, Column::Symbol])
+            .update_columns([
+                Column::PreviousClose,
+                Column::SessionOpen,
+                Column::LastPrice,
+                Column::UpdatedAt,
+            ])
+            .to_owned();
+
+        ReferencePrice::insert_many(prices.into_iter().map(|p| p.into_active_model()))
+            .on_conflict(on_conflict)
+            .exec(&self.conn)
+            .await?;
+
+        Ok(())
+    }
+}
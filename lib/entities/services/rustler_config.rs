@@ -0,0 +1,44 @@
+use {
+    crate::entities::rustler_config::{Entity as RustlerConfig, Model as RustlerConfigModel},
+    eyre::Result,
+    sea_orm::{sea_query::OnConflict, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel},
+};
+
+/// 🐎 » service for the `RustlerConfig` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `RustlerConfig` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all rustler configurations from the database
+    pub async fn get_all(&self) -> Result<Vec<RustlerConfigModel>, DbErr> {
+        let configs = RustlerConfig::find().all(&self.conn).await?;
+        Ok(configs)
+    }
+
+    /// 🐎 » retrieves the configuration of a rustler, given its name
+    pub async fn get(&self, rustler: String) -> Result<Option<RustlerConfigModel>, DbErr> {
+        let config = RustlerConfig::find_by_id(rustler).one(&self.conn).await?;
+        Ok(config)
+    }
+
+    /// 🐎 » creates or replaces the configuration of a rustler
+    pub async fn upsert(&self, config: RustlerConfigModel) -> Result<RustlerConfigModel, DbErr> {
+        use crate::entities::rustler_config::Column;
+
+        let on_conflict =
+            OnConflict::column(Column::Rustler).update_column(Column::Config).to_owned();
+
+        RustlerConfig::insert(config.clone().into_active_model())
+            .on_conflict(on_conflict)
+            .exec(&self.conn)
+            .await?;
+
+        Ok(config)
+    }
+}
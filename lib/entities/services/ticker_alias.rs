@@ -0,0 +1,38 @@
+use {
+    crate::entities::ticker_alias::{Entity as TickerAlias, Model as TickerAliasModel},
+    eyre::Result,
+    sea_orm::{DatabaseConnection, DbErr, EntityTrait},
+    std::collections::HashMap,
+};
+
+/// 🐎 » service for the `TickerAlias` entity
+pub struct Service {
+    conn: DatabaseConnection,
+}
+
+impl Service {
+    /// 🐎 » creates a new `TickerAlias` service
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    /// 🐎 » retrieves all ticker aliases from the database
+    pub async fn get_all(&self) -> Result<Vec<TickerAliasModel>, DbErr> {
+        let aliases = TickerAlias::find().all(&self.conn).await?;
+        Ok(aliases)
+    }
+
+    /// 🐎 » retrieves all ticker aliases, indexed by `(ticker_id, rustler)`
+    pub async fn get_all_by_ticker_and_rustler(
+        &self,
+    ) -> Result<HashMap<(String, String), String>, DbErr> {
+        let aliases = self
+            .get_all()
+            .await?
+            .into_iter()
+            .map(|a| ((a.ticker_id, a.rustler), a.symbol))
+            .collect();
+
+        Ok(aliases)
+    }
+}
@@ -0,0 +1,512 @@
+//! 🐎 » currency normalization
+//!
+//! Converts the prices of the quotes published by the rustlers into a base currency, using FX
+//! rates that arrive as regular quotes on the bus (e.g. a quote with id `USDARS` in the `FX`
+//! market, meaning 1 USD = `price` ARS).
+//!
+//! The currency of each ticker is its `quote_symbol` (USD when it's not set), reloaded from the
+//! database periodically. Normalized quotes are published under a separate key space (see
+//! [`normalized_prefix`]), so consumers can choose between the raw and the normalized feed.
+
+use {
+    crate::{
+        bus::{redis::KEY_PREFIX, PublisherTrait, SubscriberTrait},
+        entities::{market, sea_orm::DatabaseConnection},
+        rustlers::{Quote, Ticker},
+    },
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::{
+        collections::{HashMap, HashSet},
+        time::Duration,
+    },
+    tokio::{select, time::Instant},
+};
+
+/// currency assumed for tickers without a `quote_symbol`
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// 🐎 » returns the key prefix used to publish the quotes normalized to the given base currency
+///
+/// e.g. `rustler-norm:EUR`, so the normalized quote of `NASDAQ:AAPL` is published under
+/// `rustler-norm:EUR:quote:NASDAQ:AAPL`
+pub fn normalized_prefix(base_currency: &str) -> String {
+    format!("{}-norm:{}", KEY_PREFIX, base_currency)
+}
+
+/// #### 🐎 » Stale FX rate policy
+///
+/// what to do with a quote when the FX rate needed to normalize it is older than
+/// [`FxOpts::stale_after`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StaleFxPolicy {
+    /// don't publish the normalized quote until the rate is updated
+    #[default]
+    Skip,
+    /// normalize the quote using the last known rate anyway
+    UseLast,
+}
+
+/// #### 🐎 » FX normalization options
+#[derive(Debug, Clone)]
+pub struct FxOpts {
+    /// currency the quotes are converted to
+    pub base_currency: String,
+    /// market of the quotes carrying FX rates (their id is `<FROM><TO>`, e.g. `USDARS`)
+    pub fx_market: String,
+    /// currency used to cross rates when there's no direct rate between two currencies
+    pub pivot_currency: Option<String>,
+    /// max age of an FX rate before it's considered stale
+    pub stale_after: Duration,
+    /// what to do when a rate is stale
+    pub on_stale: StaleFxPolicy,
+}
+
+impl Default for FxOpts {
+    fn default() -> Self {
+        Self {
+            base_currency: DEFAULT_CURRENCY.to_owned(),
+            fx_market: "FX".to_owned(),
+            pivot_currency: Some(DEFAULT_CURRENCY.to_owned()),
+            stale_after: Duration::from_secs(300),
+            on_stale: StaleFxPolicy::Skip,
+        }
+    }
+}
+
+/// the last known rate of a currency pair
+#[derive(Debug, Clone, Copy)]
+struct FxRate {
+    rate: f64,
+    received: Instant,
+}
+
+/// #### 🐎 » FX normalizer
+///
+/// Keeps the last FX rates and converts quotes into the base currency. It doesn't do any i/o, so
+/// it can be fed with quotes from any source (see [`FxSvc`] for the bus based service).
+pub struct FxNormalizer {
+    opts: FxOpts,
+    /// currency of each ticker, by ticker key (`market:symbol`)
+    currencies: HashMap<String, String>,
+    /// every currency of the tickers, plus the base and pivot currencies
+    known: HashSet<String>,
+    /// rates by pair (e.g. `USDARS`)
+    rates: HashMap<String, FxRate>,
+    /// last raw quote of each ticker, used to renormalize when a rate changes
+    last_quotes: HashMap<String, Quote>,
+}
+
+impl FxNormalizer {
+    /// 🐎 » creates a new normalizer without any known currency or rate
+    ///
+    /// the base and pivot currencies are uppercased, like the currencies of the tickers
+    pub fn new(mut opts: FxOpts) -> Self {
+        opts.base_currency = opts.base_currency.to_uppercase();
+        opts.pivot_currency = opts.pivot_currency.map(|c| c.to_uppercase());
+
+        let known = [Some(&opts.base_currency), opts.pivot_currency.as_ref()]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+
+        Self {
+            opts,
+            currencies: HashMap::new(),
+            known,
+            rates: HashMap::new(),
+            last_quotes: HashMap::new(),
+        }
+    }
+
+    /// 🐎 » returns the options of the normalizer
+    pub fn opts(&self) -> &FxOpts {
+        &self.opts
+    }
+
+    /// 🐎 » sets the currency of the given tickers from their `quote_asset`
+    pub fn set_currencies(&mut self, tickers: &[Ticker]) {
+        for ticker in tickers {
+            let currency = ticker.quote_asset.as_deref().unwrap_or(DEFAULT_CURRENCY).to_uppercase();
+            self.known.insert(currency.clone());
+            self.currencies.insert(ticker.key(), currency);
+        }
+    }
+
+    /// 🐎 » handles a quote from the bus and returns the normalized quotes to publish
+    ///
+    /// FX quotes update the rates and renormalize the last quote of every ticker quoted in one of
+    /// the currencies of the pair; any other quote is converted to the base currency (if the
+    /// needed rates are available)
+    pub fn on_quote(&mut self, quote: Quote) -> Vec<Quote> {
+        if quote.market == self.opts.fx_market {
+            return self.on_rate(&quote);
+        }
+
+        let key = format!("{}:{}", quote.market, quote.id);
+        let normalized = self.normalize(&quote);
+        self.last_quotes.insert(key, quote);
+
+        normalized.into_iter().collect()
+    }
+
+    /// 🐎 » converts a quote to the base currency
+    ///
+    /// returns `None` if the rate needed to convert it is missing, or it's stale and the policy
+    /// is [`StaleFxPolicy::Skip`]
+    pub fn normalize(&self, quote: &Quote) -> Option<Quote> {
+        let key = format!("{}:{}", quote.market, quote.id);
+        let currency = self.currencies.get(&key).map(String::as_str).unwrap_or(DEFAULT_CURRENCY);
+
+        let rate = self.rate(currency, &self.opts.base_currency)?;
+
+        // the change percent is kept as is: the fx rate changes during the session are not
+        // considered part of the ticker's price change
+        Some(Quote {
+            price: quote.price * rate,
+            stats: quote.stats.as_ref().map(|s| s.converted(rate)),
+            ..quote.clone()
+        })
+    }
+
+    /// 🐎 » returns the rate to convert from one currency to another, if it's known and fresh
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+
+        if let Some(rate) = self.pair_rate(from, to) {
+            return Some(rate);
+        }
+
+        let pivot = self.opts.pivot_currency.as_deref()?;
+        if pivot == from || pivot == to {
+            return None;
+        }
+
+        Some(self.pair_rate(from, pivot)? * self.pair_rate(pivot, to)?)
+    }
+
+    /// gets the rate of a pair using either the direct or the inverse quote
+    fn pair_rate(&self, from: &str, to: &str) -> Option<f64> {
+        if let Some(rate) = self.fresh(&format!("{}{}", from, to)) {
+            return Some(rate);
+        }
+
+        self.fresh(&format!("{}{}", to, from)).filter(|r| *r != 0.0).map(|r| 1.0 / r)
+    }
+
+    /// gets the rate of a pair, applying the stale policy
+    fn fresh(&self, pair: &str) -> Option<f64> {
+        let rate = self.rates.get(pair)?;
+
+        if rate.received.elapsed() <= self.opts.stale_after {
+            return Some(rate.rate);
+        }
+
+        match self.opts.on_stale {
+            StaleFxPolicy::Skip => None,
+            StaleFxPolicy::UseLast => Some(rate.rate),
+        }
+    }
+
+    fn on_rate(&mut self, quote: &Quote) -> Vec<Quote> {
+        let pair = quote.id.to_uppercase();
+        self.rates.insert(
+            pair.clone(),
+            FxRate {
+                rate: quote.price,
+                received: Instant::now(),
+            },
+        );
+
+        let Some((from, to)) = self.split_pair(&pair) else {
+            warn!("Can't tell the currencies of the FX pair '{}'", pair);
+            return vec![];
+        };
+
+        // quotes in one of the currencies of the pair, or crossed through the pivot currency
+        let base = self.opts.base_currency.as_str();
+        let in_pair = |currency: &str| currency == from || currency == to;
+        self.last_quotes
+            .iter()
+            .filter(|(key, _)| {
+                let currency = self.currencies.get(*key).map(String::as_str);
+                let currency = currency.unwrap_or(DEFAULT_CURRENCY);
+                currency != base && (in_pair(currency) || in_pair(base))
+            })
+            .filter_map(|(_, quote)| self.normalize(quote))
+            .collect()
+    }
+
+    /// splits a pair into its currencies, using the known currencies to find where the first one
+    /// ends (so `USDTARS` is `USDT` and `ARS`, not `USD` and `TARS`); pairs of unknown currencies
+    /// are split as two iso codes (three letters each)
+    fn split_pair<'a>(&self, pair: &'a str) -> Option<(&'a str, &'a str)> {
+        let known = (1..pair.len())
+            .filter(|i| pair.is_char_boundary(*i))
+            .map(|i| pair.split_at(i))
+            .find(|(from, to)| self.known.contains(*from) && self.known.contains(*to));
+
+        match known {
+            Some(split) => Some(split),
+            None if pair.len() == 6 && pair.is_ascii() => Some(pair.split_at(3)),
+            None => None,
+        }
+    }
+}
+
+/// #### 🐎 » FX normalization service
+///
+/// Subscribes to the quotes of the bus and publishes them normalized to the base currency.
+///
+/// The publisher should be prefixed with [`normalized_prefix`] so normalized quotes don't
+/// overwrite the raw ones.
+///
+/// **Usage**
+///
+/// ```rust
+/// let opts = FxOpts { base_currency: s!("ARS"), ..Default::default() };
+///
+/// let mut subscriber = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// subscriber.with_pattern("quote:*");
+///
+/// let mut publisher = bus::redis::publisher::<Quote, _>(&redis).await?;
+/// publisher.with_prefix(&fx::normalized_prefix(&opts.base_currency));
+///
+/// FxSvc::new(conn, opts).await?.run(subscriber, publisher).await?;
+/// ```
+pub struct FxSvc {
+    conn: Option<DatabaseConnection>,
+    normalizer: FxNormalizer,
+    reload_interval: Duration,
+}
+
+impl FxSvc {
+    /// 🐎 » creates the service, loading the currency of every ticker from the database
+    pub async fn new(conn: DatabaseConnection, opts: FxOpts) -> Result<Self> {
+        let mut svc = Self {
+            conn: Some(conn),
+            normalizer: FxNormalizer::new(opts),
+            reload_interval: Duration::from_secs(30),
+        };
+
+        svc.reload().await?;
+        Ok(svc)
+    }
+
+    /// 🐎 » creates the service from an existing normalizer (the currencies aren't reloaded)
+    pub fn with_normalizer(normalizer: FxNormalizer) -> Self {
+        Self {
+            conn: None,
+            normalizer,
+            reload_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// 🐎 » sets the interval between currency reloads (defaults to 30 seconds)
+    pub fn set_reload_interval(&mut self, interval: Duration) -> &mut Self {
+        self.reload_interval = interval;
+        self
+    }
+
+    /// 🐎 » reloads the currency of every ticker from the database, so tickers added while the
+    /// service runs are normalized with the right currency
+    pub async fn reload(&mut self) -> Result<()> {
+        let Some(conn) = &self.conn else {
+            return Ok(());
+        };
+
+        let markets = market::Service::new(conn.clone()).await.get_all_with_tickers().await?;
+        for (market, tickers) in markets {
+            self.normalizer.set_currencies(&Ticker::many_from(&tickers, &market));
+        }
+
+        Ok(())
+    }
+
+    /// 🐎 » normalizes the quotes of the subscriber until its stream ends
+    pub async fn run<S, P>(mut self, mut subscriber: S, mut publisher: P) -> Result<()>
+    where
+        S: SubscriberTrait<Quote>,
+        P: PublisherTrait<Quote>,
+    {
+        info!(
+            "Normalizing quotes to '{}'",
+            self.normalizer.opts().base_currency
+        );
+
+        let mut stream = subscriber.stream().await?;
+        let mut reload = tokio::time::interval(self.reload_interval);
+
+        loop {
+            select! {
+                quote = stream.next() => {
+                    let Some(quote) = quote else {
+                        break;
+                    };
+
+                    for normalized in self.normalizer.on_quote(quote) {
+                        if let Err(e) = publisher.publish(normalized).await {
+                            warn!("Failed to publish normalized quote: {}", e);
+                        }
+                    }
+                }
+                _ = reload.tick() => {
+                    if let Err(e) = self.reload().await {
+                        warn!("Failed to reload currencies: {}", e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::rustlers::MarketHourType};
+
+    fn quote(market: &str, id: &str, price: f64) -> Quote {
+        Quote {
+            id: id.into(),
+            market: market.into(),
+            price,
+            change_percent: 1.5,
+            time: 0,
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume: None,
+            stats: None,
+        }
+    }
+
+    fn ticker(market: &str, symbol: &str, currency: Option<&str>) -> Ticker {
+        Ticker {
+            symbol: symbol.into(),
+            market: market.into(),
+            quote_asset: currency.map(Into::into),
+            provider_symbol: None,
+        }
+    }
+
+    fn normalizer(opts: FxOpts) -> FxNormalizer {
+        let mut normalizer = FxNormalizer::new(opts);
+        normalizer.set_currencies(&[
+            ticker("BCBA", "GGAL", Some("ars")),
+            ticker("BINANCE", "BTC", Some("USDT")),
+            ticker("XETRA", "SAP", Some("EUR")),
+            ticker("NASDAQ", "AAPL", None),
+        ]);
+        normalizer
+    }
+
+    #[test]
+    fn currencies_are_uppercased() {
+        let normalizer = FxNormalizer::new(FxOpts {
+            base_currency: "ars".into(),
+            pivot_currency: Some("usd".into()),
+            ..Default::default()
+        });
+
+        assert_eq!(normalizer.opts().base_currency, "ARS");
+        assert_eq!(normalizer.opts().pivot_currency.as_deref(), Some("USD"));
+        assert_eq!(normalizer.rate("ARS", "ARS"), Some(1.0));
+    }
+
+    #[test]
+    fn pairs_are_split_by_the_known_currencies() {
+        let normalizer = normalizer(FxOpts::default());
+
+        assert_eq!(normalizer.split_pair("USDARS"), Some(("USD", "ARS")));
+        assert_eq!(normalizer.split_pair("USDTARS"), Some(("USDT", "ARS")));
+        assert_eq!(normalizer.split_pair("EURUSDT"), Some(("EUR", "USDT")));
+
+        // unknown currencies are split as iso codes
+        assert_eq!(normalizer.split_pair("GBPJPY"), Some(("GBP", "JPY")));
+        assert_eq!(normalizer.split_pair("GBPUSDC"), None);
+        assert_eq!(normalizer.split_pair("XX"), None);
+    }
+
+    #[test]
+    fn rates_are_direct_inverse_or_crossed_through_the_pivot() {
+        let mut normalizer = normalizer(FxOpts::default());
+        normalizer.on_quote(quote("FX", "USDARS", 1000.0));
+        normalizer.on_quote(quote("FX", "EURUSD", 1.25));
+
+        assert_eq!(normalizer.rate("USD", "ARS"), Some(1000.0));
+        assert_eq!(normalizer.rate("ARS", "USD"), Some(0.001));
+        assert_eq!(normalizer.rate("EUR", "USD"), Some(1.25));
+        assert_eq!(normalizer.rate("EUR", "ARS"), Some(1250.0));
+        assert!((normalizer.rate("ARS", "EUR").unwrap() - 0.0008).abs() < 1e-12);
+
+        // there's no rate to cross USDT through the pivot
+        assert_eq!(normalizer.rate("USDT", "ARS"), None);
+
+        // nor a pivot to cross through
+        let mut unpivoted = FxNormalizer::new(FxOpts {
+            pivot_currency: None,
+            ..Default::default()
+        });
+        unpivoted.on_quote(quote("FX", "USDARS", 1000.0));
+        unpivoted.on_quote(quote("FX", "EURUSD", 1.25));
+        assert_eq!(unpivoted.rate("EUR", "ARS"), None);
+    }
+
+    #[test]
+    fn quotes_are_normalized_once_their_rate_is_known() {
+        let mut normalizer = normalizer(FxOpts {
+            base_currency: "usd".into(),
+            ..Default::default()
+        });
+
+        // missing rates hold the quote back, unless it's already in the base currency
+        assert!(normalizer.on_quote(quote("BCBA", "GGAL", 5000.0)).is_empty());
+        let aapl = normalizer.on_quote(quote("NASDAQ", "AAPL", 200.0));
+        assert_eq!(aapl[0].price, 200.0);
+
+        // the rate normalizes the last quote of the tickers quoted in its currencies
+        let normalized = normalizer.on_quote(quote("FX", "USDARS", 1000.0));
+        assert_eq!(normalized.len(), 1);
+        assert_eq!(normalized[0].id, "GGAL");
+        assert_eq!(normalized[0].price, 5.0);
+        assert_eq!(normalized[0].change_percent, 1.5);
+
+        let ggal = normalizer.on_quote(quote("BCBA", "GGAL", 6000.0));
+        assert_eq!(ggal[0].price, 6.0);
+    }
+
+    #[test]
+    fn stale_rates_follow_the_policy() {
+        let opts = |on_stale| FxOpts {
+            stale_after: Duration::from_millis(20),
+            on_stale,
+            ..Default::default()
+        };
+        let mut skip = normalizer(opts(StaleFxPolicy::Skip));
+        let mut use_last = normalizer(opts(StaleFxPolicy::UseLast));
+
+        for normalizer in [&mut skip, &mut use_last] {
+            normalizer.on_quote(quote("FX", "USDARS", 1000.0));
+            assert_eq!(normalizer.rate("ARS", "USD"), Some(0.001));
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(skip.rate("ARS", "USD"), None);
+        assert!(skip.on_quote(quote("BCBA", "GGAL", 5000.0)).is_empty());
+
+        assert_eq!(use_last.rate("ARS", "USD"), Some(0.001));
+        assert_eq!(
+            use_last.on_quote(quote("BCBA", "GGAL", 5000.0))[0].price,
+            5.0
+        );
+
+        // a new rate is fresh again
+        skip.on_quote(quote("FX", "USDARS", 2000.0));
+        assert_eq!(skip.on_quote(quote("BCBA", "GGAL", 5000.0))[0].price, 2.5);
+    }
+}
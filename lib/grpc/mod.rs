@@ -4,6 +4,10 @@ mod services {
         sea_orm::{DbErr, SqlErr},
     };
 
+    /// alert rule grpc services
+    pub mod alert_rule;
+    /// history grpc services
+    pub mod history;
     /// market grpc services
     pub mod market;
     /// ticker grpc services
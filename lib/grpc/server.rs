@@ -1,7 +1,8 @@
 use {
     crate::{
-        entities::{market, ticker},
+        entities::{alert_rule, market, ticker},
         grpc::services,
+        history::HistoryReader,
     },
     eyre::Result,
     lool::{cli::stylize::Stylize, logger::info},
@@ -30,9 +31,13 @@ pub async fn start(conn: DatabaseConnection) -> Result<()> {
 
     let market_db = market::Service::new(conn.clone()).await;
     let ticker_db = ticker::Service::new(conn.clone()).await;
+    let alert_rule_db = alert_rule::Service::new(conn.clone()).await;
+    let history_db = HistoryReader::new(conn.clone()).await;
 
     let market_grpc = services::market::GrpcServer { svc: market_db };
     let ticker_grpc = services::ticker::GrpcServer { svc: ticker_db };
+    let alert_rule_grpc = services::alert_rule::GrpcServer { svc: alert_rule_db };
+    let history_grpc = services::history::GrpcServer { svc: history_db };
 
     info!(
         "🎉 gRPC server listening on {}",
@@ -42,6 +47,8 @@ pub async fn start(conn: DatabaseConnection) -> Result<()> {
     Server::builder()
         .add_service(market_grpc.svc()) // add the market api
         .add_service(ticker_grpc.svc()) // add the ticker api
+        .add_service(alert_rule_grpc.svc()) // add the alert rule api
+        .add_service(history_grpc.svc()) // add the history api
         .serve(addr)
         .await?;
 
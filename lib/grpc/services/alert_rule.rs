@@ -0,0 +1,156 @@
+use {
+    crate::{alerts::AlertCondition, entities::alert_rule, grpc::services::handle_sql_err},
+    alert_mod::{
+        alert_rule_api_server::{AlertRuleApi, AlertRuleApiServer},
+        AlertRule, AlertRuleId, AlertRules, Empty,
+    },
+    eyre::Result,
+    lool::logger::{error, info},
+    std::{any::Any, fmt::Debug, time::Instant},
+    tonic::{Request, Response, Status},
+};
+
+pub mod alert_mod {
+    tonic::include_proto!("alert");
+}
+
+impl AlertRule {
+    /// 🐎 » converts an `AlertRule` entity from gRPC to a database sea-orm `alert_rule::Model`
+    ///
+    /// fails with `invalid_argument` if the condition is unknown
+    fn into_model(self) -> Result<alert_rule::Model, Status> {
+        if let Err(e) = self.condition.parse::<AlertCondition>() {
+            return Err(Status::invalid_argument(e.to_string()));
+        }
+
+        Ok(alert_rule::Model {
+            id: self.id,
+            ticker_id: self.ticker_id,
+            condition: self.condition,
+            threshold: self.threshold,
+            cooldown: self.cooldown,
+            one_shot: self.one_shot,
+            active: self.active,
+            last_triggered_at: self.last_triggered_at,
+        })
+    }
+
+    /// 🐎 » converts an `alert_rule::Model` database entity to a gRPC `AlertRule` entity
+    fn from_model(model: alert_rule::Model) -> Self {
+        Self {
+            id: model.id,
+            ticker_id: model.ticker_id,
+            condition: model.condition,
+            threshold: model.threshold,
+            cooldown: model.cooldown,
+            one_shot: model.one_shot,
+            active: model.active,
+            last_triggered_at: model.last_triggered_at,
+        }
+    }
+}
+
+/// 🐎 » grpc Server to manage alert rule entities
+pub struct GrpcServer {
+    pub(crate) svc: alert_rule::Service,
+}
+
+impl GrpcServer {
+    pub fn log_if_err<T: Any, K: Debug>(&self, res: &Result<T, K>) {
+        if let Err(err) = &res {
+            error!("{:?}", err);
+        }
+    }
+
+    /// 🐎 » creates the alert rule api server
+    pub fn svc(self) -> AlertRuleApiServer<GrpcServer> {
+        AlertRuleApiServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl AlertRuleApi for GrpcServer {
+    /// retrieves and returns an alert rule entity from the database, given its id
+    async fn get(&self, req: Request<AlertRuleId>) -> Result<Response<AlertRule>, Status> {
+        let start = Instant::now();
+        let rule = req.into_inner();
+        let result = self.svc.get(rule.id).await;
+        self.log_if_err(&result);
+
+        let response = match result {
+            Ok(Some(r)) => Ok(Response::new(AlertRule::from_model(r))),
+            Ok(None) => Err(Status::not_found("Alert rule not found")),
+            Err(err) => Err(handle_sql_err(err, "Getting", "alert rule")),
+        };
+
+        info!("`AlertRuleApi.get` took {:?}", start.elapsed());
+        response
+    }
+
+    /// retrieves and returns all alert rule entities from the database
+    async fn get_all(&self, _: Request<Empty>) -> Result<Response<AlertRules>, Status> {
+        let start = Instant::now();
+        let result = self.svc.get_all().await;
+        self.log_if_err(&result);
+
+        let response = match result {
+            Ok(rules) => Ok(Response::new(AlertRules {
+                rules: rules.into_iter().map(AlertRule::from_model).collect(),
+            })),
+            Err(err) => Err(handle_sql_err(err, "Getting", "alert rules")),
+        };
+
+        info!("`AlertRuleApi.get_all` took {:?}", start.elapsed());
+        response
+    }
+
+    /// creates a new alert rule entity in the database
+    async fn create(&self, req: Request<AlertRule>) -> Result<Response<AlertRule>, Status> {
+        let start = Instant::now();
+        let rule = req.into_inner().into_model()?;
+        let result = self.svc.create(rule).await;
+        self.log_if_err(&result);
+
+        let response = match result {
+            Ok(r) => Ok(Response::new(AlertRule::from_model(r))),
+            Err(err) => Err(handle_sql_err(err, "creating", "alert rule")),
+        };
+
+        info!("`AlertRuleApi.create` took {:?}", start.elapsed());
+        response
+    }
+
+    /// updates an existing alert rule entity in the database
+    async fn update(&self, req: Request<AlertRule>) -> Result<Response<AlertRule>, Status> {
+        let start = Instant::now();
+        let rule = req.into_inner().into_model()?;
+        let result = self.svc.update(rule).await;
+        self.log_if_err(&result);
+
+        let response = match result {
+            Ok(r) => Ok(Response::new(AlertRule::from_model(r))),
+            Err(sea_orm::DbErr::RecordNotUpdated) => Err(Status::not_found("Alert rule not found")),
+            Err(err) => Err(handle_sql_err(err, "updating", "alert rule")),
+        };
+
+        info!("`AlertRuleApi.update` took {:?}", start.elapsed());
+        response
+    }
+
+    /// deletes an alert rule entity from the database, given its id
+    async fn delete(&self, req: Request<AlertRuleId>) -> Result<Response<Empty>, Status> {
+        let start = Instant::now();
+        let rule = req.into_inner();
+        let result = self.svc.delete(rule.id).await;
+        self.log_if_err(&result);
+
+        let response = match result {
+            Ok(true) => Ok(Response::new(Empty {})),
+            Ok(false) => Err(Status::not_found("Alert rule not found")),
+            Err(err) => Err(handle_sql_err(err, "deleting", "alert rule")),
+        };
+
+        info!("`AlertRuleApi.delete` took {:?}", start.elapsed());
+        response
+    }
+}
@@ -0,0 +1,98 @@
+use {
+    crate::{
+        bus::ToFromProto,
+        candles::Resolution,
+        history::{HistoryRange, HistoryReader},
+    },
+    futures::{Stream, StreamExt},
+    history_mod::{
+        history_api_server::{HistoryApi, HistoryApiServer},
+        CandlePage, CandleRange, QuotePage, QuoteRange,
+    },
+    lool::logger::{error, info},
+    std::{pin::Pin, sync::Arc, time::Instant},
+    tonic::{Request, Response, Status},
+};
+
+pub mod history_mod {
+    tonic::include_proto!("history");
+}
+
+type PageStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// 🐎 » grpc Server to query the stored quote and candle history
+pub struct GrpcServer {
+    pub(crate) svc: Arc<HistoryReader>,
+}
+
+impl GrpcServer {
+    /// 🐎 » creates the history api server
+    pub fn svc(self) -> HistoryApiServer<GrpcServer> {
+        HistoryApiServer::new(self)
+    }
+}
+
+/// maps the errors of a page stream to an internal `Status`, logging them
+fn into_status(err: eyre::Report) -> Status {
+    error!("{:?}", err);
+    Status::internal("Error reading history")
+}
+
+#[tonic::async_trait]
+impl HistoryApi for GrpcServer {
+    type GetQuotesStream = PageStream<QuotePage>;
+    type GetCandlesStream = PageStream<CandlePage>;
+
+    /// streams the stored quotes of a ticker in a time range, one page per message
+    async fn get_quotes(
+        &self,
+        req: Request<QuoteRange>,
+    ) -> Result<Response<Self::GetQuotesStream>, Status> {
+        let start = Instant::now();
+        let req = req.into_inner();
+        let range = HistoryRange {
+            market: req.market,
+            symbol: req.symbol,
+            from: req.from,
+            to: req.to,
+            page_size: req.page_size,
+        };
+
+        let stream = self.svc.quotes(range).map(|page| {
+            let quotes = page.map_err(into_status)?.iter().map(ToFromProto::to_proto).collect();
+            Ok(QuotePage { quotes })
+        });
+
+        info!("`HistoryApi.get_quotes` took {:?}", start.elapsed());
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    /// streams the stored candles of a ticker in a time range, one page per message
+    async fn get_candles(
+        &self,
+        req: Request<CandleRange>,
+    ) -> Result<Response<Self::GetCandlesStream>, Status> {
+        let start = Instant::now();
+        let req = req.into_inner();
+        let resolution = req
+            .resolution
+            .parse::<Resolution>()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let range = HistoryRange {
+            market: req.market,
+            symbol: req.symbol,
+            from: req.from,
+            to: req.to,
+            page_size: req.page_size,
+        };
+
+        let stream = self.svc.candles(range, resolution).map(|page| {
+            let candles = page.map_err(into_status)?.iter().map(ToFromProto::to_proto).collect();
+            Ok(CandlePage { candles })
+        });
+
+        info!("`HistoryApi.get_candles` took {:?}", start.elapsed());
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
@@ -30,6 +30,7 @@ impl Market {
             pre_market_offset: self.pre_market_offset,
             post_market_offset: self.post_market_offset,
             time_zone_offset: self.time_zone_offset,
+            change_reference: self.change_reference,
         }
     }
 
@@ -47,6 +48,7 @@ impl Market {
             pre_market_offset: model.pre_market_offset,
             post_market_offset: model.post_market_offset,
             time_zone_offset: model.time_zone_offset,
+            change_reference: model.change_reference,
         }
     }
 }
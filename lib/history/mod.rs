@@ -0,0 +1,457 @@
+//! 🐎 » quote history
+//!
+//! Stores the quotes of the bus in the `quote_history` table, either every tick or conflated
+//! snapshots, with batched inserts. Ticks of a ticker received in the same millisecond are
+//! conflated into the last one. Old history is downsampled into candles (stored in the
+//! `candle` table) and deleted after a configurable retention period.
+//!
+//! The stored history can be read through [`HistoryReader`], which is exposed by the gRPC
+//! `HistoryApi` and the socket history events (see [`crate::socket::history`]).
+
+use {
+    crate::{
+        bus::SubscriberTrait,
+        candles::{CandleAggregator, Resolution},
+        entities::{candle, market, quote_history, sea_orm::DatabaseConnection},
+        rustlers::{session::MarketSession, Quote},
+    },
+    chrono::Utc,
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::{collections::HashMap, time::Duration},
+    tokio::select,
+};
+
+mod query;
+
+pub use query::*;
+
+/// #### 🐎 » History options
+#[derive(Debug, Clone)]
+pub struct HistoryOpts {
+    /// if set, only the last quote of each ticker in every interval of this length is stored
+    pub conflate: Option<Duration>,
+    /// number of buffered quotes that triggers an insert
+    pub batch_size: usize,
+    /// max time a quote stays buffered before being inserted
+    pub flush_interval: Duration,
+    /// default time the history is kept (`None` to keep it forever)
+    pub retention: Option<Duration>,
+    /// retention by market name, overriding the default one
+    pub market_retention: HashMap<String, Duration>,
+    /// if set, history older than this is downsampled into candles and deleted
+    pub downsample_after: Option<Duration>,
+    /// resolution of the candles the old history is downsampled into
+    pub downsample_resolution: Resolution,
+    /// interval between runs of the retention and downsampling jobs
+    pub maintenance_interval: Duration,
+}
+
+impl Default for HistoryOpts {
+    fn default() -> Self {
+        Self {
+            conflate: None,
+            batch_size: 500,
+            flush_interval: Duration::from_secs(1),
+            retention: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            market_retention: HashMap::new(),
+            downsample_after: None,
+            downsample_resolution: Resolution::M1,
+            maintenance_interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// 🐎 » converts a quote to its history model
+pub fn to_model(quote: &Quote) -> quote_history::Model {
+    quote_history::Model {
+        market: quote.market.clone(),
+        symbol: quote.id.clone(),
+        time: quote.time,
+        price: quote.price,
+        change_percent: quote.change_percent,
+        volume: quote.volume,
+        market_hours: Into::<u8>::into(quote.market_hours.clone()) as i32,
+    }
+}
+
+/// 🐎 » converts a history model back to a quote
+pub fn from_model(model: quote_history::Model) -> Quote {
+    Quote {
+        id: model.symbol,
+        market: model.market,
+        price: model.price,
+        change_percent: model.change_percent,
+        time: model.time,
+        market_hours: (model.market_hours as u8).into(),
+        source: None,
+        volume: model.volume,
+        stats: None,
+    }
+}
+
+/// #### 🐎 » History buffer
+///
+/// Buffers the quotes to store, conflating them if configured. It doesn't do any i/o, so it can
+/// be fed with quotes from any source.
+#[derive(Debug, Default)]
+pub struct HistoryBuffer {
+    conflate: Option<i64>,
+    ready: Vec<quote_history::Model>,
+    /// last quote of each ticker in the current conflation interval
+    pending: HashMap<String, quote_history::Model>,
+}
+
+impl HistoryBuffer {
+    /// 🐎 » creates a buffer, conflating the quotes in intervals of the given length
+    pub fn new(conflate: Option<Duration>) -> Self {
+        Self {
+            conflate: conflate.map(|c| (c.as_millis() as i64).max(1)),
+            ..Default::default()
+        }
+    }
+
+    /// 🐎 » returns the number of quotes ready to be stored
+    pub fn len(&self) -> usize {
+        self.ready.len()
+    }
+
+    /// 🐎 » returns `true` if there are no quotes ready to be stored
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty()
+    }
+
+    /// 🐎 » adds a quote to the buffer
+    pub fn push(&mut self, quote: &Quote) {
+        let model = to_model(quote);
+
+        let Some(interval) = self.conflate else {
+            self.ready.push(model);
+            return;
+        };
+
+        let key = format!("{}:{}", model.market, model.symbol);
+        if let Some(previous) = self.pending.insert(key, model.clone()) {
+            if previous.time.div_euclid(interval) != model.time.div_euclid(interval) {
+                self.ready.push(previous);
+            }
+        }
+    }
+
+    /// 🐎 » takes the quotes ready to be stored, including the conflated quotes of intervals
+    /// that ended before the given time (unix timestamp in milliseconds)
+    pub fn take(&mut self, now: i64) -> Vec<quote_history::Model> {
+        if let Some(interval) = self.conflate {
+            let current = now.div_euclid(interval);
+            let ended: Vec<String> = self
+                .pending
+                .iter()
+                .filter(|(_, q)| q.time.div_euclid(interval) < current)
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in ended {
+                self.ready.extend(self.pending.remove(&key));
+            }
+        }
+
+        std::mem::take(&mut self.ready)
+    }
+}
+
+/// #### 🐎 » History service
+///
+/// Subscribes to the quotes of the bus and stores them, running the retention and downsampling
+/// jobs periodically.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut subscriber = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// subscriber.with_pattern("quote:*");
+///
+/// let opts = HistoryOpts { conflate: Some(Duration::from_secs(1)), ..Default::default() };
+/// HistorySvc::new(conn, opts).await?.run(subscriber).await?;
+/// ```
+pub struct HistorySvc {
+    opts: HistoryOpts,
+    buffer: HistoryBuffer,
+    history_svc: quote_history::Service,
+    candle_svc: candle::Service,
+    /// session of each market, by market name
+    sessions: HashMap<String, MarketSession>,
+}
+
+impl HistorySvc {
+    /// 🐎 » creates the service, loading the markets from the database
+    pub async fn new(conn: DatabaseConnection, opts: HistoryOpts) -> Result<Self> {
+        let sessions = market::Service::new(conn.clone())
+            .await
+            .get_all()
+            .await?
+            .iter()
+            .map(|m| {
+                let name = m.pub_name.clone().unwrap_or_else(|| m.short_name.clone());
+                (name, MarketSession::of(m))
+            })
+            .collect();
+
+        Ok(Self {
+            buffer: HistoryBuffer::new(opts.conflate),
+            opts,
+            history_svc: quote_history::Service::new(conn.clone()).await,
+            candle_svc: candle::Service::new(conn).await,
+            sessions,
+        })
+    }
+
+    /// 🐎 » stores the quotes of the subscriber until its stream ends
+    pub async fn run<S: SubscriberTrait<Quote>>(mut self, mut subscriber: S) -> Result<()> {
+        info!("Storing quote history");
+
+        let mut stream = subscriber.stream().await?;
+        let mut flush = tokio::time::interval(self.opts.flush_interval);
+        let mut maintenance = tokio::time::interval(self.opts.maintenance_interval);
+
+        loop {
+            select! {
+                quote = stream.next() => {
+                    let Some(quote) = quote else {
+                        break;
+                    };
+
+                    self.buffer.push(&quote);
+                    if self.buffer.len() >= self.opts.batch_size {
+                        self.flush().await;
+                    }
+                }
+                _ = flush.tick() => self.flush().await,
+                _ = maintenance.tick() => {
+                    if let Err(e) = self.maintain().await {
+                        warn!("Quote history maintenance failed: {}", e);
+                    }
+                }
+            }
+        }
+
+        self.flush().await;
+        Ok(())
+    }
+
+    /// inserts the buffered quotes
+    async fn flush(&mut self) {
+        let quotes = self.buffer.take(Utc::now().timestamp_millis());
+        if quotes.is_empty() {
+            return;
+        }
+
+        let count = quotes.len();
+        if let Err(e) = self.history_svc.insert_many(quotes).await {
+            warn!("Failed to store {} quotes: {}", count, e);
+        }
+    }
+
+    /// 🐎 » downsamples and deletes the old history
+    pub async fn maintain(&self) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+
+        if let Some(after) = self.opts.downsample_after {
+            self.downsample(now - after.as_millis() as i64).await?;
+        }
+
+        let mut markets: Vec<&String> = self.sessions.keys().collect();
+        let unknown = self.opts.market_retention.keys().filter(|m| !self.sessions.contains_key(*m));
+        markets.extend(unknown);
+
+        for market in markets {
+            let retention = self.opts.market_retention.get(market).or(self.opts.retention.as_ref());
+            let Some(retention) = retention else {
+                continue;
+            };
+
+            let deleted = self
+                .history_svc
+                .delete_market_before(market, now - retention.as_millis() as i64)
+                .await?;
+
+            if deleted > 0 {
+                info!("Deleted {} old quotes of market '{}'", deleted, market);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// aggregates the history older than the given time into candles and deletes it
+    ///
+    /// only complete candles are downsampled, so the history of the candle containing `before` is
+    /// kept until the next run. The history of each ticker is loaded one trading day at a time
+    /// (candles don't cross trading days), starting at its oldest quote.
+    async fn downsample(&self, before: i64) -> Result<()> {
+        let resolution = self.opts.downsample_resolution;
+
+        for (market, symbol) in self.history_svc.get_tickers_before(before).await? {
+            let session = self.sessions.get(&market).copied().unwrap_or_default();
+            let cut = resolution.align(before, &session);
+
+            // the downsampled days are deleted, so the oldest quote left starts the next one
+            while let Some(oldest) =
+                self.history_svc.get_page(&market, &symbol, i64::MIN, cut - 1, 1).await?.pop()
+            {
+                let end = session.end(session.trading_day(oldest.time)).min(cut);
+                let ticks =
+                    self.history_svc.get_range(&market, &symbol, oldest.time, end - 1).await?;
+
+                let mut aggregator = CandleAggregator::new(vec![resolution], true);
+                aggregator.set_session(&market, session);
+
+                let mut candles: Vec<candle::Model> = ticks
+                    .into_iter()
+                    .flat_map(|tick| aggregator.on_quote(&from_model(tick)))
+                    .filter(|c| c.closed)
+                    .map(|c| c.to_model())
+                    .collect();
+                candles.extend(aggregator.flush(i64::MAX).iter().map(|c| c.to_model()));
+
+                self.candle_svc.upsert_many(candles).await?;
+                self.history_svc.delete_ticker_before(&market, &symbol, end).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{entities::db, rustlers::MarketHourType},
+    };
+
+    /// 2026-03-06 00:00 utc
+    const DAY: i64 = 1_772_755_200_000;
+
+    fn quote(symbol: &str, time: i64, price: f64) -> Quote {
+        Quote {
+            id: symbol.into(),
+            market: "NASDAQ".into(),
+            price,
+            change_percent: 0.0,
+            time,
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume: None,
+            stats: None,
+        }
+    }
+
+    fn prices(models: &[quote_history::Model]) -> Vec<(&str, f64)> {
+        models.iter().map(|m| (m.symbol.as_str(), m.price)).collect()
+    }
+
+    #[test]
+    fn every_tick_is_stored_without_conflation() {
+        let mut buffer = HistoryBuffer::new(None);
+        buffer.push(&quote("AAPL", 100, 1.0));
+        buffer.push(&quote("AAPL", 100, 2.0));
+        buffer.push(&quote("AAPL", 200, 3.0));
+
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(
+            prices(&buffer.take(0)),
+            [("AAPL", 1.0), ("AAPL", 2.0), ("AAPL", 3.0)]
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn conflation_keeps_the_last_quote_of_each_interval() {
+        let mut buffer = HistoryBuffer::new(Some(Duration::from_secs(1)));
+
+        buffer.push(&quote("AAPL", 100, 1.0));
+        buffer.push(&quote("MSFT", 200, 10.0));
+        buffer.push(&quote("AAPL", 500, 2.0));
+        assert!(buffer.is_empty());
+
+        // a quote of the next interval makes the previous one ready
+        buffer.push(&quote("AAPL", 1200, 3.0));
+        assert_eq!(buffer.len(), 1);
+
+        // intervals still in progress are kept, the ended ones are taken
+        let mut taken = prices(&buffer.take(1500));
+        taken.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(taken, [("AAPL", 2.0), ("MSFT", 10.0)]);
+        assert!(buffer.take(1500).is_empty());
+        assert_eq!(prices(&buffer.take(2000)), [("AAPL", 3.0)]);
+    }
+
+    #[test]
+    fn conflated_intervals_are_taken_per_ticker() {
+        let mut buffer = HistoryBuffer::new(Some(Duration::from_secs(1)));
+        buffer.push(&quote("AAPL", 100, 1.0));
+        buffer.push(&quote("AAPL", 1100, 2.0));
+        buffer.push(&quote("MSFT", 1200, 10.0));
+
+        let mut taken = prices(&buffer.take(1999));
+        taken.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(taken, [("AAPL", 1.0)]);
+
+        let mut taken = prices(&buffer.take(2000));
+        taken.sort_by(|a, b| a.0.cmp(b.0));
+        assert_eq!(taken, [("AAPL", 2.0), ("MSFT", 10.0)]);
+    }
+
+    #[tokio::test]
+    async fn ticks_of_the_same_millisecond_keep_the_last_price() -> Result<()> {
+        let svc = quote_history::Service::new(db::in_memory().await).await;
+
+        let ticks = [quote("AAPL", 100, 1.0), quote("AAPL", 100, 2.0), quote("AAPL", 101, 3.0)];
+        svc.insert_many(ticks.iter().map(to_model).collect()).await?;
+        assert_eq!(
+            prices(&svc.get_range("NASDAQ", "AAPL", 0, 200).await?),
+            [("AAPL", 2.0), ("AAPL", 3.0)]
+        );
+
+        svc.insert_many(vec![to_model(&quote("AAPL", 100, 4.0))]).await?;
+        assert_eq!(
+            prices(&svc.get_range("NASDAQ", "AAPL", 0, 200).await?),
+            [("AAPL", 4.0), ("AAPL", 3.0)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downsampling_replaces_the_complete_candles_of_old_history() -> Result<()> {
+        let conn = db::in_memory().await;
+        let opts = HistoryOpts {
+            downsample_resolution: Resolution::M1,
+            ..Default::default()
+        };
+        let svc = HistorySvc::new(conn.clone(), opts).await?;
+
+        let ticks = [(0, 1.0), (10, 3.0), (30, 2.0), (70, 5.0), (130, 4.0)];
+        let ticks =
+            ticks.iter().map(|(secs, price)| to_model(&quote("AAPL", DAY + secs * 1000, *price)));
+        svc.history_svc.insert_many(ticks.collect()).await?;
+
+        // the candle containing `before` isn't complete yet
+        svc.downsample(DAY + 125_000).await?;
+
+        let candles =
+            candle::Service::new(conn).await.get_range("NASDAQ", "AAPL", "1m", 0, i64::MAX).await?;
+        let ohlc: Vec<_> =
+            candles.iter().map(|c| (c.start, c.open, c.high, c.low, c.close)).collect();
+        assert_eq!(
+            ohlc,
+            [(DAY, 1.0, 3.0, 1.0, 2.0), (DAY + 60_000, 5.0, 5.0, 5.0, 5.0)]
+        );
+
+        let left = svc.history_svc.get_range("NASDAQ", "AAPL", 0, i64::MAX).await?;
+        assert_eq!(prices(&left), [("AAPL", 4.0)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use {
+    super::from_model,
+    crate::{
+        candles::{Candle, Resolution},
+        entities::{candle, quote_history, sea_orm::DatabaseConnection},
+        rustlers::Quote,
+    },
+    async_stream::try_stream,
+    eyre::Result,
+    futures::Stream,
+    serde::Deserialize,
+    std::sync::Arc,
+};
+
+/// default number of items per page
+pub const DEFAULT_PAGE_SIZE: u64 = 1000;
+/// max number of items per page
+pub const MAX_PAGE_SIZE: u64 = 10_000;
+
+/// #### 🐎 » History range
+///
+/// A query for the history of a ticker between two unix timestamps in milliseconds (inclusive)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRange {
+    pub market: String,
+    pub symbol: String,
+    pub from: i64,
+    pub to: i64,
+    /// number of items per page (defaults to [`DEFAULT_PAGE_SIZE`], capped to [`MAX_PAGE_SIZE`])
+    pub page_size: Option<u64>,
+}
+
+impl HistoryRange {
+    fn page_size(&self) -> u64 {
+        self.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// #### 🐎 » History reader
+///
+/// Reads the stored quotes and candles page by page, so large ranges can be streamed without
+/// loading them in memory at once. Used by the gRPC `HistoryApi` and the socket history events.
+pub struct HistoryReader {
+    quotes: quote_history::Service,
+    candles: candle::Service,
+}
+
+impl HistoryReader {
+    /// 🐎 » creates a new history reader
+    pub async fn new(conn: DatabaseConnection) -> Arc<Self> {
+        Arc::new(Self {
+            quotes: quote_history::Service::new(conn.clone()).await,
+            candles: candle::Service::new(conn).await,
+        })
+    }
+
+    /// 🐎 » streams the quotes of a ticker in the given range, in pages sorted by time
+    pub fn quotes(
+        self: &Arc<Self>,
+        range: HistoryRange,
+    ) -> impl Stream<Item = Result<Vec<Quote>>> + Send + 'static {
+        let reader = self.clone();
+        let limit = range.page_size();
+
+        try_stream! {
+            let mut from = range.from;
+
+            loop {
+                let page = reader
+                    .quotes
+                    .get_page(&range.market, &range.symbol, from, range.to, limit)
+                    .await?;
+
+                let Some(last) = page.last() else {
+                    break;
+                };
+
+                from = last.time + 1;
+                let full = page.len() as u64 == limit;
+                yield page.into_iter().map(from_model).collect::<Vec<_>>();
+
+                if !full {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// 🐎 » streams the stored candles of a ticker in the given range, in pages sorted by start
+    /// time
+    pub fn candles(
+        self: &Arc<Self>,
+        range: HistoryRange,
+        resolution: Resolution,
+    ) -> impl Stream<Item = Result<Vec<Candle>>> + Send + 'static {
+        let reader = self.clone();
+        let limit = range.page_size();
+
+        try_stream! {
+            let mut from = range.from;
+
+            loop {
+                let (market, symbol) = (&range.market, &range.symbol);
+                let page = reader
+                    .candles
+                    .get_page(market, symbol, resolution.as_str(), from, range.to, limit)
+                    .await?;
+
+                let Some(last) = page.last() else {
+                    break;
+                };
+
+                from = last.start + 1;
+                let full = page.len() as u64 == limit;
+                yield page.into_iter().map(Candle::from_model).collect::<Result<Vec<_>>>()?;
+
+                if !full {
+                    break;
+                }
+            }
+        }
+    }
+}
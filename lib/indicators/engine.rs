@@ -0,0 +1,154 @@
+use {
+    super::{Indicator, IndicatorConfig, IndicatorValue},
+    crate::{
+        candles::{Candle, Resolution},
+        rustlers::Quote,
+    },
+    std::collections::HashMap,
+};
+
+/// an indicator being computed
+#[derive(Debug)]
+struct Tracked {
+    config: IndicatorConfig,
+    indicator: Indicator,
+    /// time of the last value the indicator was warmed up with
+    warmed_until: Option<i64>,
+}
+
+/// #### 🐎 » Indicator engine
+///
+/// Computes the configured indicators of every ticker incrementally. It doesn't do any i/o, so it
+/// can be fed with live or replayed quotes and candles (see [`super::IndicatorSvc`] for the bus
+/// based service).
+///
+/// Indicators without a resolution are updated with the price of every quote of their ticker,
+/// and the ones with a resolution with the close of every closed candle of that resolution
+/// (candles in progress are ignored).
+#[derive(Debug, Default)]
+pub struct IndicatorEngine {
+    /// indicators by ticker key
+    indicators: HashMap<String, Vec<Tracked>>,
+}
+
+impl IndicatorEngine {
+    /// 🐎 » sets the indicators to compute
+    ///
+    /// the state of the indicators whose configuration didn't change is kept; returns the
+    /// configurations of the indicators that start from an empty state, which can be warmed up
+    /// with past values (see [`Self::warm_up`])
+    pub fn set_configs(&mut self, configs: Vec<IndicatorConfig>) -> Vec<IndicatorConfig> {
+        let mut fresh = vec![];
+        let mut previous: HashMap<String, Tracked> = self
+            .indicators
+            .drain()
+            .flat_map(|(_, tracked)| tracked)
+            .map(|tracked| (tracked.config.id.clone(), tracked))
+            .collect();
+
+        for config in configs {
+            let tracked = match previous.remove(&config.id) {
+                Some(tracked) if tracked.config == config => tracked,
+                _ => {
+                    fresh.push(config.clone());
+                    Tracked {
+                        indicator: Indicator::new(&config),
+                        config,
+                        warmed_until: None,
+                    }
+                }
+            };
+
+            self.indicators.entry(tracked.config.ticker.clone()).or_default().push(tracked);
+        }
+
+        fresh
+    }
+
+    /// 🐎 » warms up an indicator with past values (`(time, value)`, oldest first) without
+    /// producing outputs, e.g. from the stored history of a new indicator
+    ///
+    /// later values that aren't newer than the last warm-up value are ignored, so values that
+    /// were both stored and streamed aren't counted twice
+    pub fn warm_up(&mut self, config_id: &str, values: &[(i64, f64)]) {
+        let tracked = self.indicators.values_mut().flatten().find(|t| t.config.id == config_id);
+        let (Some(tracked), Some((last, _))) = (tracked, values.last()) else {
+            return;
+        };
+
+        for (_, value) in values {
+            tracked.indicator.update(*value);
+        }
+
+        tracked.warmed_until = Some(*last);
+    }
+
+    /// 🐎 » returns the number of indicators being computed
+    pub fn len(&self) -> usize {
+        self.indicators.values().map(Vec::len).sum()
+    }
+
+    /// 🐎 » returns `true` if there are no indicators being computed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 🐎 » updates the quote based indicators of the quote's ticker, returning their new values
+    pub fn on_quote(&mut self, quote: &Quote) -> Vec<IndicatorValue> {
+        self.update(&quote.market, &quote.id, None, quote.time, quote.price)
+    }
+
+    /// 🐎 » updates the indicators of the candle's ticker and resolution, returning their new
+    /// values
+    pub fn on_candle(&mut self, candle: &Candle) -> Vec<IndicatorValue> {
+        if !candle.closed {
+            return vec![];
+        }
+
+        let resolution = Some(candle.resolution);
+        self.update(
+            &candle.market,
+            &candle.symbol,
+            resolution,
+            candle.start,
+            candle.close,
+        )
+    }
+
+    /// updates the indicators of a ticker fed by candles of the given resolution (or by quotes
+    /// if `None`)
+    fn update(
+        &mut self,
+        market: &str,
+        symbol: &str,
+        resolution: Option<Resolution>,
+        time: i64,
+        price: f64,
+    ) -> Vec<IndicatorValue> {
+        let Some(tracked) = self.indicators.get_mut(&format!("{}:{}", market, symbol)) else {
+            return vec![];
+        };
+
+        tracked
+            .iter_mut()
+            .filter(|t| t.config.resolution == resolution)
+            .filter(|t| !t.warmed_until.is_some_and(|until| time <= until))
+            .filter_map(|t| {
+                let output = t.indicator.update(price)?;
+
+                Some(IndicatorValue {
+                    config_id: t.config.id.clone(),
+                    market: market.to_owned(),
+                    symbol: symbol.to_owned(),
+                    kind: t.config.kind,
+                    period: t.config.period,
+                    resolution,
+                    time,
+                    value: output.value,
+                    upper: output.upper,
+                    lower: output.lower,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,464 @@
+use {
+    crate::{candles::Resolution, entities::indicator_config},
+    eyre::Result,
+    lool::fail,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::VecDeque,
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// default standard deviation multiplier of the Bollinger bands
+pub const DEFAULT_BOLLINGER_MULTIPLIER: f64 = 2.0;
+
+/// number of periods the smoothed indicators (EMA and RSI) are warmed up with, so their
+/// averages converge to the ones computed over the whole history
+const WARM_UP_PERIODS: usize = 4;
+
+/// #### 🐎 » Indicator kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorKind {
+    /// simple moving average
+    Sma,
+    /// exponential moving average
+    Ema,
+    /// relative strength index (Wilder's smoothing)
+    Rsi,
+    /// Bollinger bands (middle band plus upper and lower bands)
+    Bollinger,
+}
+
+impl IndicatorKind {
+    /// 🐎 » returns the string representation of the kind (e.g. `sma`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sma => "sma",
+            Self::Ema => "ema",
+            Self::Rsi => "rsi",
+            Self::Bollinger => "bollinger",
+        }
+    }
+}
+
+impl FromStr for IndicatorKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sma" => Ok(Self::Sma),
+            "ema" => Ok(Self::Ema),
+            "rsi" => Ok(Self::Rsi),
+            "bollinger" => Ok(Self::Bollinger),
+            _ => fail!("Unknown indicator `{}`", s),
+        }
+    }
+}
+
+impl Display for IndicatorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// #### 🐎 » Indicator configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorConfig {
+    pub id: String,
+    /// key of the ticker (`market:symbol`)
+    pub ticker: String,
+    pub kind: IndicatorKind,
+    /// number of values (quotes or candles) the indicator is computed over
+    pub period: usize,
+    /// resolution of the candles the indicator is computed from (`None` for quotes)
+    pub resolution: Option<Resolution>,
+    /// standard deviation multiplier of the Bollinger bands
+    pub multiplier: f64,
+}
+
+impl IndicatorConfig {
+    /// 🐎 » creates a configuration from its database model, given the key of its ticker
+    pub fn from_model(model: &indicator_config::Model, ticker: String) -> Result<Self> {
+        if model.period < 1 {
+            fail!("Invalid period {}", model.period);
+        }
+
+        Ok(Self {
+            id: model.id.clone(),
+            ticker,
+            kind: model.kind.parse()?,
+            period: model.period as usize,
+            resolution: model.resolution.as_deref().map(str::parse).transpose()?,
+            multiplier: model.multiplier.unwrap_or(DEFAULT_BOLLINGER_MULTIPLIER),
+        })
+    }
+
+    /// 🐎 » returns the number of past values the indicator is warmed up with: a window for
+    /// the windowed indicators and a few periods for the smoothed ones
+    pub fn warm_up_len(&self) -> usize {
+        match self.kind {
+            IndicatorKind::Sma | IndicatorKind::Bollinger => self.period,
+            IndicatorKind::Ema => self.period * WARM_UP_PERIODS,
+            // the first value only sets the previous price
+            IndicatorKind::Rsi => self.period * WARM_UP_PERIODS + 1,
+        }
+    }
+}
+
+/// #### 🐎 » Indicator output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Output {
+    pub value: f64,
+    /// upper band (Bollinger bands only)
+    pub upper: Option<f64>,
+    /// lower band (Bollinger bands only)
+    pub lower: Option<f64>,
+}
+
+impl Output {
+    fn value(value: f64) -> Self {
+        Self {
+            value,
+            upper: None,
+            lower: None,
+        }
+    }
+}
+
+/// #### 🐎 » Indicator
+///
+/// Incremental state of an indicator: every new value updates it in constant time, and an output
+/// is produced once enough values were received.
+#[derive(Debug, Clone)]
+pub enum Indicator {
+    Sma {
+        window: Window,
+    },
+    Ema {
+        period: usize,
+        /// values received until the average is seeded
+        seed: Vec<f64>,
+        ema: Option<f64>,
+    },
+    Rsi {
+        period: usize,
+        last: Option<f64>,
+        /// gains and losses received until the averages are seeded
+        seed: Vec<(f64, f64)>,
+        avg_gain: f64,
+        avg_loss: f64,
+    },
+    Bollinger {
+        window: Window,
+        multiplier: f64,
+    },
+}
+
+impl Indicator {
+    /// 🐎 » creates the empty state of an indicator
+    pub fn new(config: &IndicatorConfig) -> Self {
+        let period = config.period;
+
+        match config.kind {
+            IndicatorKind::Sma => Self::Sma {
+                window: Window::new(period),
+            },
+            IndicatorKind::Ema => Self::Ema {
+                period,
+                seed: Vec::with_capacity(period),
+                ema: None,
+            },
+            IndicatorKind::Rsi => Self::Rsi {
+                period,
+                last: None,
+                seed: Vec::with_capacity(period),
+                avg_gain: 0.0,
+                avg_loss: 0.0,
+            },
+            IndicatorKind::Bollinger => Self::Bollinger {
+                window: Window::new(period),
+                multiplier: config.multiplier,
+            },
+        }
+    }
+
+    /// 🐎 » updates the indicator with a new value, returning its output once it's ready
+    pub fn update(&mut self, value: f64) -> Option<Output> {
+        match self {
+            Self::Sma { window } => {
+                window.push(value);
+                window.mean().map(Output::value)
+            }
+            Self::Ema { period, seed, ema } => {
+                let next = match *ema {
+                    Some(previous) => {
+                        let alpha = 2.0 / (*period as f64 + 1.0);
+                        previous + alpha * (value - previous)
+                    }
+                    // the first average is the simple average of the first `period` values
+                    None => {
+                        seed.push(value);
+                        if seed.len() < *period {
+                            return None;
+                        }
+
+                        let sma = seed.iter().sum::<f64>() / *period as f64;
+                        seed.clear();
+                        sma
+                    }
+                };
+
+                *ema = Some(next);
+                Some(Output::value(next))
+            }
+            Self::Rsi {
+                period,
+                last,
+                seed,
+                avg_gain,
+                avg_loss,
+            } => {
+                let previous = last.replace(value)?;
+                let change = value - previous;
+                let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+                let n = *period as f64;
+
+                if seed.len() < *period {
+                    seed.push((gain, loss));
+                    if seed.len() < *period {
+                        return None;
+                    }
+
+                    *avg_gain = seed.iter().map(|(g, _)| g).sum::<f64>() / n;
+                    *avg_loss = seed.iter().map(|(_, l)| l).sum::<f64>() / n;
+                } else {
+                    *avg_gain = (*avg_gain * (n - 1.0) + gain) / n;
+                    *avg_loss = (*avg_loss * (n - 1.0) + loss) / n;
+                }
+
+                let rsi = match (*avg_gain, *avg_loss) {
+                    // flat prices
+                    (gain, loss) if gain == 0.0 && loss == 0.0 => 50.0,
+                    (_, loss) if loss == 0.0 => 100.0,
+                    (gain, loss) => 100.0 - 100.0 / (1.0 + gain / loss),
+                };
+
+                Some(Output::value(rsi))
+            }
+            Self::Bollinger { window, multiplier } => {
+                window.push(value);
+                let (mean, std_dev) = (window.mean()?, window.std_dev()?);
+
+                Some(Output {
+                    value: mean,
+                    upper: Some(mean + *multiplier * std_dev),
+                    lower: Some(mean - *multiplier * std_dev),
+                })
+            }
+        }
+    }
+}
+
+/// #### 🐎 » Rolling window
+///
+/// Last `period` values, with their running mean and sum of squared deviations (updated with
+/// Welford's method). Both are recomputed from the values once every `period` evictions, so the
+/// rounding errors of the updates don't build up.
+#[derive(Debug, Clone)]
+pub struct Window {
+    period: usize,
+    values: VecDeque<f64>,
+    mean: f64,
+    /// sum of the squared deviations from the mean
+    m2: f64,
+    /// values evicted since the last recomputation
+    evicted: usize,
+}
+
+impl Window {
+    fn new(period: usize) -> Self {
+        Self {
+            period,
+            values: VecDeque::with_capacity(period + 1),
+            mean: 0.0,
+            m2: 0.0,
+            evicted: 0,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        self.values.push_back(value);
+        let n = self.values.len() as f64;
+        let delta = value - self.mean;
+        self.mean += delta / n;
+        self.m2 += delta * (value - self.mean);
+
+        if self.values.len() <= self.period {
+            return;
+        }
+
+        if let Some(old) = self.values.pop_front() {
+            let n = self.values.len() as f64;
+            let delta = old - self.mean;
+            self.mean -= delta / n;
+            self.m2 -= delta * (old - self.mean);
+        }
+
+        self.evicted += 1;
+        if self.evicted >= self.period {
+            self.recompute();
+        }
+    }
+
+    /// recomputes the mean and the squared deviations from the values of the window
+    fn recompute(&mut self) {
+        let n = self.values.len() as f64;
+        self.mean = self.values.iter().sum::<f64>() / n;
+        self.m2 = self.values.iter().map(|v| (v - self.mean).powi(2)).sum();
+        self.evicted = 0;
+    }
+
+    /// mean of the window, once it's full
+    fn mean(&self) -> Option<f64> {
+        (self.values.len() == self.period).then_some(self.mean)
+    }
+
+    /// population standard deviation of the window, once it's full
+    fn std_dev(&self) -> Option<f64> {
+        self.mean()?;
+
+        // rounding errors can make the deviations slightly negative
+        Some((self.m2 / self.period as f64).max(0.0).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indicator(kind: IndicatorKind, period: usize) -> Indicator {
+        Indicator::new(&IndicatorConfig {
+            id: "test".into(),
+            ticker: "NASDAQ:AAPL".into(),
+            kind,
+            period,
+            resolution: None,
+            multiplier: DEFAULT_BOLLINGER_MULTIPLIER,
+        })
+    }
+
+    /// feeds the values to the indicator, returning the outputs once it's ready
+    fn run(indicator: &mut Indicator, values: &[f64]) -> Vec<Output> {
+        values.iter().filter_map(|v| indicator.update(*v)).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() <= tolerance, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn sma() {
+        let outputs = run(
+            &mut indicator(IndicatorKind::Sma, 3),
+            &[1.0, 2.0, 3.0, 4.0, 8.0],
+        );
+        let values: Vec<f64> = outputs.iter().map(|o| o.value).collect();
+
+        assert_close(&values, &[2.0, 3.0, 5.0], 1e-12);
+        assert!(outputs.iter().all(|o| o.upper.is_none() && o.lower.is_none()));
+    }
+
+    #[test]
+    fn ema() {
+        // 10 day EMA example of StockCharts' ChartSchool
+        let closes = [
+            22.27, 22.19, 22.08, 22.17, 22.18, 22.13, 22.23, 22.43, 22.24, 22.29, 22.15, 22.39,
+            22.38, 22.61, 23.36, 24.05, 23.75, 23.83, 23.95, 23.63, 23.82, 23.87, 23.65, 23.19,
+            23.10, 23.33, 22.68, 23.10, 22.40, 22.17,
+        ];
+        let expected = [
+            22.22, 22.21, 22.24, 22.27, 22.33, 22.52, 22.80, 22.97, 23.13, 23.28, 23.34, 23.43,
+            23.51, 23.54, 23.47, 23.40, 23.39, 23.26, 23.23, 23.08, 22.92,
+        ];
+
+        let outputs = run(&mut indicator(IndicatorKind::Ema, 10), &closes);
+        let values: Vec<f64> = outputs.iter().map(|o| o.value).collect();
+
+        assert_close(&values, &expected, 0.01);
+    }
+
+    #[test]
+    fn rsi() {
+        // 14 day RSI example of StockCharts' ChartSchool, which rounds the averages to two
+        // decimals along the way
+        let closes = [
+            44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03,
+            45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25, 45.71, 46.45,
+            45.78, 45.35, 44.03, 44.18, 44.22, 44.57, 43.42, 42.66, 43.13,
+        ];
+        let expected = [
+            70.53, 66.32, 66.55, 69.41, 66.36, 57.97, 62.93, 63.26, 56.06, 62.38, 54.71, 50.42,
+            39.99, 41.46, 41.87, 45.46, 37.30, 33.08, 37.77,
+        ];
+
+        let outputs = run(&mut indicator(IndicatorKind::Rsi, 14), &closes);
+        let values: Vec<f64> = outputs.iter().map(|o| o.value).collect();
+
+        assert_close(&values, &expected, 0.1);
+    }
+
+    #[test]
+    fn rsi_bounds() {
+        let rising = run(&mut indicator(IndicatorKind::Rsi, 2), &[1.0, 2.0, 3.0, 4.0]);
+        assert!(rising.iter().all(|o| o.value == 100.0));
+
+        let flat = run(&mut indicator(IndicatorKind::Rsi, 2), &[1.0, 1.0, 1.0]);
+        assert_eq!(flat.iter().map(|o| o.value).collect::<Vec<_>>(), [50.0]);
+    }
+
+    #[test]
+    fn bollinger() {
+        // mean 5 and population standard deviation 2
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let outputs = run(&mut indicator(IndicatorKind::Bollinger, 8), &values);
+
+        assert_eq!(
+            outputs,
+            [Output {
+                value: 5.0,
+                upper: Some(9.0),
+                lower: Some(1.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn window_doesnt_drift() {
+        let mut window = Window::new(20);
+
+        // large prices with small moves, where a running sum of squares loses every digit
+        let values: Vec<f64> =
+            (0..200_007).map(|i| 1e8 + ((i * 7919) % 1000) as f64 / 100.0).collect();
+        for value in &values {
+            window.push(*value);
+        }
+
+        let last = &values[values.len() - 20..];
+        let mean = last.iter().sum::<f64>() / 20.0;
+        let std_dev = (last.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 20.0).sqrt();
+
+        assert!((window.mean().unwrap() - mean).abs() < 1e-6);
+        assert!((window.std_dev().unwrap() - std_dev).abs() < 1e-6);
+    }
+}
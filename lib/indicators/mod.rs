@@ -0,0 +1,191 @@
+//! 🐎 » technical indicators
+//!
+//! Computes the technical indicators (SMA, EMA, RSI and Bollinger bands) configured per ticker in
+//! the `indicator_config` table, incrementally from the quotes or the closed candles of the bus,
+//! and publishes their values as [`IndicatorValue`] bus messages. New indicators are warmed up with
+//! the stored candles of their resolution (or the stored quote history), so they don't need to
+//! wait for a full period of live values.
+
+use {
+    crate::{
+        bus::{PublisherTrait, SubscriberTrait},
+        candles::Candle,
+        entities::{candle, indicator_config, market, quote_history, sea_orm::DatabaseConnection},
+        rustlers::{Quote, Ticker},
+    },
+    eyre::Result,
+    futures::StreamExt,
+    lool::logger::{info, warn},
+    std::{collections::HashMap, time::Duration},
+    tokio::select,
+};
+
+mod engine;
+mod indicator;
+mod value;
+
+pub use {engine::IndicatorEngine, indicator::*, value::IndicatorValue};
+
+/// #### 🐎 » Indicators service
+///
+/// Subscribes to the quotes and candles of the bus, computes the active indicators stored in the
+/// database and publishes their values. Configurations are reloaded periodically, so changes
+/// made to the `indicator_config` table are picked up without restarting the service.
+///
+/// **Usage**
+///
+/// ```rust
+/// let mut quotes = bus::redis::subscriber::<Quote, _>(&redis).await?;
+/// quotes.with_pattern("quote:*");
+/// let mut candles = bus::redis::subscriber::<Candle, _>(&redis).await?;
+/// candles.with_pattern("candle:*");
+/// let publisher = bus::redis::publisher::<IndicatorValue, _>(&redis).await?;
+///
+/// IndicatorSvc::new(conn).await?.run(quotes, candles, publisher).await?;
+/// ```
+pub struct IndicatorSvc {
+    conn: DatabaseConnection,
+    config_svc: indicator_config::Service,
+    candle_svc: candle::Service,
+    history_svc: quote_history::Service,
+    engine: IndicatorEngine,
+    reload_interval: Duration,
+}
+
+impl IndicatorSvc {
+    /// 🐎 » creates the service, loading the active configurations from the database
+    pub async fn new(conn: DatabaseConnection) -> Result<Self> {
+        let mut svc = Self {
+            config_svc: indicator_config::Service::new(conn.clone()).await,
+            candle_svc: candle::Service::new(conn.clone()).await,
+            history_svc: quote_history::Service::new(conn.clone()).await,
+            conn,
+            engine: IndicatorEngine::default(),
+            reload_interval: Duration::from_secs(30),
+        };
+
+        svc.reload().await?;
+        Ok(svc)
+    }
+
+    /// 🐎 » sets the interval between configuration reloads (defaults to 30 seconds)
+    pub fn set_reload_interval(&mut self, interval: Duration) -> &mut Self {
+        self.reload_interval = interval;
+        self
+    }
+
+    /// 🐎 » reloads the active configurations from the database
+    ///
+    /// configurations with an unknown indicator, resolution or ticker are skipped, and the new
+    /// ones are warmed up with the stored values of their ticker
+    pub async fn reload(&mut self) -> Result<()> {
+        let markets = market::Service::new(self.conn.clone()).await.get_all_with_tickers().await?;
+        let keys: HashMap<String, String> = markets
+            .iter()
+            .flat_map(|(market, tickers)| {
+                tickers.iter().map(move |t| (t.id.clone(), Ticker::from(t, market).key()))
+            })
+            .collect();
+
+        let configs = self
+            .config_svc
+            .get_all_active()
+            .await?
+            .iter()
+            .filter_map(|model| {
+                let Some(key) = keys.get(&model.ticker_id) else {
+                    warn!("Skipping indicator '{}': ticker not found", model.id);
+                    return None;
+                };
+
+                match IndicatorConfig::from_model(model, key.clone()) {
+                    Ok(config) => Some(config),
+                    Err(e) => {
+                        warn!("Skipping indicator '{}': {}", model.id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let fresh = self.engine.set_configs(configs);
+        self.warm_up(fresh).await;
+        Ok(())
+    }
+
+    /// warms up the given indicators with the last stored candles of their resolution, or the
+    /// last stored quotes if they don't have one
+    ///
+    /// indicators whose values can't be read start empty
+    async fn warm_up(&mut self, configs: Vec<IndicatorConfig>) {
+        for config in configs {
+            let Some((market, symbol)) = config.ticker.split_once(':') else {
+                continue;
+            };
+
+            let limit = config.warm_up_len() as u64;
+            let values: Result<Vec<(i64, f64)>, _> = match config.resolution {
+                Some(resolution) => {
+                    let resolution = resolution.as_str();
+                    let candles = self.candle_svc.get_last(market, symbol, resolution, limit).await;
+                    candles.map(|c| c.into_iter().map(|c| (c.start, c.close)).collect())
+                }
+                None => {
+                    let quotes = self.history_svc.get_last(market, symbol, limit).await;
+                    quotes.map(|q| q.into_iter().map(|q| (q.time, q.price)).collect())
+                }
+            };
+
+            match values {
+                Ok(values) => self.engine.warm_up(&config.id, &values),
+                Err(e) => warn!("Failed to warm up indicator '{}': {}", config.id, e),
+            }
+        }
+    }
+
+    /// 🐎 » computes the indicators until one of the subscribers' streams ends
+    pub async fn run<Q, C, P>(
+        mut self,
+        mut quotes: Q,
+        mut candles: C,
+        mut publisher: P,
+    ) -> Result<()>
+    where
+        Q: SubscriberTrait<Quote>,
+        C: SubscriberTrait<Candle>,
+        P: PublisherTrait<IndicatorValue>,
+    {
+        info!("Computing {} indicators", self.engine.len());
+
+        let mut quote_stream = quotes.stream().await?;
+        let mut candle_stream = candles.stream().await?;
+        let mut reload = tokio::time::interval(self.reload_interval);
+
+        loop {
+            let values = select! {
+                quote = quote_stream.next() => match quote {
+                    Some(quote) => self.engine.on_quote(&quote),
+                    None => break,
+                },
+                candle = candle_stream.next() => match candle {
+                    Some(candle) => self.engine.on_candle(&candle),
+                    None => break,
+                },
+                _ = reload.tick() => {
+                    if let Err(e) = self.reload().await {
+                        warn!("Failed to reload indicators: {}", e);
+                    }
+                    continue;
+                }
+            };
+
+            for value in values {
+                if let Err(e) = publisher.publish(value).await {
+                    warn!("Failed to publish indicator value: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
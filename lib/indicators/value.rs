@@ -0,0 +1,182 @@
+use {
+    super::IndicatorKind,
+    crate::{
+        bus::{
+            codec::proto, redis::stream::StreamMsg, BusFields, BusMessage, FromBusVal,
+            MessageFields, ToBusKey, ToBusVal, ToFromBusMessage, ToFromProto,
+        },
+        candles::Resolution,
+    },
+    eyre::Result,
+    lool::s,
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// #### 🐎 » Indicator value
+///
+/// Published to the bus every time an indicator of a ticker is updated.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndicatorValue {
+    /// id of the indicator configuration
+    pub config_id: String,
+    pub market: String,
+    pub symbol: String,
+    pub kind: IndicatorKind,
+    pub period: usize,
+    /// resolution of the candles the indicator is computed from (`None` for quotes)
+    pub resolution: Option<Resolution>,
+    /// time of the quote or start time of the candle that updated the indicator
+    pub time: i64,
+    pub value: f64,
+    /// upper band (Bollinger bands only)
+    pub upper: Option<f64>,
+    /// lower band (Bollinger bands only)
+    pub lower: Option<f64>,
+}
+
+impl ToBusVal for IndicatorValue {
+    fn to_bus_val(&self) -> Vec<(String, String)> {
+        vec![
+            (s!("config_id"), self.config_id.to_owned()),
+            (s!("market"), self.market.to_owned()),
+            (s!("symbol"), self.symbol.to_owned()),
+            (s!("kind"), self.kind.to_string()),
+            (s!("period"), self.period.to_string()),
+            (
+                s!("resolution"),
+                self.resolution.map(|r| r.to_string()).unwrap_or_default(),
+            ),
+            (s!("time"), self.time.to_string()),
+            (s!("value"), self.value.to_string()),
+            (
+                s!("upper"),
+                self.upper.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+            (
+                s!("lower"),
+                self.lower.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+impl FromBusVal for IndicatorValue {
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self> {
+        let fields = BusFields::new(fields);
+
+        Ok(Self {
+            config_id: fields.get("config_id")?.to_string(),
+            market: fields.get("market")?.to_string(),
+            symbol: fields.get("symbol")?.to_string(),
+            kind: fields.parse("kind")?,
+            period: fields.parse::<usize>("period")?,
+            resolution: fields.parse_opt("resolution")?,
+            time: fields.parse::<i64>("time")?,
+            value: fields.parse::<f64>("value")?,
+            upper: fields.parse_opt::<f64>("upper")?,
+            lower: fields.parse_opt::<f64>("lower")?,
+        })
+    }
+}
+
+impl ToBusKey for IndicatorValue {
+    fn to_bus_key(&self) -> String {
+        format!(
+            "indicator:{}:{}:{}",
+            self.market, self.symbol, self.config_id
+        )
+    }
+}
+
+impl ToFromBusMessage for IndicatorValue {
+    /// 🐎 » converts an `IndicatorValue` to a serialized message that can be sent over a redis
+    /// channel
+    ///
+    /// the message is in the format
+    /// `config_id¦market¦symbol¦kind¦period¦resolution¦time¦value¦upper¦lower`
+    fn as_message(&self) -> String {
+        format!(
+            "{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}",
+            self.config_id,
+            self.market,
+            self.symbol,
+            self.kind,
+            self.period,
+            self.resolution.map(|r| r.to_string()).unwrap_or_default(),
+            self.time,
+            self.value,
+            self.upper.map(|v| v.to_string()).unwrap_or_default(),
+            self.lower.map(|v| v.to_string()).unwrap_or_default(),
+        )
+    }
+
+    /// 🐎 » creates an `IndicatorValue` from a message
+    ///
+    /// fails if the message is not in the correct format
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self> {
+        let fields = MessageFields::new(msg.as_ref());
+
+        Ok(Self {
+            config_id: fields.get(0, "config_id")?.to_string(),
+            market: fields.get(1, "market")?.to_string(),
+            symbol: fields.get(2, "symbol")?.to_string(),
+            kind: fields.parse(3, "kind")?,
+            period: fields.parse::<usize>(4, "period")?,
+            resolution: fields.parse_opt(5, "resolution")?,
+            time: fields.parse::<i64>(6, "time")?,
+            value: fields.parse::<f64>(7, "value")?,
+            upper: fields.parse_opt::<f64>(8, "upper")?,
+            lower: fields.parse_opt::<f64>(9, "lower")?,
+        })
+    }
+}
+
+impl ToFromProto for IndicatorValue {
+    type Proto = proto::IndicatorValue;
+
+    /// 🐎 » converts an `IndicatorValue` to its protobuf message
+    fn to_proto(&self) -> proto::IndicatorValue {
+        proto::IndicatorValue {
+            config_id: self.config_id.clone(),
+            market: self.market.clone(),
+            symbol: self.symbol.clone(),
+            kind: self.kind.to_string(),
+            period: self.period as u64,
+            resolution: self.resolution.map(|r| r.to_string()),
+            time: self.time,
+            value: self.value,
+            upper: self.upper,
+            lower: self.lower,
+        }
+    }
+
+    /// 🐎 » creates an `IndicatorValue` from its protobuf message
+    ///
+    /// fails if the kind or the resolution are unknown
+    fn from_proto(value: proto::IndicatorValue) -> Result<Self> {
+        Ok(Self {
+            kind: value.kind.parse()?,
+            period: usize::try_from(value.period)?,
+            resolution: value.resolution.map(|r| r.parse()).transpose()?,
+            config_id: value.config_id,
+            market: value.market,
+            symbol: value.symbol,
+            time: value.time,
+            value: value.value,
+            upper: value.upper,
+            lower: value.lower,
+        })
+    }
+}
+
+impl StreamMsg for IndicatorValue {}
+impl BusMessage for IndicatorValue {
+    fn message_time(&self) -> Option<i64> {
+        Some(self.time)
+    }
+
+    fn topic() -> Option<&'static str> {
+        Some("indicator")
+    }
+}
@@ -1,7 +1,16 @@
 #![doc = include_str!("README.md")]
 
+// allows using the `rustler-core-macros` (which expand to `::rustler_core` paths) inside this crate
+extern crate self as rustler_core;
+
+pub mod alerts;
 pub mod bus;
+pub mod candles;
+pub mod derived;
 pub mod entities;
+pub mod fx;
 pub mod grpc;
+pub mod history;
+pub mod indicators;
 pub mod rustlers;
 pub mod socket;
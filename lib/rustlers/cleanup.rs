@@ -0,0 +1,175 @@
+//! 🐎 » cleanup of the last-value hashes of the bus
+//!
+//! The hashes of the quotes are kept in redis after their ticker is deactivated or removed from
+//! the database, so snapshot readers would keep seeing their last values. Publishers can expire
+//! them with a TTL (see [`crate::bus::ttl`]), and [`remove_orphaned_quotes`] deletes the ones left
+//! behind; the [`RustlersSvc`](super::svc::RustlersSvc) runs it on startup when it's set up to.
+
+use {
+    super::Ticker,
+    crate::{
+        bus::redis::{key, scan_hashes, RedisClient},
+        entities::{derived_instrument, market, sea_orm::DatabaseConnection},
+    },
+    eyre::Result,
+    lool::logger::info,
+    std::collections::HashSet,
+};
+
+/// number of keys scanned and deleted per round trip
+const BATCH: usize = 500;
+
+/// 🐎 » deletes the quote hashes under `prefix` whose ticker (`market:symbol`) isn't an active
+/// ticker or derived instrument of the database, returning the deleted keys
+///
+/// the prefix is the one the quotes were published with, e.g. `rustler` (the default) or
+/// [`crate::fx::normalized_prefix`] for the normalized quotes
+///
+/// **Usage**
+///
+/// ```rust
+/// let removed = cleanup::remove_orphaned_quotes(&redis, conn, "rustler").await?;
+/// ```
+pub async fn remove_orphaned_quotes<RC: RedisClient>(
+    redis: &RC,
+    conn: DatabaseConnection,
+    prefix: &str,
+) -> Result<Vec<String>> {
+    let active = active_tickers(conn).await?;
+
+    let quote_prefix = key(prefix, "quote:");
+    let mut conn = redis.get_client()?.get_multiplexed_tokio_connection().await?;
+    let keys = scan_hashes(&mut conn, &format!("{}*", quote_prefix), BATCH).await?;
+    let orphaned = orphaned(keys, &quote_prefix, &active);
+
+    for chunk in orphaned.chunks(BATCH) {
+        // unlink frees the memory in the background
+        let _: usize = redis::cmd("UNLINK").arg(chunk).query_async(&mut conn).await?;
+    }
+
+    info!(
+        "Removed {} orphaned quote keys under '{}'",
+        orphaned.len(),
+        quote_prefix
+    );
+    Ok(orphaned)
+}
+
+/// returns the tickers (`market:symbol`) whose quotes are published: the active tickers and
+/// derived instruments of the database
+async fn active_tickers(conn: DatabaseConnection) -> Result<HashSet<String>> {
+    let mut active = HashSet::new();
+
+    let markets = market::Service::new(conn.clone()).await.get_all_with_tickers().await?;
+    for (market, tickers) in markets {
+        let tickers = tickers.iter().filter(|t| t.active);
+        active.extend(tickers.map(|t| Ticker::from(t, &market).key()));
+    }
+
+    let instruments = derived_instrument::Service::new(conn).await.get_all_active().await?;
+    active.extend(instruments.iter().map(|i| format!("{}:{}", i.market, i.symbol)));
+
+    Ok(active)
+}
+
+/// returns the quote keys whose ticker isn't active, ignoring the keys outside `quote_prefix`
+fn orphaned(keys: Vec<String>, quote_prefix: &str, active: &HashSet<String>) -> Vec<String> {
+    keys.into_iter()
+        .filter(|k| k.strip_prefix(quote_prefix).is_some_and(|t| !active.contains(t)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::entities::{db, sea_orm::ActiveModelTrait, ticker},
+    };
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    fn market(id: &str, pub_name: Option<&str>) -> market::Model {
+        market::Model {
+            id: id.into(),
+            short_name: id.into(),
+            full_name: id.into(),
+            pub_name: pub_name.map(Into::into),
+            opens_from: None,
+            opens_till: None,
+            open_time: None,
+            close_time: None,
+            pre_market_offset: None,
+            post_market_offset: None,
+            time_zone_offset: None,
+            change_reference: None,
+        }
+    }
+
+    fn ticker(id: &str, market_id: &str, active: bool) -> ticker::Model {
+        ticker::Model {
+            id: format!("{}-{}", market_id, id),
+            symbol: id.into(),
+            quote_symbol: None,
+            market_id: market_id.into(),
+            active,
+        }
+    }
+
+    fn instrument(symbol: &str, active: bool) -> derived_instrument::Model {
+        derived_instrument::Model {
+            id: symbol.into(),
+            market: "DERIVED".into(),
+            symbol: symbol.into(),
+            formula: "NYSE:IBM * 2".into(),
+            active,
+        }
+    }
+
+    #[test]
+    fn only_the_quotes_of_inactive_tickers_are_orphaned() {
+        let active = HashSet::from(["NYSE:IBM".to_string(), "DERIVED:SPREAD".to_string()]);
+        let found = keys(&[
+            "rustler:quote:NYSE:IBM",
+            "rustler:quote:NYSE:DELISTED",
+            "rustler:quote:DERIVED:SPREAD",
+            "rustler:quote:DERIVED:RATIO",
+            "rustler:quote:NASDAQ:IBM",
+            "rustler:candle:1m:NYSE:DELISTED",
+        ]);
+
+        assert_eq!(
+            orphaned(found, "rustler:quote:", &active),
+            [
+                "rustler:quote:NYSE:DELISTED",
+                "rustler:quote:DERIVED:RATIO",
+                "rustler:quote:NASDAQ:IBM",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn the_active_tickers_and_derived_instruments_are_kept() -> Result<()> {
+        let conn = db::in_memory().await;
+
+        let markets = market::Service::new(conn.clone()).await;
+        markets.create(market("nyse", Some("NYSE"))).await?;
+        markets.create(market("bcba", None)).await?;
+
+        let tickers = ticker::Service::new(conn.clone()).await;
+        tickers.create(ticker("IBM", "nyse", true)).await?;
+        tickers.create(ticker("DELISTED", "nyse", false)).await?;
+        tickers.create(ticker("GGAL", "bcba", true)).await?;
+
+        for model in [instrument("SPREAD", true), instrument("RATIO", false)] {
+            derived_instrument::ActiveModel::from(model).insert(&conn).await?;
+        }
+
+        let active = active_tickers(conn).await?;
+        let expected = ["NYSE:IBM", "bcba:GGAL", "DERIVED:SPREAD"];
+        assert_eq!(active, HashSet::from(expected.map(String::from)));
+
+        Ok(())
+    }
+}
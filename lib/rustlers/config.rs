@@ -0,0 +1,210 @@
+use {
+    super::RustlerOpts,
+    crate::entities::{rustler_config, sea_orm::DatabaseConnection},
+    eyre::{Result, WrapErr},
+    lool::{
+        cli::stylize::Stylize,
+        fail,
+        logger::{info, warn},
+    },
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// the environment variable to set the path of a json file with rustler configurations
+const RUSTLER_CONFIG_FILE: &str = "RUSTLER_CONFIG_FILE";
+
+/// #### 🐎 » Rustler configuration
+///
+/// Typed configuration of a rustler. It's delivered to the rustler through
+/// [`Rustler::configure`](super::Rustler::configure) before it's started.
+///
+/// **Example** (json)
+///
+/// ```json
+/// {
+///     "endpoints": { "ws": "wss://stream.binance.com:9443/ws" },
+///     "credentials_ref": "BINANCE_API_KEY",
+///     "batch_size": 200,
+///     "opts": { "connect_on_start": false },
+///     "extra": { "depth": 5 }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RustlerConfig {
+    /// api endpoints used by the rustler, indexed by name (e.g. `ws`, `rest`)
+    pub endpoints: HashMap<String, String>,
+    /// name of the environment variable holding the rustler credentials
+    ///
+    /// the credentials themselves are never stored in the configuration
+    pub credentials_ref: Option<String>,
+    /// max number of tickers the rustler should handle in a single request or subscription
+    pub batch_size: Option<usize>,
+    /// the rustler options (see [`RustlerOpts`]); when missing, the rustler keeps the ones it was
+    /// created with
+    pub opts: Option<RustlerOpts>,
+    /// rustler specific settings (see [`RustlerConfig::extra_as`])
+    pub extra: serde_json::Value,
+}
+
+impl RustlerConfig {
+    /// 🐎 » parses a configuration from a json string
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config = serde_json::from_str(json)?;
+        Ok(config)
+    }
+
+    /// 🐎 » returns the endpoint with the given name, failing if it's not configured
+    pub fn endpoint(&self, name: &str) -> Result<&str> {
+        match self.endpoints.get(name) {
+            Some(endpoint) => Ok(endpoint.as_str()),
+            None => fail!("Endpoint `{}` is not configured", name),
+        }
+    }
+
+    /// 🐎 » resolves the credentials referenced by `credentials_ref`
+    ///
+    /// fails if there's no reference or if the referenced environment variable is not set
+    pub fn credentials(&self) -> Result<String> {
+        match &self.credentials_ref {
+            Some(var) => std::env::var(var)
+                .wrap_err_with(|| format!("Credentials env var `{}` is not set", var)),
+            None => fail!("No credentials reference configured"),
+        }
+    }
+
+    /// 🐎 » deserializes the rustler specific settings into `T`
+    pub fn extra_as<T: DeserializeOwned>(&self) -> Result<T> {
+        let extra = serde_json::from_value(self.extra.clone())?;
+        Ok(extra)
+    }
+}
+
+/// #### 🐎 » load rustler configurations
+///
+/// Loads the rustler configurations stored in the `rustler_config` table and, if the
+/// `RUSTLER_CONFIG_FILE` env var is set, from the json file it points to (an object where the keys
+/// are rustler names and the values are [`RustlerConfig`]s). Configurations from the file take
+/// precedence over the ones in the database.
+///
+/// Fails if any of the configurations can't be deserialized.
+pub async fn load(conn: DatabaseConnection) -> Result<HashMap<String, RustlerConfig>> {
+    let file = std::env::var(RUSTLER_CONFIG_FILE).ok();
+    if file.is_none() {
+        info!(
+            "`{}` not set, using rustler configurations from the database only",
+            RUSTLER_CONFIG_FILE.italic()
+        );
+    }
+
+    load_from(conn, file.as_deref()).await
+}
+
+/// loads the rustler configurations stored in the database and, if given, in a json file
+async fn load_from(
+    conn: DatabaseConnection,
+    file: Option<&str>,
+) -> Result<HashMap<String, RustlerConfig>> {
+    let svc = rustler_config::Service::new(conn).await;
+    let mut configs = HashMap::new();
+
+    for model in svc.get_all().await? {
+        let config = RustlerConfig::from_json(&model.config)
+            .wrap_err_with(|| format!("Invalid configuration for rustler '{}'", model.rustler))?;
+        configs.insert(model.rustler, config);
+    }
+
+    if let Some(path) = file {
+        info!("Loading rustler configurations from {}", path.green());
+        configs.extend(load_file(path)?);
+    }
+
+    if configs.is_empty() {
+        warn!("No rustler configurations found");
+    }
+
+    Ok(configs)
+}
+
+/// loads rustler configurations from a json file
+fn load_file(path: &str) -> Result<HashMap<String, RustlerConfig>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Could not read rustler config file {}", path))?;
+
+    serde_json::from_str(&content).wrap_err_with(|| format!("Invalid rustler config file {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::entities::db};
+
+    /// creates a database with the given configurations (rustler, json)
+    async fn database(configs: &[(&str, &str)]) -> DatabaseConnection {
+        let conn = db::in_memory().await;
+        let svc = rustler_config::Service::new(conn.clone()).await;
+
+        for (rustler, config) in configs {
+            let model = rustler_config::Model {
+                rustler: rustler.to_string(),
+                config: config.to_string(),
+            };
+            svc.upsert(model).await.unwrap();
+        }
+
+        conn
+    }
+
+    /// writes a configuration file, returning its path
+    fn file(content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rustlers-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn file_configs_take_precedence_over_the_database() {
+        let conn = database(&[
+            ("Binance", r#"{ "batch_size": 10 }"#),
+            ("Yahoo", r#"{ "batch_size": 20 }"#),
+        ])
+        .await;
+        let file = file(
+            r#"{
+                "Yahoo": { "batch_size": 30 },
+                "Iol": { "opts": { "connect_on_start": false } }
+            }"#,
+        );
+
+        let configs = load_from(conn, Some(&file)).await.unwrap();
+        std::fs::remove_file(file).unwrap();
+
+        assert_eq!(configs.len(), 3);
+        assert_eq!(configs["Binance"].batch_size, Some(10));
+        assert_eq!(configs["Yahoo"].batch_size, Some(30));
+
+        // the options are only set when the configuration has them, missing ones take their
+        // defaults
+        assert_eq!(configs["Binance"].opts, None);
+        let opts = RustlerOpts {
+            connect_on_start: false,
+            connect_on_add: true,
+        };
+        assert_eq!(configs["Iol"].opts, Some(opts));
+    }
+
+    #[tokio::test]
+    async fn invalid_configs_fail_to_load() {
+        let conn = database(&[("Binance", "{ batch_size: 10 }")]).await;
+        assert!(load_from(conn, None).await.is_err());
+
+        let file = file(r#"{ "Yahoo": { "batch_size": "ten" } }"#);
+        let result = load_from(database(&[]).await, Some(&file)).await;
+        std::fs::remove_file(file).unwrap();
+        assert!(result.is_err());
+
+        let missing = std::env::temp_dir().join("rustlers-missing.json");
+        let result = load_from(database(&[]).await, missing.to_str()).await;
+        assert!(result.is_err());
+    }
+}
@@ -4,6 +4,13 @@
 
 mod rustler;
 
+pub mod cleanup;
+pub mod config;
+pub mod polling;
+pub mod reference;
 pub mod rustlerjar;
+pub mod session;
+pub mod stats;
 pub mod svc;
-pub use rustler::*;
+pub mod ws;
+pub use {rustler::*, rustler_core_macros::rustler};
@@ -0,0 +1,654 @@
+use {
+    super::{
+        rustler,
+        svc::{to_msg, MsgSender},
+        MarketHourType, Quote, Rustler, RustlerAccessor, RustlerStatus, Ticker,
+    },
+    async_trait::async_trait,
+    eyre::{OptionExt, Result},
+    lool::logger::{info, warn},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::{
+        select,
+        sync::{Notify, RwLock},
+        time::{sleep, sleep_until, Instant},
+    },
+    tokio_util::sync::CancellationToken,
+};
+
+/// #### 🐎 » Polling handler
+///
+/// The provider specific part of a [`PollingRustler`]: how to fetch the quotes of a batch of
+/// tickers (e.g. an http request to the provider's api).
+#[async_trait]
+pub trait PollingHandler: Send + Sync + 'static {
+    /// 🐎 » name of the rustler (must be unique among the rustlers of a `RustlerJar`)
+    fn name(&self) -> String;
+
+    /// 🐎 » fetches the current quotes of the given tickers
+    ///
+    /// the tickers are never more than [`PollingOpts::max_batch_size`]; use
+    /// [`Ticker::provider_symbol`] to get the symbols known by the provider
+    async fn fetch(&self, tickers: &[Ticker]) -> Result<Vec<Quote>>;
+}
+
+/// #### 🐎 » Polling intervals
+///
+/// interval between the polls of a market, depending on the market hours of its last fetched
+/// quotes
+#[derive(Debug, Clone)]
+pub struct PollingIntervals {
+    pub pre: Duration,
+    pub regular: Duration,
+    pub post: Duration,
+    pub extended: Duration,
+}
+
+impl PollingIntervals {
+    /// 🐎 » uses the same interval for every market hour type
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            pre: interval,
+            regular: interval,
+            post: interval,
+            extended: interval,
+        }
+    }
+
+    /// 🐎 » returns the interval for the given market hour type
+    pub fn get(&self, market_hours: &MarketHourType) -> Duration {
+        match market_hours {
+            MarketHourType::Pre => self.pre,
+            MarketHourType::Regular => self.regular,
+            MarketHourType::Post => self.post,
+            MarketHourType::Extended => self.extended,
+        }
+    }
+}
+
+impl Default for PollingIntervals {
+    fn default() -> Self {
+        Self {
+            pre: Duration::from_secs(30),
+            regular: Duration::from_secs(5),
+            post: Duration::from_secs(30),
+            extended: Duration::from_secs(60),
+        }
+    }
+}
+
+/// #### 🐎 » Rate limit
+///
+/// max number of requests allowed in a period of time
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub per: Duration,
+}
+
+/// #### 🐎 » Polling rustler options
+#[derive(Debug, Clone)]
+pub struct PollingOpts {
+    /// interval between the polls of each market, per market hour type
+    pub intervals: PollingIntervals,
+    /// max number of tickers to fetch in a single request
+    pub max_batch_size: usize,
+    /// optional rate limit for the requests made by the rustler
+    pub rate_limit: Option<RateLimit>,
+    /// max number of retries of a failed request
+    pub max_retries: u32,
+    /// base delay before retrying a failed request (doubled after each retry, plus jitter)
+    pub retry_delay: Duration,
+}
+
+impl Default for PollingOpts {
+    fn default() -> Self {
+        Self {
+            intervals: PollingIntervals::default(),
+            max_batch_size: 50,
+            rate_limit: None,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// #### 🐎 » Polling Rustler
+///
+/// A generic [`Rustler`] for providers without streaming. While connected it periodically fetches
+/// the quotes of its tickers through a [`PollingHandler`], taking care of the polling interval
+/// (per market hour type), batching, rate limiting and retries.
+///
+/// **Usage**
+///
+/// ```rust
+/// let rustler_jar = rustlerjar! {
+///     "NYSE", "NASDAQ" => || PollingRustler::new(YahooHandler::default()),
+/// };
+/// ```
+#[rustler(name = self.handler.name(), no_constructor)]
+pub struct PollingRustler<H: PollingHandler> {
+    handler: Arc<H>,
+    polling_opts: PollingOpts,
+    polled: Arc<RwLock<HashMap<String, Ticker>>>,
+    wake: Arc<Notify>,
+    cancel: Option<CancellationToken>,
+}
+
+impl<H: PollingHandler> PollingRustler<H> {
+    /// 🐎 » creates a new polling rustler using the default [`PollingOpts`]
+    pub fn new(handler: H) -> Self {
+        Self::with_opts(handler, PollingOpts::default())
+    }
+
+    /// 🐎 » creates a new polling rustler
+    pub fn with_opts(handler: H, polling_opts: PollingOpts) -> Self {
+        Self {
+            status: RustlerStatus::default(),
+            next_run: Default::default(),
+            next_stop: None,
+            last_run: None,
+            last_stop: None,
+            last_update: None,
+            opts: Default::default(),
+            tickers: HashMap::new(),
+            msg_sender: None,
+            handler: Arc::new(handler),
+            polling_opts,
+            polled: Arc::new(RwLock::new(HashMap::new())),
+            wake: Arc::new(Notify::new()),
+            cancel: None,
+        }
+    }
+
+    /// 🐎 » returns the handler of the rustler
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+}
+
+#[async_trait]
+impl<H: PollingHandler> Rustler for PollingRustler<H> {
+    async fn connect(&mut self) -> Result<()> {
+        if self.is_connected_or_connecting() {
+            return Ok(());
+        }
+
+        self.set_status(RustlerStatus::Connecting)?;
+
+        let sender = self.msg_sender().clone().ok_or_eyre("Sender not found")?;
+        *self.polled.write().await = self.tickers().clone();
+
+        let cancel = CancellationToken::new();
+        let task = PollingTask {
+            handler: self.handler.clone(),
+            opts: self.polling_opts.clone(),
+            polled: self.polled.clone(),
+            wake: self.wake.clone(),
+            sender,
+            cancel: cancel.clone(),
+            next_request: Instant::now(),
+        };
+
+        tokio::spawn(task.run());
+
+        self.cancel = Some(cancel);
+        self.set_status(RustlerStatus::Connected)?;
+
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if self.is_disconnected_or_disconnecting() {
+            return Ok(());
+        }
+
+        self.set_status(RustlerStatus::Disconnecting)?;
+
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel();
+        }
+        self.polled.write().await.clear();
+
+        self.set_status(RustlerStatus::Disconnected)?;
+        Ok(())
+    }
+
+    async fn on_add(&mut self, tickers: &[Ticker]) -> Result<()> {
+        let mut polled = self.polled.write().await;
+        for ticker in tickers {
+            polled.insert(ticker.key(), ticker.clone());
+        }
+
+        // poll the new tickers right away instead of waiting for the next interval
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, tickers: &[Ticker]) -> Result<()> {
+        let mut polled = self.polled.write().await;
+        for ticker in tickers {
+            polled.remove(&ticker.key());
+        }
+
+        Ok(())
+    }
+}
+
+/// the task that polls the provider for a [`PollingRustler`]
+struct PollingTask<H: PollingHandler> {
+    handler: Arc<H>,
+    opts: PollingOpts,
+    polled: Arc<RwLock<HashMap<String, Ticker>>>,
+    wake: Arc<Notify>,
+    sender: MsgSender,
+    cancel: CancellationToken,
+    next_request: Instant,
+}
+
+impl<H: PollingHandler> PollingTask<H> {
+    /// polls the tickers in batches until cancelled
+    ///
+    /// every market is polled at the interval of the market hours of its last quote, so a market
+    /// in post-market hours doesn't slow down (or speed up) the polls of the others
+    async fn run(mut self) {
+        let name = self.handler.name();
+        let mut market_hours: HashMap<String, MarketHourType> = HashMap::new();
+        // when each market has to be polled again, and the tickers polled so far (new tickers are
+        // polled right away)
+        let mut due: HashMap<String, Instant> = HashMap::new();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        info!("Rustler '{}' started polling", name);
+
+        loop {
+            let polled: Vec<Ticker> = self.polled.read().await.values().cloned().collect();
+            let keys: HashSet<String> = polled.iter().map(Ticker::key).collect();
+            let markets: HashSet<String> = polled.iter().map(|t| t.market.clone()).collect();
+            due.retain(|market, _| markets.contains(market));
+            seen.retain(|key| keys.contains(key));
+
+            let now = Instant::now();
+            let due_markets: HashSet<String> = markets
+                .into_iter()
+                .filter(|market| !due.get(market).is_some_and(|at| *at > now))
+                .collect();
+
+            let tickers: Vec<Ticker> = polled
+                .into_iter()
+                .filter(|t| due_markets.contains(&t.market) || !seen.contains(&t.key()))
+                .collect();
+            seen.extend(tickers.iter().map(Ticker::key));
+
+            for batch in tickers.chunks(self.opts.max_batch_size.max(1)) {
+                let Some(result) = self.fetch(batch).await else {
+                    info!("Rustler '{}' stopped polling", name);
+                    return;
+                };
+
+                match result {
+                    Ok(quotes) => {
+                        for quote in &quotes {
+                            market_hours.insert(quote.market.clone(), quote.market_hours.clone());
+                        }
+
+                        for quote in quotes {
+                            if self.sender.send(to_msg(quote)).await.is_err() {
+                                warn!("Rustler '{}' message receiver is gone", name);
+                            }
+                        }
+                    }
+                    Err(e) => warn!("Rustler '{}' failed to fetch quotes: {}", name, e),
+                }
+            }
+
+            for market in due_markets {
+                let hours = market_hours.get(&market).unwrap_or(&MarketHourType::Regular);
+                let interval = self.opts.intervals.get(hours);
+                due.insert(market, Instant::now() + interval);
+            }
+
+            let next = match due.values().min() {
+                Some(at) => at.saturating_duration_since(Instant::now()),
+                None => self.opts.intervals.regular,
+            };
+
+            select! {
+                _ = self.cancel.cancelled() => {
+                    info!("Rustler '{}' stopped polling", name);
+                    return;
+                }
+                _ = sleep(next) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    /// fetches a batch respecting the rate limit and retrying failed requests
+    ///
+    /// returns `None` if the task was cancelled while waiting
+    async fn fetch(&mut self, batch: &[Ticker]) -> Option<Result<Vec<Quote>>> {
+        let mut attempt = 0;
+
+        loop {
+            if !self.wait_turn().await {
+                return None;
+            }
+
+            match self.handler.fetch(batch).await {
+                Ok(quotes) => return Some(Ok(quotes)),
+                Err(e) if attempt >= self.opts.max_retries => return Some(Err(e)),
+                Err(e) => {
+                    let delay = backoff(self.opts.retry_delay, attempt);
+                    warn!(
+                        "Rustler '{}' request failed, retrying in {:?}: {}",
+                        self.handler.name(),
+                        delay,
+                        e
+                    );
+
+                    attempt += 1;
+                    select! {
+                        _ = self.cancel.cancelled() => return None,
+                        _ = sleep(delay) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// waits until the rate limit allows a new request; returns `false` if cancelled
+    async fn wait_turn(&mut self) -> bool {
+        if let Some(limit) = &self.opts.rate_limit {
+            select! {
+                _ = self.cancel.cancelled() => return false,
+                _ = sleep_until(self.next_request) => {}
+            }
+
+            let spacing = limit.per / limit.max_requests.max(1);
+            self.next_request = Instant::now() + spacing;
+        }
+
+        !self.cancel.is_cancelled()
+    }
+}
+
+/// exponential backoff with up to 50% of random jitter
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    let delay = base.saturating_mul(2u32.saturating_pow(attempt));
+    let max_jitter = (delay.as_millis() / 2) as u64;
+    delay + Duration::from_millis(rand::random_range(0..=max_jitter))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::rustlers::svc::RustlerMsg,
+        eyre::bail,
+        std::sync::{
+            atomic::{AtomicU32, Ordering},
+            Mutex,
+        },
+        tokio::{
+            sync::mpsc::{channel, Receiver},
+            time::timeout,
+        },
+    };
+
+    /// a request made to the fake provider: when, and the symbols requested
+    type Request = (Instant, Vec<String>);
+
+    /// answers with a quote per ticker, in the market hours set for its market
+    #[derive(Default)]
+    struct FakeHandler {
+        requests: Mutex<Vec<Request>>,
+        /// number of requests that fail before the next one succeeds
+        failures: AtomicU32,
+        market_hours: HashMap<String, MarketHourType>,
+    }
+
+    #[async_trait]
+    impl PollingHandler for FakeHandler {
+        fn name(&self) -> String {
+            "FAKE".into()
+        }
+
+        async fn fetch(&self, tickers: &[Ticker]) -> Result<Vec<Quote>> {
+            let symbols = tickers.iter().map(|t| t.provider_symbol().to_owned()).collect();
+            self.requests.lock().unwrap().push((Instant::now(), symbols));
+
+            let failures = self.failures.load(Ordering::Relaxed);
+            if failures > 0 {
+                self.failures.store(failures - 1, Ordering::Relaxed);
+                bail!("Too many requests");
+            }
+
+            let quotes = tickers.iter().map(|t| Quote {
+                id: t.provider_symbol().to_owned(),
+                market: t.market.clone(),
+                price: 1.0,
+                change_percent: 0.0,
+                time: 0,
+                market_hours: self
+                    .market_hours
+                    .get(&t.market)
+                    .cloned()
+                    .unwrap_or(MarketHourType::Regular),
+                source: None,
+                volume: None,
+                stats: None,
+            });
+
+            Ok(quotes.collect())
+        }
+    }
+
+    impl FakeHandler {
+        fn requests(&self) -> Vec<Request> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        fn clear(&self) {
+            self.requests.lock().unwrap().clear();
+        }
+    }
+
+    fn ticker(market: &str, symbol: &str) -> Ticker {
+        Ticker {
+            symbol: symbol.into(),
+            market: market.into(),
+            quote_asset: None,
+            provider_symbol: None,
+        }
+    }
+
+    fn opts() -> PollingOpts {
+        PollingOpts {
+            intervals: PollingIntervals::every(Duration::from_secs(60)),
+            max_batch_size: 50,
+            rate_limit: None,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(20),
+        }
+    }
+
+    /// creates a rustler polling the given tickers
+    async fn start(
+        handler: FakeHandler,
+        opts: PollingOpts,
+        tickers: &[Ticker],
+    ) -> (PollingRustler<FakeHandler>, Receiver<RustlerMsg>) {
+        let (sender, receiver) = channel(100);
+        let mut rustler = PollingRustler::with_opts(handler, opts);
+        rustler.set_msg_sender(Some(sender));
+        rustler.add(&tickers.to_vec()).await.unwrap();
+
+        (rustler, receiver)
+    }
+
+    /// receives the symbols of the next `count` quotes
+    async fn next_quotes(receiver: &mut Receiver<RustlerMsg>, count: usize) -> Vec<String> {
+        let mut symbols = vec![];
+        while symbols.len() < count {
+            let msg = timeout(Duration::from_secs(5), receiver.recv()).await;
+            match msg.expect("timed out waiting for quotes").expect("sender dropped") {
+                RustlerMsg::QuoteMsg(quote) => symbols.push(quote.id),
+                RustlerMsg::StatusMsg(_) => {}
+            }
+        }
+
+        symbols.sort();
+        symbols
+    }
+
+    #[tokio::test]
+    async fn tickers_are_polled_in_batches() {
+        let tickers: Vec<Ticker> = (0..5).map(|i| ticker("NYSE", &format!("S{}", i))).collect();
+        let opts = PollingOpts {
+            max_batch_size: 2,
+            ..opts()
+        };
+
+        let (mut rustler, mut receiver) = start(FakeHandler::default(), opts, &tickers).await;
+        assert_eq!(
+            next_quotes(&mut receiver, 5).await,
+            ["S0", "S1", "S2", "S3", "S4"]
+        );
+
+        let mut sizes: Vec<usize> =
+            rustler.handler().requests().iter().map(|r| r.1.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, [1, 2, 2]);
+
+        rustler.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_respect_the_rate_limit() {
+        let tickers: Vec<Ticker> = (0..3).map(|i| ticker("NYSE", &format!("S{}", i))).collect();
+        let opts = PollingOpts {
+            max_batch_size: 1,
+            rate_limit: Some(RateLimit {
+                max_requests: 2,
+                per: Duration::from_millis(200),
+            }),
+            ..opts()
+        };
+
+        let (mut rustler, mut receiver) = start(FakeHandler::default(), opts, &tickers).await;
+        next_quotes(&mut receiver, 3).await;
+
+        // 2 requests every 200ms are spaced by 100ms
+        let requests = rustler.handler().requests();
+        assert_eq!(requests.len(), 3);
+        for pair in requests.windows(2) {
+            assert!(pair[1].0 - pair[0].0 >= Duration::from_millis(95));
+        }
+
+        rustler.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_retried_with_backoff() {
+        let handler = FakeHandler {
+            failures: AtomicU32::new(2),
+            ..Default::default()
+        };
+
+        let (mut rustler, mut receiver) = start(handler, opts(), &[ticker("NYSE", "AAPL")]).await;
+        assert_eq!(next_quotes(&mut receiver, 1).await, ["AAPL"]);
+
+        // the delay doubles after each retry (20ms, then 40ms, plus jitter)
+        let requests = rustler.handler().requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].0 - requests[0].0 >= Duration::from_millis(20));
+        assert!(requests[2].0 - requests[1].0 >= Duration::from_millis(40));
+
+        rustler.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_given_up_after_the_max_retries() {
+        let handler = FakeHandler {
+            failures: AtomicU32::new(2),
+            ..Default::default()
+        };
+        let opts = PollingOpts {
+            intervals: PollingIntervals::every(Duration::from_millis(100)),
+            max_retries: 1,
+            ..opts()
+        };
+
+        let (mut rustler, mut receiver) = start(handler, opts, &[ticker("NYSE", "AAPL")]).await;
+
+        // the first poll gives up after a retry, the next one succeeds
+        assert_eq!(next_quotes(&mut receiver, 1).await, ["AAPL"]);
+        let requests = rustler.handler().requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].0 - requests[1].0 >= Duration::from_millis(95));
+
+        rustler.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn tickers_can_be_added_and_deleted_while_polling() {
+        let opts = PollingOpts {
+            intervals: PollingIntervals::every(Duration::from_millis(50)),
+            ..opts()
+        };
+
+        let aapl = ticker("NYSE", "AAPL");
+        let (mut rustler, mut receiver) =
+            start(FakeHandler::default(), opts, &[aapl.clone()]).await;
+        assert_eq!(next_quotes(&mut receiver, 1).await, ["AAPL"]);
+
+        // new tickers are polled right away
+        rustler.add(&vec![ticker("NYSE", "MSFT")]).await.unwrap();
+        let symbols = next_quotes(&mut receiver, 1).await;
+        assert_eq!(symbols, ["MSFT"]);
+
+        rustler.delete(&vec![aapl]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        rustler.handler().clear();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let requests = rustler.handler().requests();
+        assert!(!requests.is_empty());
+        assert!(requests.iter().all(|(_, symbols)| symbols == &["MSFT"]));
+
+        rustler.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn markets_are_polled_at_the_interval_of_their_own_market_hours() {
+        let handler = FakeHandler {
+            market_hours: HashMap::from([("NYSE".into(), MarketHourType::Post)]),
+            ..Default::default()
+        };
+        let opts = PollingOpts {
+            intervals: PollingIntervals {
+                regular: Duration::from_millis(50),
+                post: Duration::from_secs(60),
+                ..PollingIntervals::every(Duration::from_secs(60))
+            },
+            ..opts()
+        };
+
+        let tickers = [ticker("NYSE", "AAPL"), ticker("BINANCE", "BTCUSDT")];
+        let (mut rustler, _receiver) = start(handler, opts, &tickers).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let requests = rustler.handler().requests();
+        let polls =
+            |symbol: &str| requests.iter().filter(|r| r.1.iter().any(|s| s == symbol)).count();
+        assert_eq!(polls("AAPL"), 1);
+        assert!(polls("BTCUSDT") >= 4);
+
+        rustler.disconnect().await.unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use {
+    super::{MarketHourType, Quote, Ticker},
+    crate::entities::{market, reference_price, sea_orm::DatabaseConnection},
+    chrono::Utc,
+    eyre::Result,
+    lool::{
+        fail,
+        logger::{info, warn},
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::HashMap, str::FromStr},
+    tokio::sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+};
+
+/// #### 🐎 » Change reference
+///
+/// The price used as reference to compute the [`Quote::change_percent`] of the quotes of a market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeReference {
+    /// keep the change percent computed by the rustler
+    #[default]
+    Provider,
+    /// last price of the previous session
+    PreviousClose,
+    /// first price of the current session
+    SessionOpen,
+}
+
+impl FromStr for ChangeReference {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "provider" => Ok(Self::Provider),
+            "previous_close" => Ok(Self::PreviousClose),
+            "session_open" => Ok(Self::SessionOpen),
+            _ => fail!("Unknown change reference `{}`", s),
+        }
+    }
+}
+
+impl ChangeReference {
+    /// 🐎 » gets the change reference configured for a market
+    ///
+    /// invalid or missing values fall back to [`ChangeReference::Provider`]
+    pub fn of(market: &market::Model) -> Self {
+        match market.change_reference.as_deref().map(str::parse::<Self>) {
+            Some(Ok(reference)) => reference,
+            Some(Err(e)) => {
+                warn!("{} for market '{}', using `provider`", e, market.short_name);
+                Self::Provider
+            }
+            None => Self::Provider,
+        }
+    }
+}
+
+/// #### 🐎 » Reference prices of a ticker
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReferencePrices {
+    pub previous_close: Option<f64>,
+    pub session_open: Option<f64>,
+    pub last_price: Option<f64>,
+}
+
+/// #### 🐎 » Reference price store
+///
+/// Keeps the reference prices (previous close and session open) of every ticker, so that the
+/// core can compute the change percent of the quotes consistently, no matter which rustler
+/// produced them.
+///
+/// Reference prices are seeded from the `reference_price` table, or from the first quote of the
+/// session when there's nothing stored, and are rolled over at market close (see
+/// [`ReferencePriceStore::roll_over`]). The session open is stored as soon as it's set, so it
+/// survives restarts in the middle of the session.
+///
+/// The prices are stored by a background writer, in the order they change, so a slow database
+/// doesn't hold up the quotes (see [`ReferencePriceStore::flush`]).
+///
+/// There's no previous close until the first roll over of a ticker; until then, the quotes of
+/// markets using [`ChangeReference::PreviousClose`] keep the change percent of the rustler.
+pub struct ReferencePriceStore {
+    svc: reference_price::Service,
+    prices: HashMap<String, ReferencePrices>,
+    references: HashMap<String, ChangeReference>,
+    writer: UnboundedSender<Write>,
+}
+
+/// a request to the writer of a [`ReferencePriceStore`]
+enum Write {
+    Upsert(Vec<reference_price::Model>),
+    /// answered once the previous upserts are stored
+    Flush(oneshot::Sender<()>),
+}
+
+impl ReferencePriceStore {
+    /// 🐎 » creates an empty store (see [`ReferencePriceStore::load`]), spawning its writer
+    pub async fn new(conn: DatabaseConnection) -> Self {
+        let (writer, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(write(
+            reference_price::Service::new(conn.clone()).await,
+            receiver,
+        ));
+
+        Self {
+            svc: reference_price::Service::new(conn).await,
+            prices: HashMap::new(),
+            references: HashMap::new(),
+            writer,
+        }
+    }
+
+    /// 🐎 » seeds the store with the reference prices stored in the database
+    pub async fn load(&mut self) -> Result<()> {
+        let prices: HashMap<String, ReferencePrices> = self
+            .svc
+            .get_all()
+            .await?
+            .into_iter()
+            .map(|p| {
+                let prices = ReferencePrices {
+                    previous_close: p.previous_close,
+                    session_open: p.session_open,
+                    last_price: p.last_price,
+                };
+                (format!("{}:{}", p.market, p.symbol), prices)
+            })
+            .collect();
+
+        info!("Loaded reference prices for {} tickers", prices.len());
+        self.prices.extend(prices);
+
+        Ok(())
+    }
+
+    /// 🐎 » sets the change reference for a market
+    ///
+    /// `market` is the market name used in the quotes (the market's public name if it has one)
+    pub fn set_reference(&mut self, market: &str, reference: ChangeReference) {
+        self.references.insert(market.to_owned(), reference);
+    }
+
+    /// 🐎 » returns the reference prices of a ticker, given its key (`market:symbol`)
+    pub fn get(&self, key: &str) -> Option<&ReferencePrices> {
+        self.prices.get(key)
+    }
+
+    /// 🐎 » records the quote and recomputes its change percent
+    ///
+    /// the first regular hours quote of the session becomes the session open, which is queued to
+    /// be stored right away
+    pub fn apply(&mut self, quote: &mut Quote) {
+        let key = format!("{}:{}", quote.market, quote.id);
+        let prices = self.prices.entry(key).or_default();
+        prices.last_price = Some(quote.price);
+
+        if prices.session_open.is_none() && matches!(quote.market_hours, MarketHourType::Regular) {
+            prices.session_open = Some(quote.price);
+
+            let model = reference_price::Model {
+                market: quote.market.clone(),
+                symbol: quote.id.clone(),
+                previous_close: prices.previous_close,
+                session_open: prices.session_open,
+                last_price: prices.last_price,
+                updated_at: Utc::now().timestamp_millis(),
+            };
+
+            if self.writer.send(Write::Upsert(vec![model])).is_err() {
+                warn!(
+                    "Failed to store the session open of '{}:{}': the writer is gone",
+                    quote.market, quote.id
+                );
+            }
+        }
+
+        let reference = match self.references.get(&quote.market).copied().unwrap_or_default() {
+            ChangeReference::Provider => return,
+            ChangeReference::PreviousClose => prices.previous_close,
+            ChangeReference::SessionOpen => prices.session_open,
+        };
+
+        if let Some(reference) = reference.filter(|r| *r != 0.0) {
+            quote.change_percent = (quote.price - reference) / reference * 100.0;
+        }
+    }
+
+    /// 🐎 » rolls over the reference prices of the given tickers at market close
+    ///
+    /// the last price becomes the previous close and the session open is cleared, so the first
+    /// quote of the next session sets it again; the new reference prices are queued to be stored
+    /// in the database
+    pub fn roll_over(&mut self, tickers: &[Ticker]) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let mut models = Vec::with_capacity(tickers.len());
+
+        for ticker in tickers {
+            let Some(prices) = self.prices.get_mut(&ticker.key()) else {
+                continue;
+            };
+
+            if prices.last_price.is_some() {
+                prices.previous_close = prices.last_price;
+            }
+            prices.session_open = None;
+
+            models.push(reference_price::Model {
+                market: ticker.market.clone(),
+                symbol: ticker.symbol.clone(),
+                previous_close: prices.previous_close,
+                session_open: prices.session_open,
+                last_price: prices.last_price,
+                updated_at: now,
+            });
+        }
+
+        if self.writer.send(Write::Upsert(models)).is_err() {
+            fail!("The reference prices writer is gone");
+        }
+
+        Ok(())
+    }
+
+    /// 🐎 » waits until the reference prices queued so far are stored
+    pub async fn flush(&self) -> Result<()> {
+        let (done, stored) = oneshot::channel();
+        if self.writer.send(Write::Flush(done)).is_err() || stored.await.is_err() {
+            fail!("The reference prices writer is gone");
+        }
+
+        Ok(())
+    }
+}
+
+/// stores the reference prices queued by a [`ReferencePriceStore`], batching the ones queued
+/// while the previous batch was being stored
+///
+/// a batch keeps only the latest prices of each ticker, since an upsert can't touch a row twice
+async fn write(svc: reference_price::Service, mut receiver: UnboundedReceiver<Write>) {
+    while let Some(first) = receiver.recv().await {
+        let mut models = HashMap::new();
+        let mut flushes = vec![];
+
+        let mut next = Some(first);
+        while let Some(write) = next {
+            match write {
+                Write::Upsert(batch) => {
+                    for model in batch {
+                        models.insert((model.market.clone(), model.symbol.clone()), model);
+                    }
+                }
+                Write::Flush(done) => flushes.push(done),
+            }
+            next = receiver.try_recv().ok();
+        }
+
+        if let Err(e) = svc.upsert_many(models.into_values().collect()).await {
+            warn!("Failed to store reference prices: {}", e);
+        }
+
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::entities::db};
+
+    fn quote(price: f64, change_percent: f64, market_hours: MarketHourType) -> Quote {
+        Quote {
+            id: "AAPL".to_owned(),
+            market: "NASDAQ".to_owned(),
+            price,
+            change_percent,
+            time: 0,
+            market_hours,
+            source: None,
+            volume: None,
+            stats: None,
+        }
+    }
+
+    fn ticker() -> Ticker {
+        Ticker {
+            symbol: "AAPL".to_owned(),
+            market: "NASDAQ".to_owned(),
+            quote_asset: None,
+            provider_symbol: None,
+        }
+    }
+
+    async fn store(reference: ChangeReference) -> ReferencePriceStore {
+        let mut store = ReferencePriceStore::new(db::in_memory().await).await;
+        store.set_reference("NASDAQ", reference);
+        store
+    }
+
+    /// applies a quote and returns its change percent
+    fn apply(store: &mut ReferencePriceStore, quote: &mut Quote) -> f64 {
+        store.apply(quote);
+        quote.change_percent
+    }
+
+    #[tokio::test]
+    async fn provider_keeps_the_change_percent_of_the_rustler() {
+        let mut store = store(ChangeReference::Provider).await;
+
+        assert_eq!(
+            apply(&mut store, &mut quote(100.0, 1.5, MarketHourType::Regular)),
+            1.5
+        );
+        assert_eq!(
+            apply(&mut store, &mut quote(110.0, 2.5, MarketHourType::Regular)),
+            2.5
+        );
+    }
+
+    #[tokio::test]
+    async fn session_open_is_the_first_regular_hours_quote() {
+        let mut store = store(ChangeReference::SessionOpen).await;
+
+        // pre-market quotes don't open the session, so they keep the provider's change
+        assert_eq!(
+            apply(&mut store, &mut quote(90.0, 1.5, MarketHourType::Pre)),
+            1.5
+        );
+        assert_eq!(store.get("NASDAQ:AAPL").unwrap().session_open, None);
+
+        assert_eq!(
+            apply(&mut store, &mut quote(100.0, 1.5, MarketHourType::Regular)),
+            0.0
+        );
+        assert_eq!(
+            apply(&mut store, &mut quote(110.0, 1.5, MarketHourType::Regular)),
+            10.0
+        );
+        assert_eq!(
+            apply(&mut store, &mut quote(95.0, 1.5, MarketHourType::Post)),
+            -5.0
+        );
+        assert_eq!(store.get("NASDAQ:AAPL").unwrap().session_open, Some(100.0));
+    }
+
+    #[tokio::test]
+    async fn previous_close_applies_after_the_first_roll_over() {
+        let mut store = store(ChangeReference::PreviousClose).await;
+
+        // there's no previous close yet
+        assert_eq!(
+            apply(&mut store, &mut quote(100.0, 1.5, MarketHourType::Regular)),
+            1.5
+        );
+        assert_eq!(
+            apply(&mut store, &mut quote(80.0, 1.5, MarketHourType::Regular)),
+            1.5
+        );
+
+        store.roll_over(&[ticker()]).unwrap();
+
+        assert_eq!(
+            apply(&mut store, &mut quote(88.0, 1.5, MarketHourType::Pre)),
+            10.0
+        );
+        assert_eq!(
+            apply(&mut store, &mut quote(72.0, 1.5, MarketHourType::Regular)),
+            -10.0
+        );
+    }
+
+    #[tokio::test]
+    async fn roll_over_moves_the_last_price_to_the_previous_close() -> Result<()> {
+        let conn = db::in_memory().await;
+        let mut store = ReferencePriceStore::new(conn.clone()).await;
+
+        store.apply(&mut quote(100.0, 0.0, MarketHourType::Regular));
+        store.apply(&mut quote(120.0, 0.0, MarketHourType::Regular));
+        store.roll_over(&[ticker()])?;
+
+        let rolled = ReferencePrices {
+            previous_close: Some(120.0),
+            session_open: None,
+            last_price: Some(120.0),
+        };
+        assert_eq!(store.get("NASDAQ:AAPL"), Some(&rolled));
+
+        // the first quote of the next session opens it again
+        store.apply(&mut quote(125.0, 0.0, MarketHourType::Regular));
+        assert_eq!(store.get("NASDAQ:AAPL").unwrap().session_open, Some(125.0));
+
+        // and the writes were queued in order, so a new store loads the latest prices
+        store.flush().await?;
+        let mut loaded = ReferencePriceStore::new(conn).await;
+        loaded.load().await?;
+
+        let stored = ReferencePrices {
+            previous_close: Some(120.0),
+            session_open: Some(125.0),
+            last_price: Some(125.0),
+        };
+        assert_eq!(loaded.get("NASDAQ:AAPL"), Some(&stored));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn roll_over_skips_tickers_without_prices() -> Result<()> {
+        let conn = db::in_memory().await;
+        let mut store = ReferencePriceStore::new(conn.clone()).await;
+
+        store.roll_over(&[ticker()])?;
+        store.flush().await?;
+
+        assert_eq!(store.get("NASDAQ:AAPL"), None);
+        assert!(reference_price::Service::new(conn).await.get_all().await?.is_empty());
+
+        Ok(())
+    }
+}
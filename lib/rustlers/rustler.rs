@@ -1,17 +1,21 @@
 pub extern crate chrono;
 pub extern crate eyre;
+pub extern crate lool;
 
 use {
-    super::svc::RustlerMsg,
+    super::{config::RustlerConfig, stats::SessionStats, svc::RustlerMsg},
     crate::{
-        bus::{redis::stream::StreamMsg, BusMessage, ToBusKey, ToBusVal, ToFromBusMessage},
+        bus::{
+            codec::proto, redis::stream::StreamMsg, BusFields, BusMessage, FromBusVal,
+            MessageFields, ToBusKey, ToBusVal, ToFromBusMessage, ToFromProto,
+        },
         entities::{market, ticker},
     },
     async_trait::async_trait,
     chrono::{DateTime, Local},
     eyre::Result,
     lool::s,
-    serde::Serialize,
+    serde::{Deserialize, Serialize},
     std::{
         collections::HashMap,
         fmt::{self, Display, Formatter},
@@ -30,7 +34,7 @@ pub enum RustlerStatus {
 }
 
 /// 🐎 » an enum representing the different types of market hours
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MarketHourType {
     Pre = 0,
     Regular = 1,
@@ -58,7 +62,7 @@ impl From<u8> for MarketHourType {
 
 /// 🐎 » a struct storing a ticker's quote at a given time, and the change in price since the last
 /// quote
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quote {
     pub id: String,
     pub market: String,
@@ -66,11 +70,29 @@ pub struct Quote {
     pub change_percent: f64,
     pub time: i64,
     pub market_hours: MarketHourType,
+    /// name of the rustler that produced the quote; set by the [`RustlersSvc`] before publishing
+    ///
+    /// [`RustlersSvc`]: super::svc::RustlersSvc
+    pub source: Option<String>,
+    /// volume traded in the session so far, if the provider reports it
+    pub volume: Option<f64>,
+    /// running statistics of the ticker's session; set by the [`RustlersSvc`] before publishing
+    ///
+    /// they're stored in the ticker's hash but not sent in the bus messages
+    ///
+    /// [`RustlersSvc`]: super::svc::RustlersSvc
+    pub stats: Option<SessionStats>,
 }
 
 impl Quote {
+    /// 🐎 » whether the quote is of the given ticker
+    ///
+    /// the quote id can be either the canonical symbol of the ticker or its provider symbol, so
+    /// this works both on the quotes of a rustler and on the ones routed by the [`RustlersSvc`]
+    ///
+    /// [`RustlersSvc`]: super::svc::RustlersSvc
     pub fn belongs_to(&self, ticker: &Ticker) -> bool {
-        self.id == ticker.symbol && self.market == ticker.market
+        ticker.is(&self.id, &self.market)
     }
 }
 
@@ -84,14 +106,45 @@ impl ToBusVal for Quote {
     fn to_bus_val(&self) -> Vec<(String, String)> {
         let market_hours_u8: u8 = self.market_hours.clone().into();
 
-        vec![
+        let mut val = vec![
             (s!("id"), self.id.to_owned()),
             (s!("market"), self.market.to_owned()),
             (s!("price"), self.price.to_string()),
             (s!("market_hours"), market_hours_u8.to_string()),
             (s!("time"), self.time.to_string()),
             (s!("change_percent"), self.change_percent.to_string()),
-        ]
+            (s!("source"), self.source.clone().unwrap_or_default()),
+            (
+                s!("volume"),
+                self.volume.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+        ];
+
+        if let Some(stats) = &self.stats {
+            val.extend(stats.to_bus_val());
+        }
+
+        val
+    }
+}
+
+impl FromBusVal for Quote {
+    /// 🐎 » decodes the fields of a quote hash, including the session statistics if it has them
+    fn from_bus_val(fields: &HashMap<String, String>) -> Result<Self> {
+        let stats = SessionStats::from_bus_val(fields);
+        let fields = BusFields::new(fields);
+
+        Ok(Self {
+            id: fields.get("id")?.to_string(),
+            market: fields.get("market")?.to_string(),
+            price: fields.parse::<f64>("price")?,
+            change_percent: fields.parse::<f64>("change_percent")?,
+            time: fields.parse::<i64>("time")?,
+            market_hours: fields.parse::<u8>("market_hours")?.into(),
+            source: fields.opt("source").map(|s| s.to_string()),
+            volume: fields.parse_opt::<f64>("volume")?,
+            stats,
+        })
     }
 }
 
@@ -104,56 +157,98 @@ impl ToBusKey for Quote {
 impl ToFromBusMessage for Quote {
     /// 🐎 » converts a `Quote` to a serialized message that can be sent over a redis channel
     ///
-    /// the message is in the format `id¦market¦price¦change_percent¦time¦market_hours`
+    /// the message is in the format
+    /// `id¦market¦price¦change_percent¦time¦market_hours¦source¦volume`
     fn as_message(&self) -> String {
-        // id¦market¦price¦change_percent¦time¦market_hours
+        // id¦market¦price¦change_percent¦time¦market_hours¦source¦volume
         format!(
-            "{}¦{}¦{}¦{}¦{}¦{}",
+            "{}¦{}¦{}¦{}¦{}¦{}¦{}¦{}",
             self.id,
             self.market,
             self.price,
             self.change_percent,
             self.time,
-            Into::<u8>::into(self.market_hours.clone())
+            Into::<u8>::into(self.market_hours.clone()),
+            self.source.as_deref().unwrap_or_default(),
+            self.volume.map(|v| v.to_string()).unwrap_or_default()
         )
     }
 
     /// 🐎 » creates a `Quote` from a message
     ///
-    /// the message should be in the format `id¦market¦price¦change_percent¦time¦market_hours`
+    /// the message should be in the format `id¦market¦price¦change_percent¦time¦market_hours`,
+    /// optionally followed by `¦source` and `¦volume`
     ///
-    /// **panics** if the message is not in the correct format
-    fn from_message<T: AsRef<str>>(msg: T) -> Self {
-        let msg = msg.as_ref();
-        let parts: Vec<&str> = msg.split('¦').collect();
-
-        let id = parts[0].to_string();
-        let market = parts[1].to_string();
-        let price = parts[2].parse::<f64>().unwrap();
-        let change_percent = parts[3].parse::<f64>().unwrap();
-        let time = parts[4].parse::<i64>().unwrap();
-        let market_hours = parts[5].parse::<u8>().unwrap().into();
-
-        Self {
+    /// fails if the message is not in the correct format
+    fn from_message<T: AsRef<str>>(msg: T) -> Result<Self> {
+        let fields = MessageFields::new(msg.as_ref());
+
+        let id = fields.get(0, "id")?.to_string();
+        let market = fields.get(1, "market")?.to_string();
+        let price = fields.parse::<f64>(2, "price")?;
+        let change_percent = fields.parse::<f64>(3, "change_percent")?;
+        let time = fields.parse::<i64>(4, "time")?;
+        let market_hours = fields.parse::<u8>(5, "market_hours")?.into();
+        let source = fields.opt(6).map(|s| s.to_string());
+        let volume = fields.parse_opt::<f64>(7, "volume")?;
+
+        Ok(Self {
             id,
             market,
             price,
             change_percent,
             time,
             market_hours,
+            source,
+            volume,
+            stats: None,
+        })
+    }
+}
+
+impl ToFromProto for Quote {
+    type Proto = proto::Quote;
+
+    /// 🐎 » converts a `Quote` to its protobuf message (session statistics aren't included)
+    fn to_proto(&self) -> proto::Quote {
+        proto::Quote {
+            id: self.id.clone(),
+            market: self.market.clone(),
+            price: self.price,
+            change_percent: self.change_percent,
+            time: self.time,
+            market_hours: Into::<u8>::into(self.market_hours.clone()) as u32,
+            source: self.source.clone(),
+            volume: self.volume,
         }
     }
+
+    /// 🐎 » creates a `Quote` from its protobuf message
+    fn from_proto(quote: proto::Quote) -> Result<Self> {
+        Ok(Self {
+            id: quote.id,
+            market: quote.market,
+            price: quote.price,
+            change_percent: quote.change_percent,
+            time: quote.time,
+            market_hours: u8::try_from(quote.market_hours)?.into(),
+            source: quote.source,
+            volume: quote.volume,
+            stats: None,
+        })
+    }
 }
 
+/// a quote equals a ticker if it [belongs to it](Quote::belongs_to)
 impl PartialEq<Ticker> for Quote {
     fn eq(&self, other: &Ticker) -> bool {
-        self.id == other.symbol && self.market == other.market
+        self.belongs_to(other)
     }
 }
 
 impl PartialEq<Quote> for Ticker {
     fn eq(&self, other: &Quote) -> bool {
-        self.symbol == other.id && self.market == other.market
+        other.belongs_to(self)
     }
 }
 
@@ -164,9 +259,23 @@ impl PartialEq<Quote> for Quote {
 }
 
 impl StreamMsg for Quote {}
-impl BusMessage for Quote {}
+impl BusMessage for Quote {
+    fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    fn message_time(&self) -> Option<i64> {
+        Some(self.time)
+    }
 
-#[derive(Debug, Clone)]
+    fn topic() -> Option<&'static str> {
+        Some("quote")
+    }
+}
+
+/// 🐎 » options that control when a rustler connects to its data source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RustlerOpts {
     pub connect_on_start: bool,
     pub connect_on_add: bool,
@@ -188,11 +297,15 @@ impl Default for RustlerOpts {
 /// they `key` of a ticker is the concatenation of the market and the symbol separated by a colon
 ///
 /// e.g. `AAPL` in the `NASDAQ` market would have the key `NASDAQ:AAPL`
+///
+/// the `symbol` is always the canonical one, providers that use a different symbol for the same
+/// ticker (e.g. `BTCUSDT` vs `BTC-USD`) get it through `provider_symbol`
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Ticker {
     pub symbol: String,
     pub market: String,
     pub quote_asset: Option<String>,
+    pub provider_symbol: Option<String>,
 }
 
 impl Ticker {
@@ -206,9 +319,28 @@ impl Ticker {
             symbol: t.symbol.clone(),
             market,
             quote_asset: t.quote_symbol.clone(),
+            provider_symbol: None,
         }
     }
 
+    /// 🐎 » sets the symbol used by the provider for this ticker
+    pub fn with_provider_symbol(mut self, provider_symbol: Option<String>) -> Self {
+        self.provider_symbol = provider_symbol;
+        self
+    }
+
+    /// 🐎 » returns the symbol rustlers must use to subscribe to this ticker in their provider
+    ///
+    /// falls back to the canonical `symbol` when the rustler has no alias for the ticker
+    pub fn provider_symbol(&self) -> &str {
+        self.provider_symbol.as_deref().unwrap_or(&self.symbol)
+    }
+
+    /// 🐎 » whether `symbol` in `market` is this ticker, by its canonical or provider symbol
+    pub fn is(&self, symbol: &str, market: &str) -> bool {
+        self.market == market && (self.symbol == symbol || self.provider_symbol() == symbol)
+    }
+
     pub fn many_from(tickers: &[ticker::Model], market: &market::Model) -> Vec<Self> {
         tickers.iter().map(|t| Self::from(t, market)).collect()
     }
@@ -354,6 +486,38 @@ pub trait Rustler: RustlerAccessor + Send + Sync {
     async fn disconnect(&mut self) -> Result<()>;
     // #endregion
 
+    /// 🐎 » fn called with the rustler's configuration before applying it
+    ///
+    /// Implementations should validate the configuration and keep whatever they need from it
+    /// (endpoints, credentials, etc.). Returning an error aborts the start of the rustlers
+    /// service, so invalid configurations are detected at startup instead of at first connect.
+    async fn on_configure(&mut self, _config: &RustlerConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// 🐎 » whether the rustler can't run without a configuration
+    ///
+    /// When `true`, the rustlers service fails at startup if no configuration is found for the
+    /// rustler, instead of starting it unconfigured.
+    fn requires_config(&self) -> bool {
+        false
+    }
+
+    /// 🐎 » configures the rustler
+    ///
+    /// Will call the [`Rustler::on_configure`] function and, if it succeeds, set the rustler
+    /// options to the ones in the configuration (if it has them, otherwise the rustler keeps its
+    /// current options).
+    ///
+    /// Called by the rustlers service before starting the rustler.
+    async fn configure(&mut self, config: RustlerConfig) -> Result<()> {
+        self.on_configure(&config).await?;
+        if let Some(opts) = config.opts {
+            self.set_opts(opts);
+        }
+        Ok(())
+    }
+
     /// 🐎 » starts the rustler
     async fn start(&mut self) -> Result<()> {
         let opts = self.opts();
@@ -466,7 +630,7 @@ macro_rules! rustler_accessors {
             self.status = status;
             self.handle_status_change()?;
 
-            lool::logger::info!(
+            $crate::rustlers::lool::logger::info!(
                 "Rustler {} status changed to {:?}",
                 self.name(),
                 self.status()
@@ -540,29 +704,27 @@ macro_rules! rustler_accessors {
         fn set_opts(&mut self, opts: $crate::rustlers::RustlerOpts) {
             self.opts = opts;
         }
-        fn tickers(&self) -> &HashMap<String, $crate::rustlers::Ticker> {
+        fn tickers(&self) -> &::std::collections::HashMap<String, $crate::rustlers::Ticker> {
             &self.tickers
         }
-        fn tickers_mut(&mut self) -> &mut HashMap<String, $crate::rustlers::Ticker> {
+        fn tickers_mut(
+            &mut self,
+        ) -> &mut ::std::collections::HashMap<String, $crate::rustlers::Ticker> {
             &mut self.tickers
         }
-        fn set_tickers(&mut self, tickers: HashMap<String, $crate::rustlers::Ticker>) {
+        fn set_tickers(
+            &mut self,
+            tickers: ::std::collections::HashMap<String, $crate::rustlers::Ticker>,
+        ) {
             self.tickers = tickers;
         }
-        fn msg_sender(
-            &self,
-        ) -> &Option<tokio::sync::mpsc::Sender<$crate::rustlers::svc::RustlerMsg>> {
+        fn msg_sender(&self) -> &Option<$crate::rustlers::svc::MsgSender> {
             &self.msg_sender
         }
-        fn msg_sender_mut(
-            &mut self,
-        ) -> &mut Option<tokio::sync::mpsc::Sender<$crate::rustlers::svc::RustlerMsg>> {
+        fn msg_sender_mut(&mut self) -> &mut Option<$crate::rustlers::svc::MsgSender> {
             &mut self.msg_sender
         }
-        fn set_msg_sender(
-            &mut self,
-            sender: Option<tokio::sync::mpsc::Sender<$crate::rustlers::svc::RustlerMsg>>,
-        ) {
+        fn set_msg_sender(&mut self, sender: Option<$crate::rustlers::svc::MsgSender>) {
             self.msg_sender = sender;
         }
     };
@@ -573,6 +735,10 @@ macro_rules! rustler_accessors {
 /// The `rustler!` macro is used to define a new `Rustler` struct, expanding the struct definition
 /// with the required fields and derives, and implementing the `RustlerAccessor` trait for the
 /// struct.
+///
+/// **☢️ deprecated**: it only supports plain structs whose fields implement `Default`; use the
+/// [`#[rustler]`](crate::rustlers::rustler) attribute macro instead.
+#[deprecated(note = "use the `#[rustler]` attribute macro instead")]
 #[macro_export]
 macro_rules! rustler {
     // Entry point for the macro, takes the struct definition
@@ -591,8 +757,8 @@ macro_rules! rustler {
             last_stop: Option<$crate::rustlers::chrono::DateTime<$crate::rustlers::chrono::Local>>,
             last_update: Option<$crate::rustlers::chrono::DateTime<$crate::rustlers::chrono::Local>>,
             opts: $crate::rustlers::RustlerOpts,
-            tickers: HashMap<String, $crate::rustlers::Ticker>,
-            msg_sender: Option<tokio::sync::mpsc::Sender<$crate::rustlers::svc::RustlerMsg>>,
+            tickers: ::std::collections::HashMap<String, $crate::rustlers::Ticker>,
+            msg_sender: Option<$crate::rustlers::svc::MsgSender>,
             $($fields)*
         }
 
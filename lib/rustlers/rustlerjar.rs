@@ -10,12 +10,17 @@ use {
 /// A macro to create a `RustlerJar` with multiple Rustler instances and their corresponding
 /// mappings.
 ///
+/// A market can be listed under more than one rustler: the first rustler declared for a market
+/// is its primary, and the following ones are used as fallbacks (in declaration order) when the
+/// primary fails.
+///
 /// **Usage**
 ///
 /// ```rust
 /// let rustler_jar = rustlerjar! {
 ///    "NYSE", "NASDAQ" => FooRustler::create,
 ///    "BINANCE" => BarRustler::create(url),
+///    "NASDAQ" => BazRustler::create, // secondary for NASDAQ
 /// };
 /// ```
 #[macro_export]
@@ -29,7 +34,10 @@ macro_rules! rustlerjar {
         $(
             let instance = Box::new($rustler());
             $(
-                mappings.insert($name.to_string(), instance.name());
+                mappings
+                    .entry($name.to_string())
+                    .or_insert_with(Vec::new)
+                    .push(instance.name());
             )*
             instances.push(instance);
         )*
@@ -42,8 +50,9 @@ macro_rules! rustlerjar {
 ///
 /// A `RustlerJar` is a collection of Rustlers and their corresponding mappings to the markets.
 /// Which indicates which Rustler should be used for a given market. Rustlers are stored as
-/// instances of `Box<dyn Rustler>`, and the mappings are stored as a `HashMap<String, String>` (
-/// where the key is the market short name and the value is the Rustler name).
+/// instances of `Box<dyn Rustler>`, and the mappings are stored as a `HashMap<String, Vec<String>>`
+/// (where the key is the market short name and the value is the ordered list of Rustler names,
+/// primary first).
 ///
 /// **Usage**
 ///
@@ -58,14 +67,17 @@ macro_rules! rustlerjar {
 /// ```
 pub struct RustlerJar {
     rustlers: HashMap<String, Arc<Mutex<Box<dyn Rustler>>>>,
-    mappings: HashMap<String, String>,
+    mappings: HashMap<String, Vec<String>>,
 }
 
 impl RustlerJar {
     /// create a new `RustlerJar` with the given Rustlers and mappings.
     ///
     /// **☢️ warn**: using the `rustlerjar!` macro is recommended
-    pub fn new(rustlers_list: Vec<Box<dyn Rustler>>, mappings: HashMap<String, String>) -> Self {
+    pub fn new(
+        rustlers_list: Vec<Box<dyn Rustler>>,
+        mappings: HashMap<String, Vec<String>>,
+    ) -> Self {
         let mut rustlers = HashMap::new();
         for rustler in rustlers_list {
             rustlers.insert(rustler.name(), Arc::new(Mutex::new(rustler)));
@@ -74,20 +86,33 @@ impl RustlerJar {
         Self { rustlers, mappings }
     }
 
-    /// get the Rustler for the given market
+    /// get the primary Rustler for the given market
     pub fn get(&self, market: &market::Model) -> Option<&Arc<Mutex<Box<dyn Rustler>>>> {
         let key = self.get_key(market)?;
         self.rustlers.get(key)
     }
 
-    /// get the mutable Rustler for the given market as a mutable reference
+    /// get the primary Rustler for the given market as a mutable reference
     pub fn get_mut(&mut self, market: &market::Model) -> Option<&mut Arc<Mutex<Box<dyn Rustler>>>> {
         let key = self.get_key(market)?.to_owned();
         self.rustlers.get_mut(&key)
     }
 
-    /// get the key from the mappings for the given market
+    /// get all the Rustlers for the given market, ordered by priority (primary first)
+    pub fn get_all(&self, market: &market::Model) -> Vec<&Arc<Mutex<Box<dyn Rustler>>>> {
+        self.mappings
+            .get(&market.short_name)
+            .map(|names| names.iter().filter_map(|name| self.rustlers.get(name)).collect())
+            .unwrap_or_default()
+    }
+
+    /// get all the Rustlers in the jar
+    pub fn all(&self) -> impl Iterator<Item = &Arc<Mutex<Box<dyn Rustler>>>> {
+        self.rustlers.values()
+    }
+
+    /// get the key of the primary rustler from the mappings for the given market
     fn get_key(&self, market: &market::Model) -> Option<&String> {
-        self.mappings.get(&market.short_name)
+        self.mappings.get(&market.short_name)?.first()
     }
 }
@@ -0,0 +1,322 @@
+use {
+    crate::entities::market,
+    chrono::{
+        DateTime, Days, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeDelta,
+        TimeZone, Utc,
+    },
+    chrono_tz::Tz,
+    eyre::Result,
+    lool::{fail, logger::warn},
+};
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// 🐎 » parses a fixed utc offset (hours, e.g. `-3`, `+5:30` or `-03:00`) into milliseconds
+pub fn parse_utc_offset(offset: &str) -> Result<i64> {
+    let offset = offset.trim();
+    let (sign, value) = match offset.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+    };
+
+    let (hours, minutes) = value.split_once(':').unwrap_or((value, "0"));
+    match (hours.parse::<i64>(), minutes.parse::<i64>()) {
+        (Ok(h), Ok(m)) if h <= 14 && m < 60 => Ok(sign * (h * 60 + m) * 60_000),
+        _ => fail!("Invalid time zone offset `{}`", offset),
+    }
+}
+
+/// time zone of a market
+#[derive(Debug, Clone, Copy)]
+enum MarketTz {
+    Fixed(FixedOffset),
+    /// follows the daylight saving changes of the zone
+    Named(Tz),
+}
+
+impl MarketTz {
+    fn parse(time_zone: &str) -> Result<Self> {
+        if let Ok(offset) = parse_utc_offset(time_zone) {
+            match FixedOffset::east_opt((offset / 1000) as i32) {
+                Some(offset) => return Ok(Self::Fixed(offset)),
+                None => fail!("Invalid time zone offset `{}`", time_zone),
+            }
+        }
+
+        match time_zone.trim().parse::<Tz>() {
+            Ok(tz) => Ok(Self::Named(tz)),
+            Err(_) => fail!("Unknown time zone `{}`", time_zone),
+        }
+    }
+
+    fn to_local(self, time: i64) -> NaiveDateTime {
+        let utc = DateTime::from_timestamp_millis(time).unwrap_or_default();
+        match self {
+            Self::Fixed(offset) => utc.with_timezone(&offset).naive_local(),
+            Self::Named(tz) => utc.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    fn offset_at(self, time: i64) -> i64 {
+        let utc = DateTime::from_timestamp_millis(time).unwrap_or_default().naive_utc();
+        let offset = match self {
+            Self::Fixed(offset) => offset,
+            Self::Named(tz) => tz.offset_from_utc_datetime(&utc).fix(),
+        };
+
+        offset.local_minus_utc() as i64 * 1000
+    }
+
+    /// converts a local time to utc; times skipped by a daylight saving change use the offset
+    /// before the change, and repeated times resolve to the first one
+    fn to_utc(self, local: NaiveDateTime) -> i64 {
+        let earliest = match self {
+            Self::Fixed(offset) => {
+                offset.from_local_datetime(&local).earliest().map(|t| t.to_utc())
+            }
+            Self::Named(tz) => tz.from_local_datetime(&local).earliest().map(|t| t.to_utc()),
+        };
+
+        let naive = local.and_utc().timestamp_millis();
+        match earliest {
+            Some(time) => time.timestamp_millis(),
+            None => naive - self.offset_at(naive - DAY),
+        }
+    }
+}
+
+impl Default for MarketTz {
+    fn default() -> Self {
+        Self::Fixed(Utc.fix())
+    }
+}
+
+/// #### 🐎 » Market session
+///
+/// Trading calendar of a market: its time zone (a fixed offset or a zone name like
+/// `America/New_York`, which follows daylight saving changes) and the local times its regular
+/// session opens and ends.
+///
+/// Every time belongs to the session of a trading day. Sessions change at the end of the post
+/// market hours (or at midnight for markets without a close time), so a session crossing
+/// midnight (e.g. from 18:00 to 17:00 the next day) belongs to a single trading day: the day it
+/// closes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarketSession {
+    tz: MarketTz,
+    /// local time the regular session opens
+    open: Option<NaiveTime>,
+    /// local time a trading day ends and the next one begins
+    boundary: NaiveTime,
+}
+
+impl MarketSession {
+    /// 🐎 » creates the session of a market from its time zone, open and close times (`HH:MM`
+    /// or `HH:MM:SS`) and the hours of post market trading after the close
+    pub fn new(
+        time_zone: Option<&str>,
+        open_time: Option<&str>,
+        close_time: Option<&str>,
+        post_market_hours: u32,
+    ) -> Result<Self> {
+        let tz = time_zone.map(MarketTz::parse).transpose()?.unwrap_or_default();
+        let open = open_time.map(parse_time).transpose()?;
+
+        let boundary = match close_time.map(parse_time).transpose()? {
+            Some(close) => {
+                close.overflowing_add_signed(TimeDelta::hours(post_market_hours as i64)).0
+            }
+            None => NaiveTime::MIN,
+        };
+
+        Ok(Self { tz, open, boundary })
+    }
+
+    /// 🐎 » returns the session of a market (aligned to utc midnight if its settings are invalid)
+    pub fn of(market: &market::Model) -> Self {
+        let session = Self::new(
+            market.time_zone_offset.as_deref(),
+            market.open_time.as_deref(),
+            market.close_time.as_deref(),
+            market.post_market_offset.unwrap_or(0),
+        );
+
+        match session {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("{} for market '{}', using utc", e, market.short_name);
+                Self::default()
+            }
+        }
+    }
+
+    /// 🐎 » returns the utc offset of the market at the given time, in milliseconds
+    pub fn utc_offset(&self, time: i64) -> i64 {
+        self.tz.offset_at(time)
+    }
+
+    /// 🐎 » returns the trading day of the session containing the given time
+    pub fn trading_day(&self, time: i64) -> NaiveDate {
+        let local = self.tz.to_local(time);
+        let date = local.date();
+
+        if self.boundary != NaiveTime::MIN && local.time() >= self.boundary {
+            date + Days::new(1)
+        } else {
+            date
+        }
+    }
+
+    /// 🐎 » returns the time the session of a trading day starts: its open, or the end of the
+    /// previous trading day if the market has no open time
+    pub fn start(&self, day: NaiveDate) -> i64 {
+        let starts_the_day_before = match self.open {
+            Some(open) => self.boundary != NaiveTime::MIN && open >= self.boundary,
+            None => self.boundary != NaiveTime::MIN,
+        };
+
+        let date = if starts_the_day_before { day - Days::new(1) } else { day };
+        let time = self.open.unwrap_or(self.boundary);
+
+        self.tz.to_utc(date.and_time(time))
+    }
+
+    /// 🐎 » returns the time the trading day begins, which is the end of the previous one
+    pub fn begin(&self, day: NaiveDate) -> i64 {
+        self.end(day - Days::new(1))
+    }
+
+    /// 🐎 » returns the time the trading day ends (exclusive)
+    pub fn end(&self, day: NaiveDate) -> i64 {
+        let date = if self.boundary == NaiveTime::MIN { day + Days::new(1) } else { day };
+        self.tz.to_utc(date.and_time(self.boundary))
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    let time = time.trim();
+    match NaiveTime::parse_from_str(time, "%H:%M:%S").or(NaiveTime::parse_from_str(time, "%H:%M")) {
+        Ok(time) => Ok(time),
+        Err(_) => fail!("Invalid time `{}`", time),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono_tz::America::New_York};
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn local(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    /// milliseconds of a new york local time (`YYYY-MM-DD HH:MM`)
+    fn ny(time: &str) -> i64 {
+        New_York.from_local_datetime(&local(time)).unwrap().timestamp_millis()
+    }
+
+    /// milliseconds of a utc time (`YYYY-MM-DD HH:MM`)
+    fn utc(time: &str) -> i64 {
+        local(time).and_utc().timestamp_millis()
+    }
+
+    #[test]
+    fn utc_offsets_are_parsed() {
+        assert_eq!(parse_utc_offset("-3").unwrap(), -3 * 3_600_000);
+        assert_eq!(parse_utc_offset("+5:30").unwrap(), 330 * 60_000);
+        assert_eq!(parse_utc_offset("-03:00").unwrap(), -3 * 3_600_000);
+        assert!(parse_utc_offset("+15").is_err());
+        assert!(parse_utc_offset("America/New_York").is_err());
+    }
+
+    #[test]
+    fn invalid_sessions_fail() {
+        assert!(MarketSession::new(Some("Mars/Olympus"), None, None, 0).is_err());
+        assert!(MarketSession::new(None, Some("9.30"), None, 0).is_err());
+        assert!(MarketSession::new(None, None, Some("25:00"), 0).is_err());
+    }
+
+    #[test]
+    fn sessions_without_times_follow_the_calendar() {
+        let session = MarketSession::new(Some("-3"), None, None, 0).unwrap();
+
+        assert_eq!(
+            session.trading_day(utc("2026-03-06 02:59")),
+            date("2026-03-05")
+        );
+        assert_eq!(
+            session.trading_day(utc("2026-03-06 03:00")),
+            date("2026-03-06")
+        );
+        assert_eq!(session.start(date("2026-03-06")), utc("2026-03-06 03:00"));
+        assert_eq!(session.end(date("2026-03-06")), utc("2026-03-07 03:00"));
+        assert_eq!(session.begin(date("2026-03-06")), utc("2026-03-06 03:00"));
+
+        let session = MarketSession::default();
+        assert_eq!(session.start(date("2026-03-06")), utc("2026-03-06 00:00"));
+        assert_eq!(session.utc_offset(utc("2026-03-06 00:00")), 0);
+    }
+
+    #[test]
+    fn trading_days_end_with_the_post_market_hours() {
+        let session =
+            MarketSession::new(Some("America/New_York"), Some("09:30"), Some("16:00"), 4).unwrap();
+
+        assert_eq!(
+            session.trading_day(ny("2026-03-06 04:00")),
+            date("2026-03-06")
+        );
+        assert_eq!(
+            session.trading_day(ny("2026-03-06 19:59")),
+            date("2026-03-06")
+        );
+        assert_eq!(
+            session.trading_day(ny("2026-03-06 20:00")),
+            date("2026-03-07")
+        );
+
+        assert_eq!(session.start(date("2026-03-06")), ny("2026-03-06 09:30"));
+        assert_eq!(session.begin(date("2026-03-06")), ny("2026-03-05 20:00"));
+        assert_eq!(session.end(date("2026-03-06")), ny("2026-03-06 20:00"));
+    }
+
+    #[test]
+    fn sessions_crossing_midnight_belong_to_the_day_they_close() {
+        let session = MarketSession::new(Some("-5"), Some("18:00"), Some("17:00"), 0).unwrap();
+
+        assert_eq!(
+            session.trading_day(utc("2026-03-06 01:00")),
+            date("2026-03-06")
+        );
+        assert_eq!(
+            session.trading_day(utc("2026-03-06 07:00")),
+            date("2026-03-06")
+        );
+        assert_eq!(
+            session.trading_day(utc("2026-03-06 22:00")),
+            date("2026-03-07")
+        );
+
+        assert_eq!(session.start(date("2026-03-06")), utc("2026-03-05 23:00"));
+        assert_eq!(session.end(date("2026-03-06")), utc("2026-03-06 22:00"));
+    }
+
+    #[test]
+    fn named_time_zones_follow_daylight_saving() {
+        let session =
+            MarketSession::new(Some("America/New_York"), Some("09:30"), Some("16:00"), 0).unwrap();
+
+        // daylight saving starts on 2026-03-08
+        assert_eq!(session.start(date("2026-03-06")), utc("2026-03-06 14:30"));
+        assert_eq!(session.start(date("2026-03-09")), utc("2026-03-09 13:30"));
+        assert_eq!(session.utc_offset(utc("2026-03-06 14:30")), -5 * 3_600_000);
+        assert_eq!(session.utc_offset(utc("2026-03-09 13:30")), -4 * 3_600_000);
+
+        // times skipped by the change use the offset before it
+        let session = MarketSession::new(Some("America/New_York"), Some("02:30"), None, 0).unwrap();
+        assert_eq!(session.start(date("2026-03-08")), utc("2026-03-08 07:30"));
+    }
+}
@@ -0,0 +1,436 @@
+use {
+    super::{session::MarketSession, Quote},
+    crate::bus::{
+        redis::{key, RedisClient},
+        SnapshotTrait,
+    },
+    chrono::Utc,
+    eyre::Result,
+    lool::s,
+    redis::AsyncCommands,
+    serde::{Deserialize, Serialize},
+    std::collections::{hash_map::Entry, HashMap},
+};
+
+/// #### 🐎 » Session statistics of a ticker
+///
+/// Running statistics of the current session, updated with every quote of the ticker and reset
+/// when its market opens. They're stored in the ticker's quote hash, next to the quote fields.
+///
+/// The [`RustlersSvc`] attaches them to every quote it publishes, so each quote overwrites all
+/// the statistics fields of the hash (the vwap is left empty when it's unknown), and a reset
+/// takes effect with the first quote of the new session. Quotes published without statistics
+/// (e.g. by other services) leave the fields of the hash as they are.
+///
+/// [`RustlersSvc`]: super::svc::RustlersSvc
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    /// volume weighted average price, if the provider reports the session volume
+    pub vwap: Option<f64>,
+    /// number of quotes received in the session
+    pub ticks: u64,
+    /// time of the last trade (unix timestamp in milliseconds)
+    ///
+    /// when the provider doesn't report the session volume, every quote is considered a trade
+    pub last_trade_time: i64,
+    /// last session volume seen
+    #[serde(skip)]
+    volume: Option<f64>,
+    /// sum of `price * volume` of the trades of the session
+    #[serde(skip)]
+    turnover: f64,
+    /// volume used to compute the vwap
+    #[serde(skip)]
+    traded: f64,
+}
+
+impl SessionStats {
+    /// 🐎 » creates the statistics of a session from its first quote
+    pub fn from(quote: &Quote) -> Self {
+        Self {
+            open: quote.price,
+            high: quote.price,
+            low: quote.price,
+            vwap: None,
+            ticks: 1,
+            last_trade_time: quote.time,
+            // the volume traded before the first quote isn't weighted by any known price
+            volume: quote.volume,
+            turnover: 0.0,
+            traded: 0.0,
+        }
+    }
+
+    /// 🐎 » updates the statistics with a new quote of the session
+    pub fn update(&mut self, quote: &Quote) {
+        self.high = self.high.max(quote.price);
+        self.low = self.low.min(quote.price);
+        self.ticks += 1;
+
+        let Some(volume) = quote.volume else {
+            self.last_trade_time = self.last_trade_time.max(quote.time);
+            return;
+        };
+
+        // the session volume only grows, so anything else is ignored
+        let traded = self.volume.map(|v| volume - v).unwrap_or_default();
+        if traded > 0.0 {
+            self.turnover += quote.price * traded;
+            self.traded += traded;
+            self.vwap = Some(self.turnover / self.traded);
+            self.last_trade_time = self.last_trade_time.max(quote.time);
+        }
+
+        self.volume = Some(self.volume.map_or(volume, |v| v.max(volume)));
+    }
+
+    /// 🐎 » returns the statistics read from a quote hash (see [`Self::from_bus_val`]), ready to
+    /// be updated with the next quotes of the session
+    ///
+    /// the vwap accumulators aren't stored, so they're approximated from the session volume of
+    /// the quote (as if the whole volume had been traded at the vwap)
+    pub fn resumed(mut self, quote: &Quote) -> Self {
+        self.volume = quote.volume;
+        if let (Some(vwap), Some(volume)) = (self.vwap, quote.volume) {
+            self.traded = volume;
+            self.turnover = vwap * volume;
+        }
+
+        self
+    }
+
+    /// 🐎 » returns the statistics with their prices multiplied by the given rate (e.g. to
+    /// convert them to another currency)
+    pub fn converted(&self, rate: f64) -> Self {
+        Self {
+            open: self.open * rate,
+            high: self.high * rate,
+            low: self.low * rate,
+            vwap: self.vwap.map(|v| v * rate),
+            turnover: self.turnover * rate,
+            ..self.clone()
+        }
+    }
+
+    /// 🐎 » returns the fields stored in the ticker's quote hash
+    pub fn to_bus_val(&self) -> Vec<(String, String)> {
+        vec![
+            (s!("session_open"), self.open.to_string()),
+            (s!("session_high"), self.high.to_string()),
+            (s!("session_low"), self.low.to_string()),
+            (
+                s!("session_vwap"),
+                self.vwap.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+            (s!("session_ticks"), self.ticks.to_string()),
+            (
+                s!("session_last_trade_time"),
+                self.last_trade_time.to_string(),
+            ),
+        ]
+    }
+
+    /// 🐎 » reads the statistics back from the fields of a ticker's quote hash
+    ///
+    /// returns `None` if the hash has no statistics or they're invalid
+    pub fn from_bus_val(fields: &HashMap<String, String>) -> Option<Self> {
+        let get = |name: &str| fields.get(name).filter(|v| !v.is_empty());
+
+        Some(Self {
+            open: get("session_open")?.parse().ok()?,
+            high: get("session_high")?.parse().ok()?,
+            low: get("session_low")?.parse().ok()?,
+            vwap: get("session_vwap").and_then(|v| v.parse().ok()),
+            ticks: get("session_ticks")?.parse().ok()?,
+            last_trade_time: get("session_last_trade_time")?.parse().ok()?,
+            ..Default::default()
+        })
+    }
+}
+
+/// #### 🐎 » Session statistics store
+///
+/// Keeps the [`SessionStats`] of every ticker, updating them with the quotes handled by the
+/// [`RustlersSvc`] and resetting the ones of a market when it opens.
+///
+/// [`RustlersSvc`]: super::svc::RustlersSvc
+#[derive(Debug, Default)]
+pub struct SessionStatsStore {
+    stats: HashMap<String, SessionStats>,
+    /// session of each market, by market name
+    sessions: HashMap<String, MarketSession>,
+}
+
+impl SessionStatsStore {
+    /// 🐎 » creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 🐎 » sets the session of a market, used to tell whether stored statistics belong to the
+    /// current trading day (see [`Self::seed`])
+    ///
+    /// `market` is the market name used in the quotes (the market's public name if it has one);
+    /// markets without a session follow the utc calendar
+    pub fn set_session(&mut self, market: &str, session: MarketSession) {
+        self.sessions.insert(market.to_owned(), session);
+    }
+
+    /// 🐎 » returns the statistics of a ticker, given its key (`market:symbol`)
+    pub fn get(&self, key: &str) -> Option<&SessionStats> {
+        self.stats.get(key)
+    }
+
+    /// 🐎 » updates the statistics of the quote's ticker and attaches them to the quote, so
+    /// they're stored along with it when it's published
+    pub fn apply(&mut self, quote: &mut Quote) {
+        let key = format!("{}:{}", quote.market, quote.id);
+
+        let stats = match self.stats.get_mut(&key) {
+            Some(stats) => {
+                stats.update(quote);
+                stats.clone()
+            }
+            None => {
+                let stats = SessionStats::from(quote);
+                self.stats.insert(key, stats.clone());
+                stats
+            }
+        };
+
+        quote.stats = Some(stats);
+    }
+
+    /// 🐎 » seeds the statistics of the tickers from their last published quotes (e.g. read from
+    /// a [`SnapshotTrait`] after a restart), keeping the ones already in the store
+    ///
+    /// only the statistics of quotes of the trading day of their market at the given time (unix
+    /// timestamp in milliseconds) are seeded, as the older ones belong to a previous session;
+    /// returns the number of seeded tickers
+    pub fn seed(&mut self, quotes: Vec<Quote>, now: i64) -> usize {
+        let mut seeded = 0;
+
+        for mut quote in quotes {
+            let session = self.sessions.get(&quote.market).copied().unwrap_or_default();
+            let current = session.trading_day(quote.time) == session.trading_day(now);
+
+            let Some(stats) = quote.stats.take().filter(|_| current) else {
+                continue;
+            };
+
+            let key = format!("{}:{}", quote.market, quote.id);
+            if let Entry::Vacant(entry) = self.stats.entry(key) {
+                entry.insert(stats.resumed(&quote));
+                seeded += 1;
+            }
+        }
+
+        seeded
+    }
+
+    /// 🐎 » loads the statistics of today's sessions from the quote hashes of a snapshot (see
+    /// [`Self::seed`])
+    pub async fn load<S: SnapshotTrait<Quote> + ?Sized>(
+        &mut self,
+        snapshot: &mut S,
+    ) -> Result<usize> {
+        let quotes = snapshot.get_many("quote:*").await?;
+        Ok(self.seed(quotes, Utc::now().timestamp_millis()))
+    }
+
+    /// 🐎 » resets the statistics of every ticker of a market at the start of a session
+    ///
+    /// `market` is the market name used in the quotes (the market's public name if it has one)
+    pub fn reset(&mut self, market: &str) {
+        let prefix = format!("{}:", market);
+        self.stats.retain(|key, _| !key.starts_with(&prefix));
+    }
+}
+
+/// 🐎 » reads the session statistics of a ticker from its quote hash in the bus
+///
+/// the prefix is the one the quotes were published with, e.g. `rustler` (the default)
+///
+/// **Usage**
+///
+/// ```rust
+/// let stats = stats::snapshot(&"redis://127.0.0.1/", "rustler", "NASDAQ", "AAPL").await?;
+/// ```
+pub async fn snapshot<RC: RedisClient>(
+    redis: &RC,
+    prefix: &str,
+    market: &str,
+    symbol: &str,
+) -> Result<Option<SessionStats>> {
+    let mut conn = redis.get_client()?.get_multiplexed_tokio_connection().await?;
+    let fields: HashMap<String, String> =
+        conn.hgetall(key(prefix, format!("quote:{}:{}", market, symbol))).await?;
+
+    Ok(SessionStats::from_bus_val(&fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::rustlers::MarketHourType};
+
+    /// 2026-03-06 15:00 utc (10:00 in new york)
+    const NOW: i64 = 1_772_809_200_000;
+    const HOUR: i64 = 3_600_000;
+
+    fn quote(market: &str, time: i64, price: f64, volume: Option<f64>) -> Quote {
+        Quote {
+            id: "AAPL".into(),
+            market: market.into(),
+            price,
+            change_percent: 0.0,
+            time,
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume,
+            stats: None,
+        }
+    }
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn vwap_is_weighted_by_the_session_volume_deltas() {
+        let mut stats = SessionStats::from(&quote("NASDAQ", 1, 10.0, Some(100.0)));
+        assert_eq!(stats.vwap, None);
+
+        stats.update(&quote("NASDAQ", 2, 11.0, Some(150.0)));
+        assert!(close(stats.vwap, 11.0));
+
+        stats.update(&quote("NASDAQ", 3, 12.0, Some(200.0)));
+        assert!(close(stats.vwap, 11.5));
+
+        // a shrinking volume isn't a trade
+        stats.update(&quote("NASDAQ", 4, 13.0, Some(180.0)));
+        assert!(close(stats.vwap, 11.5));
+        assert_eq!(stats.last_trade_time, 3);
+
+        // and the next trades are counted from the largest volume seen
+        stats.update(&quote("NASDAQ", 5, 10.0, Some(220.0)));
+        assert!(close(stats.vwap, 1350.0 / 120.0));
+        assert_eq!(stats.last_trade_time, 5);
+
+        assert_eq!((stats.open, stats.high, stats.low), (10.0, 13.0, 10.0));
+        assert_eq!(stats.ticks, 5);
+    }
+
+    #[test]
+    fn quotes_without_volume_are_trades_without_vwap() {
+        let mut stats = SessionStats::from(&quote("NASDAQ", 1, 10.0, None));
+        stats.update(&quote("NASDAQ", 3, 9.0, None));
+        stats.update(&quote("NASDAQ", 2, 11.0, None));
+
+        assert_eq!(stats.vwap, None);
+        assert_eq!(stats.last_trade_time, 3);
+        assert_eq!(
+            (stats.open, stats.high, stats.low, stats.ticks),
+            (10.0, 11.0, 9.0, 3)
+        );
+
+        // the volume reported before the first known volume isn't weighted by any price
+        stats.update(&quote("NASDAQ", 4, 12.0, Some(1000.0)));
+        assert_eq!(stats.vwap, None);
+        stats.update(&quote("NASDAQ", 5, 12.0, Some(1010.0)));
+        assert!(close(stats.vwap, 12.0));
+    }
+
+    #[test]
+    fn resumed_stats_approximate_the_vwap_from_the_session_volume() {
+        let mut stats = SessionStats::from(&quote("NASDAQ", 1, 10.0, Some(100.0)));
+        stats.update(&quote("NASDAQ", 2, 11.0, Some(150.0)));
+        stats.update(&quote("NASDAQ", 3, 12.0, Some(200.0)));
+
+        let last = Quote {
+            stats: Some(stats.clone()),
+            ..quote("NASDAQ", 3, 12.0, Some(200.0))
+        };
+        let stored = SessionStats::from_bus_val(&last.to_bus_val().into_iter().collect()).unwrap();
+        let mut resumed = stored.resumed(&last);
+        assert_eq!(
+            (resumed.open, resumed.ticks, resumed.vwap),
+            (10.0, 3, stats.vwap)
+        );
+
+        // as if the 200 traded so far had been traded at the vwap
+        resumed.update(&quote("NASDAQ", 4, 14.0, Some(300.0)));
+        assert!(close(resumed.vwap, (11.5 * 200.0 + 14.0 * 100.0) / 300.0));
+
+        // shrinking volumes are still ignored
+        resumed.update(&quote("NASDAQ", 5, 1.0, Some(250.0)));
+        assert!(close(resumed.vwap, (11.5 * 200.0 + 14.0 * 100.0) / 300.0));
+    }
+
+    #[test]
+    fn only_the_stats_of_the_current_trading_day_are_seeded() {
+        let session =
+            MarketSession::new(Some("America/New_York"), Some("09:30"), Some("16:00"), 4).unwrap();
+
+        let mut store = SessionStatsStore::new();
+        store.set_session("NASDAQ", session);
+
+        let with_stats = |market: &str, time: i64| {
+            let mut quote = quote(market, time, 10.0, None);
+            quote.stats = Some(SessionStats::from(&quote));
+            quote
+        };
+
+        // 00:00 utc is 19:00 of the previous trading day in new york, but the same day in utc
+        let quotes = vec![
+            with_stats("NASDAQ", NOW - HOUR),
+            Quote {
+                id: "MSFT".into(),
+                ..with_stats("NASDAQ", NOW - 15 * HOUR)
+            },
+            with_stats("NYSE", NOW - 15 * HOUR),
+            quote("BCBA", NOW, 10.0, None),
+        ];
+
+        assert_eq!(store.seed(quotes, NOW), 2);
+        assert!(store.get("NASDAQ:AAPL").is_some());
+        assert!(store.get("NASDAQ:MSFT").is_none());
+        assert!(store.get("NYSE:AAPL").is_some());
+        assert!(store.get("BCBA:AAPL").is_none());
+    }
+
+    #[test]
+    fn seeding_keeps_the_stats_already_in_the_store() {
+        let mut store = SessionStatsStore::new();
+        store.apply(&mut quote("NASDAQ", NOW, 20.0, None));
+
+        let mut stored = quote("NASDAQ", NOW, 10.0, None);
+        stored.stats = Some(SessionStats::from(&stored));
+
+        assert_eq!(store.seed(vec![stored], NOW), 0);
+        assert_eq!(store.get("NASDAQ:AAPL").unwrap().open, 20.0);
+    }
+
+    #[test]
+    fn reset_clears_the_stats_of_a_market() {
+        let mut store = SessionStatsStore::new();
+
+        let mut first = quote("NASDAQ", 1, 10.0, None);
+        store.apply(&mut first);
+        store.apply(&mut quote("NASDAQ", 2, 12.0, None));
+        store.apply(&mut quote("NYSE", 2, 12.0, None));
+
+        assert_eq!(first.stats.unwrap().ticks, 1);
+        assert_eq!(store.get("NASDAQ:AAPL").unwrap().ticks, 2);
+
+        store.reset("NASDAQ");
+        assert!(store.get("NASDAQ:AAPL").is_none());
+        assert!(store.get("NYSE:AAPL").is_some());
+
+        let mut next = quote("NASDAQ", 3, 11.0, None);
+        store.apply(&mut next);
+        assert_eq!(next.stats.map(|s| (s.open, s.ticks)), Some((11.0, 1)));
+    }
+}
@@ -1,15 +1,20 @@
 use {
     super::{
+        cleanup, config,
+        reference::{ChangeReference, ReferencePriceStore},
         rustler::{Rustler, Ticker},
         rustlerjar::RustlerJar,
-        MarketHourType,
+        session::MarketSession,
+        stats::SessionStatsStore,
+        MarketHourType, RustlerStatus,
     },
     crate::{
-        bus::PublisherTrait,
-        entities::{market, sea_orm::DatabaseConnection, ticker},
+        bus::{redis::RedisClient, PublisherTrait, SnapshotTrait},
+        entities::{market, sea_orm::DatabaseConnection, ticker, ticker_alias},
         rustlers::Quote,
     },
-    eyre::Result,
+    chrono::Local,
+    eyre::{Result, WrapErr},
     lool::{
         fail,
         logger::{info, warn},
@@ -18,15 +23,31 @@ use {
             SchedulingRule,
         },
     },
-    std::sync::Arc,
-    tokio::sync::{mpsc::Sender, Mutex},
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+    tokio::sync::{
+        mpsc::{self, Sender},
+        Mutex, RwLock,
+    },
 };
 
 /// #### 🐎 » Rustler Message
 pub enum RustlerMsg {
     QuoteMsg(Quote),
+    /// status change detected by a background task of the rustler (e.g. the connection task of
+    /// a websocket rustler reconnecting), applied by the service to the rustler
+    StatusMsg(RustlerStatus),
 }
 
+/// #### 🐎 » sender used by the rustlers to send messages to the rustlers service
+pub type MsgSender = Sender<RustlerMsg>;
+
 /// #### 🐎 » create a quote message
 #[inline]
 pub fn quote(
@@ -44,6 +65,9 @@ pub fn quote(
         change_percent,
         time,
         market_hours,
+        source: None,
+        volume: None,
+        stats: None,
     })
 }
 
@@ -53,17 +77,120 @@ pub fn to_msg(quote: Quote) -> RustlerMsg {
     RustlerMsg::QuoteMsg(quote)
 }
 
+/// #### 🐎 » Failover options
+///
+/// Controls how the `RustlersSvc` decides that the active rustler of a market with fallback
+/// rustlers has failed, and when it can switch back to a rustler with a higher priority.
+#[derive(Debug, Clone)]
+pub struct FailoverOpts {
+    /// interval between health checks of the rustlers serving a market
+    pub check_interval: Duration,
+    /// time without quotes after which a connected rustler is considered stale
+    pub stale_after: Duration,
+}
+
+impl Default for FailoverOpts {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(10),
+            stale_after: Duration::from_secs(60),
+        }
+    }
+}
+
+type SharedRustler = Arc<Mutex<Box<dyn Rustler>>>;
+
+/// time of the last quote produced by a route rustler, shared by the clones of the rustler so it
+/// can be updated without locking the routes for writing
+#[derive(Clone, Default)]
+struct LastQuote(Arc<std::sync::Mutex<Option<Instant>>>);
+
+impl LastQuote {
+    fn get(&self) -> Option<Instant> {
+        self.0.lock().map(|at| *at).unwrap_or_default()
+    }
+
+    fn set(&self, at: Option<Instant>) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = at;
+        }
+    }
+}
+
+/// a rustler serving a market, with the market's tickers using the rustler's provider symbols
+#[derive(Clone)]
+struct RouteRustler {
+    name: String,
+    rustler: SharedRustler,
+    tickers: Vec<Ticker>,
+    /// provider symbol -> canonical symbol
+    canonical: HashMap<String, String>,
+    last_quote: LastQuote,
+}
+
+/// a market and the ordered list of rustlers that can serve its tickers
+struct Route {
+    market: String,
+    tickers: Vec<Ticker>,
+    rustlers: Vec<RouteRustler>,
+    /// index of the rustler whose quotes are published
+    active: AtomicUsize,
+    running: bool,
+}
+
+impl Route {
+    fn position(&self, rustler_name: &str) -> Option<usize> {
+        self.rustlers.iter().position(|r| r.name == rustler_name)
+    }
+
+    fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn set_active(&self, idx: usize) {
+        self.active.store(idx, Ordering::Relaxed);
+    }
+}
+
+/// routes indexed by the market name used in the published quotes
+type Routes = Arc<RwLock<HashMap<String, Route>>>;
+
 /// #### 🐎 » Rustlers Service
 ///
 /// `RustlersSvc` is a service that manages the rustlers and orchestrates their executions.
+///
+/// When a market is mapped to more than one rustler in the [`RustlerJar`], the service runs the
+/// primary one and automatically moves the market's tickers to the next rustler when the primary
+/// fails to connect or goes stale, switching back once the primary recovers (see
+/// [`FailoverOpts`]).
+///
+/// The session statistics of the tickers are kept in memory; set a snapshot of the published
+/// quotes (see [`Self::set_stats_snapshot`]) to resume them after a restart.
+///
+/// The quote hashes of the tickers that aren't active anymore can be removed from redis when the
+/// service starts (see [`Self::set_orphan_cleanup`]).
+///
+/// Quotes that can't be published (e.g. while the bus is unreachable) are logged, counted (see
+/// [`Self::publish_errors`]) and dropped; the service keeps routing the following ones.
 pub struct RustlersSvc<P>
 where
     P: PublisherTrait<Quote> + Send + Sync + 'static + Clone,
 {
+    conn: DatabaseConnection,
     market_svc: market::Service,
+    alias_svc: ticker_alias::Service,
     sched: Scheduler,
     rustlers: RustlerJar,
     publisher: P,
+    routes: Routes,
+    senders: HashMap<String, Sender<RustlerMsg>>,
+    failover_opts: FailoverOpts,
+    references: Arc<Mutex<ReferencePriceStore>>,
+    stats: Arc<Mutex<SessionStatsStore>>,
+    stats_snapshot: Option<Box<dyn SnapshotTrait<Quote> + Send + Sync>>,
+    publish_errors: Arc<AtomicU64>,
+    /// redis client and key prefixes of the quote hashes removed at startup
+    orphan_cleanup: Option<(redis::Client, Vec<String>)>,
 }
 
 impl<Publisher> RustlersSvc<Publisher>
@@ -81,43 +208,136 @@ where
     /// **Returns**
     /// the created `RustlersSvc` instance
     pub async fn new(conn: DatabaseConnection, rustlers: RustlerJar, publisher: Publisher) -> Self {
-        let market_svc = market::Service::new(conn).await;
+        let market_svc = market::Service::new(conn.clone()).await;
+        let alias_svc = ticker_alias::Service::new(conn.clone()).await;
+        let references = ReferencePriceStore::new(conn.clone()).await;
         let sched = Scheduler::new();
 
         Self {
+            conn,
             market_svc,
+            alias_svc,
             rustlers,
             sched,
             publisher,
+            routes: Arc::new(RwLock::new(HashMap::new())),
+            senders: HashMap::new(),
+            failover_opts: FailoverOpts::default(),
+            references: Arc::new(Mutex::new(references)),
+            stats: Arc::new(Mutex::new(SessionStatsStore::new())),
+            stats_snapshot: None,
+            publish_errors: Arc::new(AtomicU64::new(0)),
+            orphan_cleanup: None,
         }
     }
 
+    /// #### 🐎 » set failover options
+    ///
+    /// sets the options used to decide when to switch between the rustlers of a market
+    pub fn set_failover_opts(&mut self, opts: FailoverOpts) -> &mut Self {
+        self.failover_opts = opts;
+        self
+    }
+
+    /// #### 🐎 » set stats snapshot
+    ///
+    /// sets the snapshot the session statistics are seeded from when the service starts, so a
+    /// restart doesn't reset them. It must read the hashes the publisher writes (same prefix).
+    pub fn set_stats_snapshot<S>(&mut self, snapshot: S) -> &mut Self
+    where
+        S: SnapshotTrait<Quote> + Send + Sync + 'static,
+    {
+        self.stats_snapshot = Some(Box::new(snapshot));
+        self
+    }
+
+    /// #### 🐎 » set orphan cleanup
+    ///
+    /// removes the quote hashes under the given key prefixes (the ones the quotes are published
+    /// with) whose ticker isn't active anymore when the service starts (see
+    /// [`cleanup::remove_orphaned_quotes`])
+    pub fn set_orphan_cleanup<RC: RedisClient>(
+        &mut self,
+        redis: &RC,
+        prefixes: &[&str],
+    ) -> Result<&mut Self> {
+        let prefixes = prefixes.iter().map(|p| p.to_string()).collect();
+        self.orphan_cleanup = Some((redis.get_client()?, prefixes));
+        Ok(self)
+    }
+
+    /// #### 🐎 » publish errors
+    ///
+    /// returns a counter of the quotes that couldn't be published, shared with the running
+    /// service
+    pub fn publish_errors(&self) -> Arc<AtomicU64> {
+        self.publish_errors.clone()
+    }
+
     /// #### 🐎 » start rustlers
     ///
-    /// gets market data from the the database and starts
+    /// configures the rustlers, gets market data from the the database and starts
     /// the corresponding rustler for each market
+    ///
+    /// fails right away if any of the rustler configurations is invalid
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting rustlers");
+        self.configure_rustlers().await?;
+
         let markets = self.market_svc.get_all_with_tickers().await?;
 
+        {
+            let mut references = self.references.lock().await;
+            references.load().await?;
+            let mut stats = self.stats.lock().await;
+
+            for (market, _) in &markets {
+                references.set_reference(&route_key(market), ChangeReference::of(market));
+                stats.set_session(&route_key(market), MarketSession::of(market));
+            }
+        }
+
+        self.remove_orphaned_quotes();
+
         if !markets.is_empty() {
-            let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+            let (sender, mut receiver) = mpsc::channel(100);
+            let aliases = self.alias_svc.get_all_by_ticker_and_rustler().await?;
 
             for (market, tickers) in markets {
-                self.schedule_rustler_for((market, tickers), sender.clone()).await?;
+                self.schedule_rustler_for((market, tickers), &aliases, sender.clone()).await?;
+            }
+
+            let has_fallbacks = self.routes.read().await.values().any(|r| r.rustlers.len() > 1);
+            if has_fallbacks {
+                tokio::spawn(Self::watch_routes(
+                    self.routes.clone(),
+                    self.failover_opts.clone(),
+                ));
+            }
+
+            // the routes that are open were just started (resetting their stats), so the stats of
+            // the current sessions are seeded right before handling their quotes
+            if let Some(snapshot) = self.stats_snapshot.as_mut() {
+                match self.stats.lock().await.load(snapshot.as_mut()).await {
+                    Ok(seeded) => info!("Seeded the session stats of {} tickers", seeded),
+                    Err(e) => warn!("Failed to seed the session stats: {}", e),
+                }
             }
 
             // NOTE: if we wanted to stop all the rustlers for good for some reason, we should
             // use a select! instead and listen for a stop signal coming from somewhere
             let mut publisher = self.publisher.clone();
-            while let Some(msg) = receiver.recv().await {
+            while let Some((source, msg)) = receiver.recv().await {
                 match msg {
                     // TODO: if we want to restart a dead rustler, we should listen for a restart
                     //       signal here and restart the rustler. The restart signal does not exist
                     //       yet, so we will need to implement it and send it from rustlers when
                     //       it makes sense to restart them (when we are sure we are not going to
                     //       keep listening for quotes from the source feed, for example)
-                    RustlerMsg::QuoteMsg(quote) => publisher.publish(quote).await?,
+                    RustlerMsg::QuoteMsg(quote) => {
+                        self.handle_quote(&mut publisher, &source, quote).await
+                    }
+                    RustlerMsg::StatusMsg(status) => self.set_rustler_status(&source, status).await,
                 }
             }
 
@@ -139,79 +359,421 @@ where
         todo!()
     }
 
-    /// gets the right rustler for the given market and starts it
+    /// loads the rustler configurations (see [`config::load`]) and delivers them to the rustlers
+    ///
+    /// fails if a rustler that requires a configuration (see [`Rustler::requires_config`]) has none
+    async fn configure_rustlers(&mut self) -> Result<()> {
+        let mut configs = config::load(self.conn.clone()).await?;
+
+        for rustler in self.rustlers.all() {
+            let mut rustler = rustler.lock().await;
+            let name = rustler.name();
+
+            if let Some(config) = configs.remove(&name) {
+                rustler
+                    .configure(config)
+                    .await
+                    .wrap_err_with(|| format!("Invalid configuration for rustler '{}'", name))?;
+
+                info!("Rustler '{}' configured", name);
+            } else if rustler.requires_config() {
+                fail!("No configuration found for rustler '{}'", name);
+            }
+        }
+
+        for name in configs.keys() {
+            warn!("Found configuration for unknown rustler '{}'", name);
+        }
+
+        Ok(())
+    }
+
+    /// applies a status reported by a background task of a rustler, unless the rustler was
+    /// stopped in the meantime
+    async fn set_rustler_status(&self, name: &str, status: RustlerStatus) {
+        for rustler in self.rustlers.all() {
+            let mut rustler = rustler.lock().await;
+            if rustler.name() != name {
+                continue;
+            }
+
+            if !rustler.is_disconnected_or_disconnecting() {
+                if let Err(e) = rustler.set_status(status) {
+                    warn!("Failed to set the status of rustler '{}': {}", name, e);
+                }
+            }
+
+            return;
+        }
+    }
+
+    /// gets the right rustlers for the given market and starts the primary one
     ///
     /// depending on the market configuraation, the rustler might be started
     /// immediately or its start might be scheduled for a later time
     ///
-    /// this function also schedules the stop of the rustler at the end of the market
+    /// this function also schedules the stop of the rustlers at the end of the market
     /// trading hours if the market is configured to stop at a specific time
     async fn schedule_rustler_for(
         &mut self,
         market: (market::Model, Vec<ticker::Model>),
-        sender: Sender<RustlerMsg>,
+        aliases: &HashMap<(String, String), String>,
+        sender: Sender<(String, RustlerMsg)>,
     ) -> Result<()> {
-        let (market, tickers) = market;
-        let tickers: Vec<Ticker> = tickers.into_iter().map(|t| Ticker::from(&t, &market)).collect();
+        let (market, models) = market;
+        let tickers = Ticker::many_from(&models, &market);
 
         let rules = self.get_schedule_rules_for(&market)?;
-        let rustler = self.rustlers.get(&market);
+        let chain: Vec<SharedRustler> =
+            self.rustlers.get_all(&market).into_iter().cloned().collect();
 
-        if let Some(rustler) = rustler {
-            {
-                let mut rustler = rustler.lock().await;
-                info!("Setting message sender for rustler '{}'", rustler.name());
-                rustler.set_msg_sender(Some(sender))
+        if chain.is_empty() {
+            warn!("No rustler found for market '{}'", market.short_name);
+            return Ok(());
+        }
+
+        let mut rustlers = Vec::with_capacity(chain.len());
+        for rustler in chain {
+            let name = rustler.lock().await.name();
+            let msg_sender = self.sender_for(&name, &sender);
+
+            info!("Setting message sender for rustler '{}'", name);
+            rustler.lock().await.set_msg_sender(Some(msg_sender));
+
+            let (tickers, canonical) = rustler_tickers(&name, &models, &market, aliases);
+
+            rustlers.push(RouteRustler {
+                name,
+                rustler,
+                tickers,
+                canonical,
+                last_quote: LastQuote::default(),
+            });
+        }
+
+        let route_key = route_key(&market);
+        self.routes.write().await.insert(
+            route_key.clone(),
+            Route {
+                market: market.short_name.clone(),
+                tickers,
+                rustlers,
+                active: AtomicUsize::new(0),
+                running: false,
+            },
+        );
+
+        let start_name = format!("start-rustler-{}", market.short_name);
+        let end_name = format!("end-rustler-{}", market.short_name);
+
+        if let Some((start, stop)) = &rules {
+            // TODO: we will need to store the job handlers in the `RustlersSvc` struct
+            //       so that we can stop them when we need to restart the rustlers
+
+            let start_job = self
+                .sched
+                .schedule_fut(
+                    start_name.to_owned(),
+                    Self::start_route(self.routes.clone(), self.stats.clone(), route_key.clone()),
+                    start.clone(),
+                )
+                .await;
+
+            let end_job = self
+                .sched
+                .schedule_fut(
+                    end_name.to_owned(),
+                    Self::stop_route(
+                        self.routes.clone(),
+                        self.references.clone(),
+                        route_key.clone(),
+                    ),
+                    stop.clone(),
+                )
+                .await;
+
+            info!(
+                "Scheduled next execution for start job {start_name} for market '{}' at {:?}",
+                market.short_name,
+                start_job.get_next_run()
+            );
+            info!(
+                "Scheduled next execution for stop job {end_name} for market '{}' at {:?}",
+                market.short_name,
+                end_job.get_next_run()
+            );
+        } else {
+            info!("No schedule rules found for market '{}'", market.short_name);
+
+            // markets without trading hours never close, so their session rolls over at midnight
+            let roll_over_name = format!("roll-over-{}", market.short_name);
+            let mut rule = ruleset();
+            rule.at_time(0, 0, 0);
+
+            let roll_over_job = self
+                .sched
+                .schedule_fut(
+                    roll_over_name.to_owned(),
+                    Self::roll_over_day(
+                        self.routes.clone(),
+                        self.references.clone(),
+                        self.stats.clone(),
+                        route_key.clone(),
+                    ),
+                    recur(&rule),
+                )
+                .await;
+
+            info!(
+                "Scheduled next execution for job {roll_over_name} for market '{}' at {:?}",
+                market.short_name,
+                roll_over_job.get_next_run()
+            );
+        }
+
+        if should_be_running_now(rules) {
+            info!("Starting '{start_name}' right away");
+            Self::start_route(self.routes.clone(), self.stats.clone(), route_key).await;
+        }
+
+        Ok(())
+    }
+
+    /// returns the message sender for the given rustler, creating it if needed
+    ///
+    /// every rustler gets its own sender so that the messages it sends can be tagged with the
+    /// name of the rustler before reaching the main receiver
+    fn sender_for(
+        &mut self,
+        rustler_name: &str,
+        main_sender: &Sender<(String, RustlerMsg)>,
+    ) -> Sender<RustlerMsg> {
+        self.senders
+            .entry(rustler_name.to_owned())
+            .or_insert_with(|| {
+                let (sender, mut receiver) = mpsc::channel(100);
+                let main_sender = main_sender.clone();
+                let source = rustler_name.to_owned();
+
+                tokio::spawn(async move {
+                    while let Some(msg) = receiver.recv().await {
+                        if main_sender.send((source.clone(), msg)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                sender
+            })
+            .clone()
+    }
+
+    /// removes the orphaned quote hashes in the background (see [`Self::set_orphan_cleanup`]), so
+    /// scanning the keys doesn't delay the rustlers
+    fn remove_orphaned_quotes(&self) {
+        let Some((client, prefixes)) = self.orphan_cleanup.clone() else {
+            return;
+        };
+
+        let conn = self.conn.clone();
+        tokio::spawn(async move {
+            for prefix in prefixes {
+                if let Err(e) =
+                    cleanup::remove_orphaned_quotes(&client, conn.clone(), &prefix).await
+                {
+                    warn!(
+                        "Failed to remove the orphaned quotes under '{}': {}",
+                        prefix, e
+                    );
+                }
             }
+        });
+    }
+
+    /// routes a quote, attaching its change and session stats before publishing it
+    ///
+    /// a quote that can't be published is counted and dropped, so a bus outage doesn't stop the
+    /// service
+    async fn handle_quote(&self, publisher: &mut Publisher, source: &str, mut quote: Quote) {
+        if !Self::route_quote(&self.routes, source, &mut quote).await {
+            return;
+        }
+
+        self.references.lock().await.apply(&mut quote);
+        self.stats.lock().await.apply(&mut quote);
+
+        let key = format!("{}:{}", quote.market, quote.id);
+        if let Err(e) = publisher.publish(quote).await {
+            let errors = self.publish_errors.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!(
+                "Failed to publish the quote of '{}' ({} errors): {}",
+                key, errors, e
+            );
+        }
+    }
 
-            let start_name = format!("start-rustler-{}", market.short_name);
-            let end_name = format!("end-rustler-{}", market.short_name);
-
-            if let Some((start, stop)) = &rules {
-                // TODO: we will need to store the job handlers in the `RustlersSvc` struct
-                //       so that we can stop them when we need to restart the rustlers
-
-                let start_job = self
-                    .sched
-                    .schedule_fut(
-                        start_name.to_owned(),
-                        Self::start_rustler_for(rustler.clone(), tickers.clone()),
-                        start.clone(),
-                    )
-                    .await;
-
-                let end_job = self
-                    .sched
-                    .schedule_fut(
-                        end_name.to_owned(),
-                        Self::stop_rustler_for(rustler.clone(), tickers.clone()),
-                        stop.clone(),
-                    )
-                    .await;
-
-                info!(
-                    "Scheduled next execution for start job {start_name} for market '{}' at {:?}",
-                    market.short_name,
-                    start_job.get_next_run()
-                );
-                info!(
-                    "Scheduled next execution for stop job {end_name} for market '{}' at {:?}",
-                    market.short_name,
-                    end_job.get_next_run()
-                );
-            } else {
-                info!("No schedule rules found for market '{}'", market.short_name);
+    /// routes a quote (see [`route_quote`])
+    ///
+    /// it's called for every quote, so it only takes a read lock on the routes
+    async fn route_quote(routes: &Routes, source: &str, quote: &mut Quote) -> bool {
+        route_quote(&*routes.read().await, source, quote)
+    }
+
+    /// starts the primary rustler of the given route, resetting the session stats of its tickers
+    async fn start_route(routes: Routes, stats: Arc<Mutex<SessionStatsStore>>, route_key: String) {
+        let (rustler, tickers) = {
+            let mut routes = routes.write().await;
+            let Some(route) = routes.get_mut(&route_key) else {
+                return;
+            };
+
+            route.running = true;
+            route.set_active(0);
+            route.rustlers.iter().for_each(|r| r.last_quote.set(None));
+
+            let primary = &route.rustlers[0];
+            (primary.rustler.clone(), primary.tickers.clone())
+        };
+
+        stats.lock().await.reset(&route_key);
+        Self::start_rustler_for(rustler, tickers).await;
+    }
+
+    /// stops every rustler of the given route and rolls over the reference prices of its tickers
+    async fn stop_route(
+        routes: Routes,
+        references: Arc<Mutex<ReferencePriceStore>>,
+        route_key: String,
+    ) {
+        let rustlers = {
+            let mut routes = routes.write().await;
+            let Some(route) = routes.get_mut(&route_key) else {
+                return;
+            };
+
+            route.running = false;
+            route.set_active(0);
+            route.rustlers.clone()
+        };
+
+        for r in rustlers {
+            Self::stop_rustler_for(r.rustler, r.tickers).await;
+        }
+
+        Self::roll_over_route(routes, references, route_key).await;
+    }
+
+    /// rolls over the reference prices of the tickers of the given route, at market close or, for
+    /// markets without trading hours, at midnight
+    async fn roll_over_route(
+        routes: Routes,
+        references: Arc<Mutex<ReferencePriceStore>>,
+        route_key: String,
+    ) {
+        let Some(tickers) = routes.read().await.get(&route_key).map(|r| r.tickers.clone()) else {
+            return;
+        };
+
+        if let Err(e) = references.lock().await.roll_over(&tickers) {
+            warn!(
+                "Failed to roll over reference prices for '{}': {}",
+                route_key, e
+            );
+        }
+    }
+
+    /// starts a new session of a route whose market has no trading hours: rolls over the
+    /// reference prices of its tickers and resets their session stats
+    async fn roll_over_day(
+        routes: Routes,
+        references: Arc<Mutex<ReferencePriceStore>>,
+        stats: Arc<Mutex<SessionStatsStore>>,
+        route_key: String,
+    ) {
+        Self::roll_over_route(routes, references, route_key.clone()).await;
+        stats.lock().await.reset(&route_key);
+    }
+
+    /// periodically checks the health of the rustlers of every running route that has fallback
+    /// rustlers, switching to another rustler when needed
+    async fn watch_routes(routes: Routes, opts: FailoverOpts) {
+        let mut interval = tokio::time::interval(opts.check_interval);
+
+        loop {
+            interval.tick().await;
+
+            let keys: Vec<String> = routes
+                .read()
+                .await
+                .iter()
+                .filter(|(_, route)| route.running && route.rustlers.len() > 1)
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in keys {
+                Self::check_route(&routes, &key, &opts).await;
+            }
+        }
+    }
+
+    /// checks the rustlers of a route and fails over to the next rustler if the active one is
+    /// unhealthy, or switches back to a higher priority rustler once it has recovered
+    async fn check_route(routes: &Routes, route_key: &str, opts: &FailoverOpts) {
+        let (market, rustlers, active) = {
+            let routes = routes.read().await;
+            let Some(route) = routes.get(route_key) else {
+                return;
+            };
+
+            (route.market.clone(), route.rustlers.clone(), route.active())
+        };
+
+        // a higher priority rustler is only considered recovered once it produces quotes again,
+        // while the active one gets some time to produce its first quote
+        let mut healthy = Vec::with_capacity(active + 1);
+        for (idx, r) in rustlers.iter().enumerate().take(active + 1) {
+            let grace = idx == active;
+            healthy.push(is_healthy(&r.rustler, r.last_quote.get(), opts.stale_after, grace).await);
+        }
+
+        let failover = failover(&healthy, active, rustlers.len());
+
+        // keep trying to bring back the failed rustlers the route could still switch to
+        let checked = match failover {
+            Failover::SwitchTo(target) if target < active => target,
+            _ => active + 1,
+        };
+        for (r, healthy) in rustlers.iter().zip(&healthy).take(checked) {
+            if !healthy && r.rustler.lock().await.is_disconnected() {
+                Self::start_rustler_for(r.rustler.clone(), r.tickers.clone()).await;
             }
+        }
 
-            if should_be_running_now(rules) {
-                info!("Starting '{start_name}' right away");
-                Self::start_rustler_for(rustler.clone(), tickers).await;
+        let target = match failover {
+            Failover::Stay => return,
+            Failover::Exhausted => {
+                warn!("No more fallback rustlers available for market '{market}'");
+                return;
             }
+            Failover::SwitchTo(target) => target,
+        };
 
-            Ok(())
+        info!(
+            "Switching market '{market}' from rustler '{}' to rustler '{}'",
+            rustlers[active].name, rustlers[target].name
+        );
+
+        if let Some(route) = routes.read().await.get(route_key) {
+            route.set_active(target);
+        }
+
+        if target > active {
+            let r = &rustlers[target];
+            Self::start_rustler_for(r.rustler.clone(), r.tickers.clone()).await;
         } else {
-            warn!("No rustler found for market '{}'", market.short_name);
-            Ok(())
+            for r in rustlers.iter().skip(target + 1) {
+                Self::stop_rustler_for(r.rustler.clone(), r.tickers.clone()).await;
+            }
         }
     }
 
@@ -239,7 +801,7 @@ where
     }
 
     /// starts a rustler by adding the tickers to it
-    async fn start_rustler_for(rustler: Arc<Mutex<Box<dyn Rustler>>>, tickers: Vec<Ticker>) {
+    async fn start_rustler_for(rustler: SharedRustler, tickers: Vec<Ticker>) {
         let mut rustler = rustler.lock().await;
         match rustler.start().await {
             Ok(()) => {
@@ -269,7 +831,7 @@ where
     /// if the rustler is being used by other markets, or the ticker list does not contain
     /// all the tickers that the rustler is using for the given market, the rustler will not
     /// be stopped, but will stop gathering data for the given tickers.
-    async fn stop_rustler_for(rustler: Arc<Mutex<Box<dyn Rustler>>>, tickers: Vec<Ticker>) {
+    async fn stop_rustler_for(rustler: SharedRustler, tickers: Vec<Ticker>) {
         let mut rustler = rustler.lock().await;
 
         if !tickers.is_empty() {
@@ -291,6 +853,91 @@ where
     }
 }
 
+/// resolves the tickers of a market for a rustler, using its aliases (indexed by
+/// `(ticker_id, rustler)`) as provider symbols, and maps them back to the canonical symbols
+fn rustler_tickers(
+    rustler: &str,
+    models: &[ticker::Model],
+    market: &market::Model,
+    aliases: &HashMap<(String, String), String>,
+) -> (Vec<Ticker>, HashMap<String, String>) {
+    let tickers: Vec<Ticker> = models
+        .iter()
+        .map(|t| {
+            let alias = aliases.get(&(t.id.clone(), rustler.to_owned())).cloned();
+            Ticker::from(t, market).with_provider_symbol(alias)
+        })
+        .collect();
+
+    let canonical =
+        tickers.iter().map(|t| (t.provider_symbol().to_owned(), t.symbol.clone())).collect();
+
+    (tickers, canonical)
+}
+
+/// records the quote for health checks, translates its provider symbol to the canonical one and
+/// tags it with the rustler that produced it (`source`)
+///
+/// returns `true` if it was produced by the active rustler of its market, quotes from inactive
+/// rustlers must not be published
+fn route_quote(routes: &HashMap<String, Route>, source: &str, quote: &mut Quote) -> bool {
+    quote.source = Some(source.to_owned());
+
+    let Some(route) = routes.get(&quote.market) else {
+        return true;
+    };
+
+    let Some(idx) = route.position(source) else {
+        return true;
+    };
+
+    let rustler = &route.rustlers[idx];
+    rustler.last_quote.set(Some(Instant::now()));
+
+    if let Some(symbol) = rustler.canonical.get(&quote.id) {
+        quote.id = symbol.clone();
+    }
+
+    idx == route.active()
+}
+
+/// what the health check of a route decided
+#[derive(Debug, PartialEq)]
+enum Failover {
+    /// the active rustler is healthy and no higher priority rustler has recovered
+    Stay,
+    /// publish the quotes of the rustler at the given index
+    SwitchTo(usize),
+    /// the active rustler is unhealthy and there are no more fallback rustlers
+    Exhausted,
+}
+
+/// decides the rustler of a route of `rustlers` rustlers to publish the quotes of, given the
+/// health of the rustlers up to the active one (`healthy[..=active]`)
+///
+/// the first healthy rustler with a higher priority than the active one wins, otherwise the route
+/// moves to the next rustler when the active one is unhealthy
+fn failover(healthy: &[bool], active: usize, rustlers: usize) -> Failover {
+    if let Some(idx) = healthy.iter().take(active).position(|healthy| *healthy) {
+        return Failover::SwitchTo(idx);
+    }
+
+    if healthy.get(active).copied().unwrap_or_default() {
+        return Failover::Stay;
+    }
+
+    if active + 1 >= rustlers {
+        return Failover::Exhausted;
+    }
+
+    Failover::SwitchTo(active + 1)
+}
+
+/// the market name used in the quotes of the given market (its public name, if it has one)
+fn route_key(market: &market::Model) -> String {
+    market.pub_name.clone().unwrap_or_else(|| market.short_name.clone())
+}
+
 /// creates a rule for the given time and offset
 ///
 /// TODO: handle timezones
@@ -317,6 +964,33 @@ enum Op {
     Sub,
 }
 
+/// checks if a rustler is connected and producing quotes
+///
+/// when `grace` is `true`, a rustler that connected less than `stale_after` ago is considered
+/// healthy even if it hasn't produced any quote yet
+async fn is_healthy(
+    rustler: &SharedRustler,
+    last_quote: Option<Instant>,
+    stale_after: Duration,
+    grace: bool,
+) -> bool {
+    let rustler = rustler.lock().await;
+    if !rustler.is_connected() {
+        return false;
+    }
+
+    if last_quote.is_some_and(|at| at.elapsed() < stale_after) {
+        return true;
+    }
+
+    grace
+        && rustler
+            .last_run()
+            .as_ref()
+            .and_then(|run| (Local::now() - *run).to_std().ok())
+            .is_some_and(|connected_for| connected_for < stale_after)
+}
+
 /// checks if the rustler should be running now
 fn should_be_running_now(rules: Option<(SchedulingRule, SchedulingRule)>) -> bool {
     if let Some((start, stop)) = rules {
@@ -340,3 +1014,428 @@ fn should_be_running_now(rules: Option<(SchedulingRule, SchedulingRule)>) -> boo
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            bus::memory::{publish::MemoryPublisher, MemoryBus},
+            entities::{db, rustler_config},
+            rustlers::{config::RustlerConfig, rustler, RustlerAccessor, RustlerOpts},
+        },
+        async_trait::async_trait,
+        std::sync::atomic::AtomicBool,
+    };
+
+    type Svc = RustlersSvc<MemoryPublisher<Quote>>;
+
+    #[rustler(name = self.name.clone())]
+    struct FakeRustler {
+        name: String,
+        /// makes `connect` fail while it's set
+        refuse: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Rustler for FakeRustler {
+        async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn connect(&mut self) -> Result<()> {
+            if self.refuse.load(Ordering::Relaxed) {
+                fail!("Connection refused");
+            }
+
+            self.set_status(RustlerStatus::Connected)
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            self.set_status(RustlerStatus::Disconnected)
+        }
+    }
+
+    /// a fake rustler of a route and the switch that makes its connections fail
+    struct Fake {
+        rustler: SharedRustler,
+        refuse: Arc<AtomicBool>,
+    }
+
+    impl Fake {
+        async fn is_connected(&self) -> bool {
+            self.rustler.lock().await.is_connected()
+        }
+    }
+
+    /// a route of the NYSE market served by rustlers with the given names, which use the given
+    /// provider symbols for `BRK.B`
+    fn route(rustlers: &[(&str, &str)]) -> (Routes, Vec<Fake>) {
+        let ticker = Ticker {
+            symbol: "BRK.B".into(),
+            market: "NYSE".into(),
+            quote_asset: None,
+            provider_symbol: None,
+        };
+
+        let mut fakes = vec![];
+        let mut route_rustlers = vec![];
+        for (name, provider_symbol) in rustlers {
+            let refuse = Arc::new(AtomicBool::new(false));
+            let rustler: Box<dyn Rustler> =
+                Box::new(FakeRustler::new(name.to_string(), refuse.clone()));
+            let rustler = Arc::new(Mutex::new(rustler));
+
+            let tickers =
+                vec![ticker.clone().with_provider_symbol(Some(provider_symbol.to_string()))];
+            let canonical = HashMap::from([(provider_symbol.to_string(), ticker.symbol.clone())]);
+
+            route_rustlers.push(RouteRustler {
+                name: name.to_string(),
+                rustler: rustler.clone(),
+                tickers,
+                canonical,
+                last_quote: LastQuote::default(),
+            });
+            fakes.push(Fake { rustler, refuse });
+        }
+
+        let route = Route {
+            market: "NYSE".into(),
+            tickers: vec![ticker],
+            rustlers: route_rustlers,
+            active: AtomicUsize::new(0),
+            running: true,
+        };
+
+        let routes = Arc::new(RwLock::new(HashMap::from([("NYSE".to_string(), route)])));
+        (routes, fakes)
+    }
+
+    fn quote(market: &str, id: &str) -> Quote {
+        Quote {
+            id: id.into(),
+            market: market.into(),
+            price: 500.0,
+            change_percent: 0.0,
+            time: 0,
+            market_hours: MarketHourType::Regular,
+            source: None,
+            volume: None,
+            stats: None,
+        }
+    }
+
+    fn stats() -> Arc<Mutex<SessionStatsStore>> {
+        Arc::new(Mutex::new(SessionStatsStore::new()))
+    }
+
+    async fn active(routes: &Routes) -> usize {
+        routes.read().await["NYSE"].active()
+    }
+
+    fn opts(stale_after: Duration) -> FailoverOpts {
+        FailoverOpts {
+            check_interval: Duration::from_secs(1),
+            stale_after,
+        }
+    }
+
+    #[test]
+    fn failover_decisions() {
+        // the active rustler is healthy
+        assert_eq!(failover(&[true], 0, 2), Failover::Stay);
+        assert_eq!(failover(&[false, true], 1, 3), Failover::Stay);
+
+        // the active rustler is unhealthy
+        assert_eq!(failover(&[false], 0, 2), Failover::SwitchTo(1));
+        assert_eq!(failover(&[false, false], 1, 3), Failover::SwitchTo(2));
+        assert_eq!(failover(&[false], 0, 1), Failover::Exhausted);
+        assert_eq!(failover(&[false, false], 1, 2), Failover::Exhausted);
+
+        // a higher priority rustler recovered, whatever the health of the active one
+        assert_eq!(failover(&[true, false], 1, 2), Failover::SwitchTo(0));
+        assert_eq!(failover(&[true, true], 1, 2), Failover::SwitchTo(0));
+        assert_eq!(failover(&[false, true, false], 2, 3), Failover::SwitchTo(1));
+    }
+
+    #[tokio::test]
+    async fn only_the_quotes_of_the_active_rustler_are_routed() {
+        let (routes, _) = route(&[("primary", "BRK-B"), ("secondary", "BRK/B")]);
+        let routes = routes.read().await;
+
+        let mut primary = quote("NYSE", "BRK-B");
+        assert!(route_quote(&routes, "primary", &mut primary));
+        assert_eq!(primary.id, "BRK.B");
+        assert_eq!(primary.source.as_deref(), Some("primary"));
+
+        let mut secondary = quote("NYSE", "BRK/B");
+        assert!(!route_quote(&routes, "secondary", &mut secondary));
+        assert_eq!(secondary.id, "BRK.B");
+        assert_eq!(secondary.source.as_deref(), Some("secondary"));
+
+        // both are recorded for the health checks
+        let route = &routes["NYSE"];
+        assert!(route.rustlers.iter().all(|r| r.last_quote.get().is_some()));
+
+        route.set_active(1);
+        assert!(!route_quote(
+            &routes,
+            "primary",
+            &mut quote("NYSE", "BRK-B")
+        ));
+        assert!(route_quote(
+            &routes,
+            "secondary",
+            &mut quote("NYSE", "BRK/B")
+        ));
+
+        // quotes of markets without a route are published as they are
+        let mut other = quote("NASDAQ", "AAPL");
+        assert!(route_quote(&routes, "other", &mut other));
+        assert_eq!(other.id, "AAPL");
+        assert_eq!(other.source.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn rustlers_get_their_own_aliases_as_provider_symbols() {
+        let market = market::Model {
+            id: "nyse".into(),
+            short_name: "nyse".into(),
+            full_name: "New York Stock Exchange".into(),
+            pub_name: Some("NYSE".into()),
+            opens_from: None,
+            opens_till: None,
+            open_time: None,
+            close_time: None,
+            pre_market_offset: None,
+            post_market_offset: None,
+            time_zone_offset: None,
+            change_reference: None,
+        };
+        let ticker = |id: &str, symbol: &str| ticker::Model {
+            id: id.into(),
+            symbol: symbol.into(),
+            quote_symbol: None,
+            market_id: "nyse".into(),
+            active: true,
+        };
+        let models = [ticker("brk", "BRK.B"), ticker("ibm", "IBM")];
+        let aliases = HashMap::from([
+            (
+                ("brk".to_string(), "primary".to_string()),
+                "BRK-B".to_string(),
+            ),
+            (
+                ("brk".to_string(), "secondary".to_string()),
+                "BRK/B".to_string(),
+            ),
+        ]);
+
+        let (tickers, canonical) = rustler_tickers("primary", &models, &market, &aliases);
+        assert_eq!(tickers[0].symbol, "BRK.B");
+        assert_eq!(tickers[0].market, "NYSE");
+        assert_eq!(tickers[0].provider_symbol(), "BRK-B");
+        assert_eq!(tickers[1].provider_symbol(), "IBM");
+        assert_eq!(
+            canonical,
+            HashMap::from([
+                ("BRK-B".to_string(), "BRK.B".to_string()),
+                ("IBM".to_string(), "IBM".to_string()),
+            ])
+        );
+
+        // rustlers without aliases use the canonical symbols
+        let (tickers, canonical) = rustler_tickers("other", &models, &market, &aliases);
+        assert!(tickers.iter().all(|t| t.provider_symbol() == t.symbol));
+        assert_eq!(canonical["BRK.B"], "BRK.B");
+
+        // quotes belong to their ticker by either symbol, but not to the other tickers
+        let (tickers, _) = rustler_tickers("secondary", &models, &market, &aliases);
+        assert!(quote("NYSE", "BRK/B").belongs_to(&tickers[0]));
+        assert!(quote("NYSE", "BRK.B").belongs_to(&tickers[0]));
+        assert!(quote("NYSE", "BRK.B") == tickers[0]);
+        assert!(!quote("NYSE", "BRK-B").belongs_to(&tickers[0]));
+        assert!(!quote("NASDAQ", "BRK/B").belongs_to(&tickers[0]));
+        assert!(tickers[1] != quote("NYSE", "BRK/B"));
+    }
+
+    #[tokio::test]
+    async fn fails_over_when_the_primary_cant_connect_and_switches_back() {
+        let (routes, fakes) = route(&[("primary", "BRK-B"), ("secondary", "BRK/B")]);
+        let opts = opts(Duration::from_secs(60));
+
+        fakes[0].refuse.store(true, Ordering::Relaxed);
+        Svc::start_route(routes.clone(), stats(), "NYSE".into()).await;
+        assert!(!fakes[0].is_connected().await);
+
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert_eq!(active(&routes).await, 1);
+        assert!(fakes[1].is_connected().await);
+
+        // the primary connects again, but it isn't used until it produces quotes
+        fakes[0].refuse.store(false, Ordering::Relaxed);
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert!(fakes[0].is_connected().await);
+        assert_eq!(active(&routes).await, 1);
+
+        Svc::route_quote(&routes, "primary", &mut quote("NYSE", "BRK-B")).await;
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert_eq!(active(&routes).await, 0);
+
+        // the fallback rustler is stopped
+        assert!(!fakes[1].is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn fails_over_when_the_active_rustler_goes_stale() {
+        let (routes, fakes) = route(&[("primary", "BRK-B"), ("secondary", "BRK/B")]);
+        let opts = opts(Duration::from_millis(100));
+
+        Svc::start_route(routes.clone(), stats(), "NYSE".into()).await;
+        Svc::route_quote(&routes, "primary", &mut quote("NYSE", "BRK-B")).await;
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert_eq!(active(&routes).await, 0);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert_eq!(active(&routes).await, 1);
+        assert!(fakes[1].is_connected().await);
+
+        // there's nothing to fall back to once the last rustler goes stale too
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        Svc::check_route(&routes, "NYSE", &opts).await;
+        assert_eq!(active(&routes).await, 1);
+    }
+
+    #[rustler]
+    struct ConfiguredRustler {
+        required: bool,
+    }
+
+    #[async_trait]
+    impl Rustler for ConfiguredRustler {
+        async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn connect(&mut self) -> Result<()> {
+            self.set_status(RustlerStatus::Connected)
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            self.set_status(RustlerStatus::Disconnected)
+        }
+
+        async fn on_configure(&mut self, config: &RustlerConfig) -> Result<()> {
+            config.endpoint("ws")?;
+            Ok(())
+        }
+
+        fn requires_config(&self) -> bool {
+            self.required
+        }
+    }
+
+    /// configures the rustler with the given configuration (if any) stored in the database, as the
+    /// service does at startup, returning the options it ends up with
+    async fn configure(rustler: ConfiguredRustler, config: Option<&str>) -> Result<RustlerOpts> {
+        let conn = db::in_memory().await;
+        if let Some(config) = config {
+            let model = rustler_config::Model {
+                rustler: rustler.name(),
+                config: config.to_string(),
+            };
+            rustler_config::Service::new(conn.clone()).await.upsert(model).await?;
+        }
+
+        let jar = RustlerJar::new(vec![Box::new(rustler)], HashMap::new());
+        let publisher = MemoryPublisher::new(&MemoryBus::new());
+        let mut svc = Svc::new(conn, jar, publisher).await;
+        svc.configure_rustlers().await?;
+
+        let rustler = svc.rustlers.all().next().unwrap().lock().await;
+        Ok(rustler.opts().clone())
+    }
+
+    #[tokio::test]
+    async fn configs_keep_the_opts_set_in_code_unless_they_have_some() {
+        let in_code = RustlerOpts {
+            connect_on_start: false,
+            connect_on_add: true,
+        };
+        let mut rustler = ConfiguredRustler::new(false);
+        rustler.set_opts(in_code.clone());
+
+        let config = r#"{ "endpoints": { "ws": "wss://example.com" } }"#;
+        assert_eq!(configure(rustler, Some(config)).await.unwrap(), in_code);
+
+        let mut rustler = ConfiguredRustler::new(false);
+        rustler.set_opts(in_code);
+
+        let config = r#"{
+            "endpoints": { "ws": "wss://example.com" },
+            "opts": { "connect_on_start": true, "connect_on_add": false }
+        }"#;
+        let opts = configure(rustler, Some(config)).await.unwrap();
+        assert!(opts.connect_on_start);
+        assert!(!opts.connect_on_add);
+    }
+
+    #[tokio::test]
+    async fn invalid_configs_fail_the_start() {
+        let config = r#"{ "endpoints": { "rest": "https://example.com" } }"#;
+        assert!(configure(ConfiguredRustler::new(false), Some(config)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rustlers_that_require_a_config_fail_the_start_without_one() {
+        assert!(configure(ConfiguredRustler::new(true), None).await.is_err());
+        assert!(configure(ConfiguredRustler::new(false), None).await.is_ok());
+    }
+
+    /// a publisher that fails to publish the quotes of the `DOWN` market
+    #[derive(Clone, Default)]
+    struct FlakyPublisher {
+        published: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl PublisherTrait<Quote> for FlakyPublisher {
+        async fn publish(&mut self, value: Quote) -> Result<()> {
+            if value.market == "DOWN" {
+                fail!("Bus unreachable");
+            }
+
+            self.published.lock().unwrap().push(value.id);
+            Ok(())
+        }
+
+        async fn publish_traced(&mut self, value: Quote, _trace_id: &str) -> Result<()> {
+            self.publish(value).await
+        }
+    }
+
+    #[tokio::test]
+    async fn quotes_that_cant_be_published_are_counted_and_dropped() {
+        let jar = RustlerJar::new(vec![], HashMap::new());
+        let mut publisher = FlakyPublisher::default();
+        let svc = RustlersSvc::new(db::in_memory().await, jar, publisher.clone()).await;
+
+        svc.handle_quote(&mut publisher, "rustler", quote("NYSE", "IBM")).await;
+        svc.handle_quote(&mut publisher, "rustler", quote("DOWN", "IBM")).await;
+        svc.handle_quote(&mut publisher, "rustler", quote("DOWN", "AAPL")).await;
+        svc.handle_quote(&mut publisher, "rustler", quote("NYSE", "BRK.B")).await;
+
+        assert_eq!(*publisher.published.lock().unwrap(), ["IBM", "BRK.B"]);
+        assert_eq!(svc.publish_errors().load(Ordering::Relaxed), 2);
+    }
+}
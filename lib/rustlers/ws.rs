@@ -0,0 +1,570 @@
+use {
+    super::{
+        rustler,
+        svc::{to_msg, MsgSender, RustlerMsg},
+        Quote, Rustler, RustlerAccessor, RustlerStatus, Ticker,
+    },
+    async_trait::async_trait,
+    eyre::{OptionExt, Result},
+    futures::{SinkExt, StreamExt},
+    lool::logger::{debug, info, warn},
+    std::{collections::HashMap, sync::Arc, time::Duration},
+    tokio::{
+        net::TcpStream,
+        select,
+        sync::{
+            mpsc::{self, UnboundedReceiver, UnboundedSender},
+            RwLock,
+        },
+        time::{sleep, Instant},
+    },
+    tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream},
+    tokio_util::sync::CancellationToken,
+};
+
+pub use tokio_tungstenite::tungstenite::Message;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// #### 🐎 » WebSocket handler
+///
+/// The provider specific part of a [`WsRustler`]: where to connect, how to subscribe and
+/// unsubscribe tickers, and how to turn incoming frames into [`Quote`]s.
+pub trait WsHandler: Send + Sync + 'static {
+    /// 🐎 » name of the rustler (must be unique among the rustlers of a `RustlerJar`)
+    fn name(&self) -> String;
+
+    /// 🐎 » url of the websocket to connect to
+    fn url(&self) -> String;
+
+    /// 🐎 » builds the frames to send to start receiving quotes for the given tickers
+    ///
+    /// use [`Ticker::provider_symbol`] to get the symbols known by the provider
+    fn subscribe_msg(&self, tickers: &[Ticker]) -> Vec<Message>;
+
+    /// 🐎 » builds the frames to send to stop receiving quotes for the given tickers
+    fn unsubscribe_msg(&self, tickers: &[Ticker]) -> Vec<Message>;
+
+    /// 🐎 » parses an incoming frame into quotes
+    ///
+    /// frames that don't carry quotes (acks, heartbeats, etc.) should return an empty vec
+    fn parse(&self, msg: &Message) -> Result<Vec<Quote>>;
+
+    /// 🐎 » heartbeat frame and the interval to send it at (a ping every 30 seconds by default)
+    ///
+    /// return `None` if the provider doesn't need heartbeats
+    fn heartbeat(&self) -> Option<(Duration, Message)> {
+        Some((Duration::from_secs(30), Message::Ping(Default::default())))
+    }
+}
+
+/// #### 🐎 » WebSocket rustler options
+#[derive(Debug, Clone)]
+pub struct WsOpts {
+    /// delay before the first reconnection attempt (doubled after each failed attempt)
+    pub reconnect_min_delay: Duration,
+    /// max delay between reconnection attempts
+    pub reconnect_max_delay: Duration,
+    /// time without receiving any frame after which the connection is considered dropped
+    pub stale_after: Option<Duration>,
+}
+
+impl Default for WsOpts {
+    fn default() -> Self {
+        Self {
+            reconnect_min_delay: Duration::from_secs(1),
+            reconnect_max_delay: Duration::from_secs(60),
+            stale_after: Some(Duration::from_secs(90)),
+        }
+    }
+}
+
+/// commands sent from the rustler to its connection task
+enum WsCommand {
+    Subscribe(Vec<Ticker>),
+    Unsubscribe(Vec<Ticker>),
+}
+
+/// the reason a websocket session ended
+enum SessionEnd {
+    Cancelled,
+    Dropped(String),
+}
+
+/// #### 🐎 » WebSocket Rustler
+///
+/// A generic [`Rustler`] for providers that stream quotes through a websocket. It takes care of
+/// the connection, heartbeats, reconnections (with exponential backoff) and resubscribes every
+/// ticker after reconnecting, only asking a [`WsHandler`] for the provider specific parts.
+///
+/// While reconnecting, the rustler reports itself as [`RustlerStatus::Connecting`] (through a
+/// [`RustlerMsg::StatusMsg`] applied by the rustlers service), so it isn't considered healthy.
+///
+/// **Usage**
+///
+/// ```rust
+/// let rustler_jar = rustlerjar! {
+///     "BINANCE" => || WsRustler::new(BinanceHandler::default()),
+/// };
+/// ```
+#[rustler(name = self.handler.name(), no_constructor)]
+pub struct WsRustler<H: WsHandler> {
+    handler: Arc<H>,
+    ws_opts: WsOpts,
+    subscribed: Arc<RwLock<HashMap<String, Ticker>>>,
+    commands: Option<UnboundedSender<WsCommand>>,
+    cancel: Option<CancellationToken>,
+}
+
+impl<H: WsHandler> WsRustler<H> {
+    /// 🐎 » creates a new websocket rustler using the default [`WsOpts`]
+    pub fn new(handler: H) -> Self {
+        Self::with_opts(handler, WsOpts::default())
+    }
+
+    /// 🐎 » creates a new websocket rustler
+    pub fn with_opts(handler: H, ws_opts: WsOpts) -> Self {
+        Self {
+            status: RustlerStatus::default(),
+            next_run: Default::default(),
+            next_stop: None,
+            last_run: None,
+            last_stop: None,
+            last_update: None,
+            opts: Default::default(),
+            tickers: HashMap::new(),
+            msg_sender: None,
+            handler: Arc::new(handler),
+            ws_opts,
+            subscribed: Arc::new(RwLock::new(HashMap::new())),
+            commands: None,
+            cancel: None,
+        }
+    }
+
+    /// 🐎 » returns the handler of the rustler
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    fn send_command(&self, command: WsCommand) {
+        if let Some(commands) = &self.commands {
+            // if the task is gone there's nothing to (un)subscribe, it will resubscribe
+            // everything from `subscribed` when it connects again
+            let _ = commands.send(command);
+        }
+    }
+}
+
+#[async_trait]
+impl<H: WsHandler> Rustler for WsRustler<H> {
+    async fn connect(&mut self) -> Result<()> {
+        if self.is_connected_or_connecting() {
+            return Ok(());
+        }
+
+        self.set_status(RustlerStatus::Connecting)?;
+
+        let sender = self.msg_sender().clone().ok_or_eyre("Sender not found")?;
+        let url = self.handler.url();
+
+        let ws = match connect_async(url.as_str()).await {
+            Ok((ws, _)) => ws,
+            Err(e) => {
+                self.set_status(RustlerStatus::Disconnected)?;
+                return Err(e.into());
+            }
+        };
+
+        info!("Rustler '{}' connected to {}", self.name(), url);
+
+        {
+            let mut subscribed = self.subscribed.write().await;
+            *subscribed = self.tickers().clone();
+        }
+
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let cancel = CancellationToken::new();
+
+        let task = WsTask {
+            handler: self.handler.clone(),
+            opts: self.ws_opts.clone(),
+            subscribed: self.subscribed.clone(),
+            sender,
+            cancel: cancel.clone(),
+        };
+
+        tokio::spawn(task.run(ws, receiver));
+
+        self.commands = Some(commands);
+        self.cancel = Some(cancel);
+        self.set_status(RustlerStatus::Connected)?;
+
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if self.is_disconnected_or_disconnecting() {
+            return Ok(());
+        }
+
+        self.set_status(RustlerStatus::Disconnecting)?;
+
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel();
+        }
+        self.commands = None;
+        self.subscribed.write().await.clear();
+
+        self.set_status(RustlerStatus::Disconnected)?;
+        Ok(())
+    }
+
+    async fn on_add(&mut self, tickers: &[Ticker]) -> Result<()> {
+        let mut subscribed = self.subscribed.write().await;
+        let new: Vec<Ticker> =
+            tickers.iter().filter(|t| !subscribed.contains_key(&t.key())).cloned().collect();
+
+        if new.is_empty() {
+            return Ok(());
+        }
+
+        for ticker in &new {
+            subscribed.insert(ticker.key(), ticker.clone());
+        }
+        drop(subscribed);
+
+        self.send_command(WsCommand::Subscribe(new));
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, tickers: &[Ticker]) -> Result<()> {
+        let mut subscribed = self.subscribed.write().await;
+        let removed: Vec<Ticker> =
+            tickers.iter().filter_map(|t| subscribed.remove(&t.key())).collect();
+        drop(subscribed);
+
+        if !removed.is_empty() {
+            self.send_command(WsCommand::Unsubscribe(removed));
+        }
+
+        Ok(())
+    }
+}
+
+/// the task that owns the websocket connection of a [`WsRustler`]
+struct WsTask<H: WsHandler> {
+    handler: Arc<H>,
+    opts: WsOpts,
+    subscribed: Arc<RwLock<HashMap<String, Ticker>>>,
+    sender: MsgSender,
+    cancel: CancellationToken,
+}
+
+impl<H: WsHandler> WsTask<H> {
+    /// runs sessions until cancelled, reconnecting and resubscribing when the connection drops
+    async fn run(self, mut ws: WsStream, mut commands: UnboundedReceiver<WsCommand>) {
+        let name = self.handler.name();
+
+        loop {
+            if let Err(e) = self.subscribe_all(&mut ws).await {
+                warn!("Rustler '{}' failed to subscribe: {}", name, e);
+            }
+
+            match self.session(&mut ws, &mut commands).await {
+                SessionEnd::Cancelled => {
+                    let _ = ws.close(None).await;
+                    debug!("Rustler '{}' connection closed", name);
+                    return;
+                }
+                SessionEnd::Dropped(reason) => {
+                    warn!("Rustler '{}' connection dropped: {}", name, reason);
+                    self.report(RustlerStatus::Connecting).await;
+                }
+            }
+
+            match self.reconnect().await {
+                Some(new_ws) => {
+                    ws = new_ws;
+                    self.report(RustlerStatus::Connected).await;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// reports a status change of the connection to the rustlers service
+    async fn report(&self, status: RustlerStatus) {
+        if self.sender.send(RustlerMsg::StatusMsg(status)).await.is_err() {
+            warn!("Rustler '{}' message receiver is gone", self.handler.name());
+        }
+    }
+
+    /// sends the subscription frames for every subscribed ticker
+    async fn subscribe_all(&self, ws: &mut WsStream) -> Result<()> {
+        let tickers: Vec<Ticker> = self.subscribed.read().await.values().cloned().collect();
+
+        if !tickers.is_empty() {
+            for msg in self.handler.subscribe_msg(&tickers) {
+                ws.send(msg).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// reads frames and handles commands and heartbeats until the connection drops or the task
+    /// is cancelled
+    async fn session(
+        &self,
+        ws: &mut WsStream,
+        commands: &mut UnboundedReceiver<WsCommand>,
+    ) -> SessionEnd {
+        let heartbeat = self.handler.heartbeat();
+        let mut heartbeat_at = heartbeat.as_ref().map(|(every, _)| Instant::now() + *every);
+        let mut last_frame = Instant::now();
+
+        loop {
+            let stale_at = self.opts.stale_after.map(|after| last_frame + after);
+
+            select! {
+                _ = self.cancel.cancelled() => return SessionEnd::Cancelled,
+                cmd = commands.recv() => {
+                    let msgs = match cmd {
+                        Some(WsCommand::Subscribe(t)) => self.handler.subscribe_msg(&t),
+                        Some(WsCommand::Unsubscribe(t)) => self.handler.unsubscribe_msg(&t),
+                        None => return SessionEnd::Cancelled,
+                    };
+
+                    for msg in msgs {
+                        if let Err(e) = ws.send(msg).await {
+                            return SessionEnd::Dropped(e.to_string());
+                        }
+                    }
+                }
+                _ = sleep_until_opt(heartbeat_at) => {
+                    if let Some((every, msg)) = &heartbeat {
+                        heartbeat_at = Some(Instant::now() + *every);
+
+                        if let Err(e) = ws.send(msg.clone()).await {
+                            return SessionEnd::Dropped(e.to_string());
+                        }
+                    }
+                }
+                _ = sleep_until_opt(stale_at) => {
+                    return SessionEnd::Dropped("no frames received, connection is stale".into());
+                }
+                frame = ws.next() => match frame {
+                    Some(Ok(msg)) => {
+                        last_frame = Instant::now();
+
+                        if msg.is_close() {
+                            return SessionEnd::Dropped("closed by the server".into());
+                        }
+
+                        self.handle_frame(&msg).await;
+                    }
+                    Some(Err(e)) => return SessionEnd::Dropped(e.to_string()),
+                    None => return SessionEnd::Dropped("stream ended".into()),
+                }
+            }
+        }
+    }
+
+    /// parses a frame and sends the resulting quotes to the rustlers service
+    async fn handle_frame(&self, msg: &Message) {
+        if msg.is_ping() || msg.is_pong() {
+            return;
+        }
+
+        match self.handler.parse(msg) {
+            Ok(quotes) => {
+                for quote in quotes {
+                    if self.sender.send(to_msg(quote)).await.is_err() {
+                        warn!("Rustler '{}' message receiver is gone", self.handler.name());
+                    }
+                }
+            }
+            Err(e) => warn!(
+                "Rustler '{}' failed to parse frame: {}",
+                self.handler.name(),
+                e
+            ),
+        }
+    }
+
+    /// reconnects using an exponential backoff; returns `None` if cancelled while reconnecting
+    async fn reconnect(&self) -> Option<WsStream> {
+        let mut delay = self.opts.reconnect_min_delay;
+
+        loop {
+            select! {
+                _ = self.cancel.cancelled() => return None,
+                _ = sleep(delay) => {}
+            }
+
+            match connect_async(self.handler.url().as_str()).await {
+                Ok((ws, _)) => {
+                    info!("Rustler '{}' reconnected", self.handler.name());
+                    return Some(ws);
+                }
+                Err(e) => {
+                    warn!(
+                        "Rustler '{}' failed to reconnect, retrying in {:?}: {}",
+                        self.handler.name(),
+                        delay,
+                        e
+                    );
+                    delay = (delay * 2).min(self.opts.reconnect_max_delay);
+                }
+            }
+        }
+    }
+}
+
+/// sleeps until the given instant, or forever if there's none
+async fn sleep_until_opt(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::rustlers::MarketHourType,
+        tokio::{
+            net::TcpListener,
+            sync::mpsc::{channel, Receiver},
+            time::timeout,
+        },
+        tokio_tungstenite::accept_async,
+    };
+
+    struct TestHandler {
+        url: String,
+    }
+
+    impl WsHandler for TestHandler {
+        fn name(&self) -> String {
+            "TEST".into()
+        }
+
+        fn url(&self) -> String {
+            self.url.clone()
+        }
+
+        fn subscribe_msg(&self, tickers: &[Ticker]) -> Vec<Message> {
+            tickers.iter().map(|t| Message::text(format!("sub:{}", t.provider_symbol()))).collect()
+        }
+
+        fn unsubscribe_msg(&self, tickers: &[Ticker]) -> Vec<Message> {
+            tickers
+                .iter()
+                .map(|t| Message::text(format!("unsub:{}", t.provider_symbol())))
+                .collect()
+        }
+
+        /// frames are `SYMBOL=price`
+        fn parse(&self, msg: &Message) -> Result<Vec<Quote>> {
+            let Some((id, price)) = msg.to_text()?.split_once('=') else {
+                return Ok(vec![]);
+            };
+
+            Ok(vec![Quote {
+                id: id.into(),
+                market: "TEST".into(),
+                price: price.parse()?,
+                change_percent: 0.0,
+                time: 0,
+                market_hours: MarketHourType::Regular,
+                source: None,
+                volume: None,
+                stats: None,
+            }])
+        }
+
+        fn heartbeat(&self) -> Option<(Duration, Message)> {
+            None
+        }
+    }
+
+    /// accepts two connections, answering the subscription of each one with a quote; the first
+    /// connection is dropped right after its quote
+    async fn serve(listener: TcpListener) {
+        for price in [1, 2] {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+
+            let sub = ws.next().await.unwrap().unwrap();
+            assert_eq!(sub.to_text().unwrap(), "sub:AAPL");
+            ws.send(Message::text(format!("AAPL={}", price))).await.unwrap();
+
+            if price == 2 {
+                while ws.next().await.is_some() {}
+            }
+        }
+    }
+
+    async fn next_msg(receiver: &mut Receiver<RustlerMsg>) -> RustlerMsg {
+        let msg = timeout(Duration::from_secs(5), receiver.recv()).await;
+        msg.expect("timed out waiting for a message").expect("sender dropped")
+    }
+
+    async fn next_quote(receiver: &mut Receiver<RustlerMsg>) -> Quote {
+        match next_msg(receiver).await {
+            RustlerMsg::QuoteMsg(quote) => quote,
+            RustlerMsg::StatusMsg(status) => panic!("expected a quote, got {:?}", status),
+        }
+    }
+
+    async fn next_status(receiver: &mut Receiver<RustlerMsg>) -> RustlerStatus {
+        match next_msg(receiver).await {
+            RustlerMsg::StatusMsg(status) => status,
+            RustlerMsg::QuoteMsg(quote) => panic!("expected a status, got {:?}", quote),
+        }
+    }
+
+    #[tokio::test]
+    async fn connects_dispatches_and_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener));
+
+        let opts = WsOpts {
+            reconnect_min_delay: Duration::from_millis(10),
+            reconnect_max_delay: Duration::from_millis(100),
+            stale_after: None,
+        };
+        let mut rustler = WsRustler::with_opts(TestHandler { url }, opts);
+        let (sender, mut receiver) = channel(10);
+        rustler.set_msg_sender(Some(sender));
+
+        rustler.connect().await.unwrap();
+        assert_eq!(rustler.status(), &RustlerStatus::Connected);
+
+        let tickers = vec![Ticker {
+            symbol: "AAPL".into(),
+            market: "TEST".into(),
+            quote_asset: None,
+            provider_symbol: None,
+        }];
+        rustler.add(&tickers).await.unwrap();
+
+        let quote = next_quote(&mut receiver).await;
+        assert_eq!((quote.id.as_str(), quote.price), ("AAPL", 1.0));
+
+        // the server drops the first connection: the rustler reports it's reconnecting, then
+        // reconnects and resubscribes its tickers
+        assert_eq!(next_status(&mut receiver).await, RustlerStatus::Connecting);
+        assert_eq!(next_status(&mut receiver).await, RustlerStatus::Connected);
+
+        let quote = next_quote(&mut receiver).await;
+        assert_eq!((quote.id.as_str(), quote.price), ("AAPL", 2.0));
+
+        rustler.disconnect().await.unwrap();
+        assert_eq!(rustler.status(), &RustlerStatus::Disconnected);
+    }
+}
@@ -16,4 +16,7 @@ pub struct WsErrorResponse {
     #[serde(rename = "errorCode")]
     pub error_code: u16,
     pub msg: String,
+    /// id of the failed request, for the events that have one
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Value>,
 }
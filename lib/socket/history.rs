@@ -0,0 +1,193 @@
+//! 🐎 » socket history events
+//!
+//! Wraps an [`EventDispatcher`] to answer the history requests of the socket clients, reading the
+//! stored quotes and candles through a [`HistoryReader`]:
+//!
+//! - `history:quotes` with `{ requestId, market, symbol, from, to, pageSize? }`
+//! - `history:candles` with the same data plus a `resolution` (`1m`, `5m`, `15m`, `1h` or `1d`)
+//!
+//! Every page is sent as `{ event, data: { requestId, page, items, done } }`, the last one with
+//! `done: true`. Failed requests are answered with a [`WsErrorResponse`] carrying the `requestId`.
+//! Requests still sending pages are abandoned when the connection closes.
+
+use {
+    super::{
+        event::{self, WsErrorResponse},
+        CancellationToken, EventDispatcher, Outgoing,
+    },
+    crate::{
+        candles::Resolution,
+        history::{HistoryRange, HistoryReader},
+    },
+    async_trait::async_trait,
+    eyre::Result,
+    futures::{SinkExt, Stream, StreamExt},
+    lool::logger::{error, info},
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    std::sync::Arc,
+    tokio::{select, sync::Mutex},
+};
+
+/// event of the quote history requests
+pub const QUOTES_EVENT: &str = "history:quotes";
+/// event of the candle history requests
+pub const CANDLES_EVENT: &str = "history:candles";
+
+/// data of a history request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryRequest {
+    /// id chosen by the client to match the responses with the request
+    #[serde(default)]
+    request_id: Value,
+    #[serde(flatten)]
+    range: HistoryRange,
+    resolution: Option<Resolution>,
+}
+
+/// #### 🐎 » History dispatcher
+///
+/// [`EventDispatcher`] answering the history events and delegating every other event to the
+/// wrapped dispatcher.
+///
+/// **Usage**
+///
+/// ```rust
+/// let reader = HistoryReader::new(conn).await;
+/// let dispatcher = HistoryDispatcher::new(reader, Dispatcher {});
+/// let mut ws_server = socket::Server::new("127.0.0.1", "9002", dispatcher).await?;
+/// ```
+#[derive(Clone)]
+pub struct HistoryDispatcher<ED: EventDispatcher + Clone + Send + Sync + 'static> {
+    reader: Arc<HistoryReader>,
+    inner: ED,
+}
+
+impl<ED: EventDispatcher + Clone + Send + Sync + 'static> HistoryDispatcher<ED> {
+    /// 🐎 » wraps the given dispatcher
+    pub fn new(reader: Arc<HistoryReader>, inner: ED) -> Self {
+        Self { reader, inner }
+    }
+}
+
+#[async_trait]
+impl<ED: EventDispatcher + Clone + Send + Sync + 'static> EventDispatcher
+    for HistoryDispatcher<ED>
+{
+    async fn dispatch(
+        &self,
+        event: String,
+        data: event::Data,
+        outgoing: Arc<Mutex<Outgoing>>,
+        conn_id: String,
+        cancel_token: CancellationToken,
+    ) -> Result<()> {
+        if event != QUOTES_EVENT && event != CANDLES_EVENT {
+            return self.inner.dispatch(event, data, outgoing, conn_id, cancel_token).await;
+        }
+
+        let request_id = data.get("requestId").cloned().unwrap_or_default();
+        let request = match serde_json::from_value::<HistoryRequest>(Value::Object(data)) {
+            Ok(request) => request,
+            Err(e) => return send_error(&outgoing, &request_id, 400, e.to_string()).await,
+        };
+
+        info!("History request `{}` from connection {}", event, conn_id);
+
+        let reader = self.reader.clone();
+        tokio::spawn(async move {
+            let HistoryRequest {
+                request_id,
+                range,
+                resolution,
+            } = request;
+
+            let result = match (event.as_str(), resolution) {
+                (QUOTES_EVENT, _) => {
+                    let pages = reader.quotes(range);
+                    send_pages(&outgoing, &event, &request_id, pages, &cancel_token).await
+                }
+                (_, Some(resolution)) => {
+                    let pages = reader.candles(range, resolution);
+                    send_pages(&outgoing, &event, &request_id, pages, &cancel_token).await
+                }
+                (_, None) => {
+                    let msg = "Missing candle resolution".into();
+                    send_error(&outgoing, &request_id, 400, msg).await
+                }
+            };
+
+            if let Err(e) = result {
+                error!("Error answering history request: {:?}", e);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// sends every page of the stream, stopping at the first error or when the token is cancelled
+async fn send_pages<T, S>(
+    outgoing: &Mutex<Outgoing>,
+    event: &str,
+    request_id: &Value,
+    pages: S,
+    cancel_token: &CancellationToken,
+) -> Result<()>
+where
+    T: Serialize,
+    S: Stream<Item = Result<Vec<T>>>,
+{
+    let mut pages = Box::pin(pages);
+    let mut page = 0;
+
+    loop {
+        let next = select! {
+            _ = cancel_token.cancelled() => return Ok(()),
+            next = pages.next() => next,
+        };
+
+        let items = match next {
+            Some(Ok(items)) => items,
+            Some(Err(e)) => {
+                error!("Error reading history: {:?}", e);
+                let msg = "Error reading history".into();
+                return send_error(outgoing, request_id, 500, msg).await;
+            }
+            None => vec![],
+        };
+
+        let done = items.is_empty();
+        let response = json!({
+            "event": event,
+            "data": { "requestId": request_id, "page": page, "items": items, "done": done },
+        });
+
+        outgoing.lock().await.send(response.to_string().into()).await?;
+
+        if done {
+            return Ok(());
+        }
+
+        page += 1;
+    }
+}
+
+/// sends the error response of a request
+async fn send_error(
+    outgoing: &Mutex<Outgoing>,
+    request_id: &Value,
+    error_code: u16,
+    msg: String,
+) -> Result<()> {
+    let request_id = Some(request_id.clone()).filter(|id| !id.is_null());
+    let response = serde_json::to_string(&WsErrorResponse {
+        error_code,
+        msg,
+        request_id,
+    })?;
+    outgoing.lock().await.send(response.into()).await?;
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 pub mod event;
+pub mod history;
 mod server;
 pub mod stats;
 
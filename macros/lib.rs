@@ -0,0 +1,282 @@
+//! 🐎 » procedural macros for `rustler-core`
+//!
+//! these macros are re-exported by `rustler-core`, use them from there (e.g.
+//! `rustler_core::rustlers::rustler`) instead of depending on this crate directly.
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::{Span, TokenStream as TokenStream2},
+    quote::{quote, quote_spanned},
+    syn::{
+        meta::ParseNestedMeta, parse_macro_input, parse_quote, spanned::Spanned, Error, Expr,
+        Fields, Ident, ItemStruct, LitStr, Result,
+    },
+};
+
+/// names of the fields injected by the `#[rustler]` attribute
+const STATE_FIELDS: [&str; 9] = [
+    "status",
+    "next_run",
+    "next_stop",
+    "last_run",
+    "last_stop",
+    "last_update",
+    "opts",
+    "tickers",
+    "msg_sender",
+];
+
+/// #### 🐎 » rustler attribute macro
+///
+/// Expands a struct with the state fields required by a `Rustler` and implements the
+/// `RustlerAccessor` trait for it.
+///
+/// **Arguments** (all optional)
+/// - `name = ...` - the name returned by `RustlerAccessor::name` (defaults to the struct name); it
+///   can be a string literal or an expression evaluated with access to `self` (e.g.
+///   `name = self.handler.name()`)
+/// - `default` - derives `Default` for the struct (all user fields must implement `Default`)
+/// - `constructor = "..."` - the name of the generated constructor (defaults to `new`)
+/// - `no_constructor` - skips the generation of the constructor
+///
+/// The generated constructor takes the user defined fields as arguments (in declaration order) and
+/// initializes the injected state fields with their defaults, so fields that don't implement
+/// `Default` are supported.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// use rustler_core::rustlers::rustler;
+///
+/// #[rustler(name = "Binance")]
+/// pub struct BinanceRustler<C: Client> {
+///     client: C,
+/// }
+///
+/// let rustler = BinanceRustler::new(client);
+/// ```
+#[proc_macro_attribute]
+pub fn rustler(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut attrs = RustlerArgs::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    parse_macro_input!(args with parser);
+
+    let item = parse_macro_input!(input as ItemStruct);
+
+    expand(attrs, item).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// arguments of the `#[rustler]` attribute
+#[derive(Default)]
+struct RustlerArgs {
+    name: Option<Expr>,
+    default: bool,
+    constructor: Option<Ident>,
+    no_constructor: bool,
+}
+
+impl RustlerArgs {
+    fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("default") {
+            self.default = true;
+        } else if meta.path.is_ident("constructor") {
+            let name: LitStr = meta.value()?.parse()?;
+            self.constructor = Some(name.parse()?);
+        } else if meta.path.is_ident("no_constructor") {
+            self.no_constructor = true;
+        } else {
+            return Err(meta.error(
+                "unsupported `rustler` argument, expected one of: `name`, `default`, \
+                 `constructor`, `no_constructor`",
+            ));
+        }
+
+        if self.no_constructor && self.constructor.is_some() {
+            return Err(meta.error("`constructor` and `no_constructor` can't be used together"));
+        }
+
+        Ok(())
+    }
+}
+
+fn expand(args: RustlerArgs, mut item: ItemStruct) -> Result<TokenStream2> {
+    let user_fields = match &item.fields {
+        Fields::Named(fields) => fields.named.iter().cloned().collect::<Vec<_>>(),
+        Fields::Unit => vec![],
+        Fields::Unnamed(fields) => {
+            return Err(Error::new(
+                fields.span(),
+                "`#[rustler]` can only be used on structs with named fields",
+            ))
+        }
+    };
+
+    for field in &user_fields {
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+        if STATE_FIELDS.contains(&ident.to_string().as_str()) {
+            return Err(Error::new(
+                ident.span(),
+                format!(
+                    "field `{}` is reserved by `#[rustler]`, please rename it",
+                    ident
+                ),
+            ));
+        }
+    }
+
+    // every path is fully qualified, so the expansion doesn't depend on the caller's imports
+    let krate = quote!(::rustler_core::rustlers);
+    let option = quote!(::core::option::Option);
+    let string = quote!(::std::string::String);
+    let dt = quote!(#krate::chrono::DateTime<#krate::chrono::Local>);
+    let sender = quote!(#krate::svc::MsgSender);
+    let tickers = quote!(::std::collections::HashMap<#string, #krate::Ticker>);
+
+    let state_fields: syn::FieldsNamed = parse_quote!({
+        status: #krate::RustlerStatus,
+        next_run: #dt,
+        next_stop: #option<#dt>,
+        last_run: #option<#dt>,
+        last_stop: #option<#dt>,
+        last_update: #option<#dt>,
+        opts: #krate::RustlerOpts,
+        tickers: #tickers,
+        msg_sender: #option<#sender>,
+    });
+
+    let mut named = state_fields.named;
+    named.extend(user_fields.iter().cloned());
+    item.fields = Fields::Named(syn::FieldsNamed {
+        brace_token: Default::default(),
+        named,
+    });
+    item.semi_token = None;
+
+    if args.default {
+        item.attrs.push(parse_quote!(#[derive(Default)]));
+    }
+
+    let ident = &item.ident;
+    let vis = &item.vis;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    let name = args.name.unwrap_or_else(|| {
+        let name = LitStr::new(&ident.to_string(), ident.span());
+        parse_quote!(#name)
+    });
+
+    let constructor = match args.no_constructor {
+        true => quote!(),
+        false => {
+            let ctor = args.constructor.unwrap_or_else(|| Ident::new("new", Span::call_site()));
+            let params = user_fields.iter().map(|f| {
+                let (ident, ty) = (&f.ident, &f.ty);
+                quote_spanned!(f.span() => #ident: #ty)
+            });
+            let inits = user_fields.iter().map(|f| &f.ident);
+
+            quote! {
+                impl #impl_generics #ident #ty_generics #where_clause {
+                    /// 🐎 » creates a new instance of the rustler
+                    #[allow(clippy::too_many_arguments, clippy::new_without_default)]
+                    #vis fn #ctor(#(#params),*) -> Self {
+                        Self {
+                            status: ::core::default::Default::default(),
+                            next_run: ::core::default::Default::default(),
+                            next_stop: #option::None,
+                            last_run: #option::None,
+                            last_stop: #option::None,
+                            last_update: #option::None,
+                            opts: ::core::default::Default::default(),
+                            tickers: ::core::default::Default::default(),
+                            msg_sender: #option::None,
+                            #(#inits),*
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #item
+
+        #constructor
+
+        impl #impl_generics #krate::RustlerAccessor for #ident #ty_generics #where_clause {
+            fn name(&self) -> #string {
+                #string::from(#name)
+            }
+            fn status(&self) -> &#krate::RustlerStatus {
+                &self.status
+            }
+            fn set_status(&mut self, status: #krate::RustlerStatus) -> #krate::eyre::Result<()> {
+                self.status = status;
+                #krate::Rustler::handle_status_change(self)?;
+
+                #krate::lool::logger::info!(
+                    "Rustler {} status changed to {:?}",
+                    #krate::RustlerAccessor::name(self),
+                    self.status
+                );
+
+                ::core::result::Result::Ok(())
+            }
+            fn next_run(&self) -> &#dt {
+                &self.next_run
+            }
+            fn set_next_run(&mut self, next_run: #dt) {
+                self.next_run = next_run;
+            }
+            fn next_stop(&self) -> &#option<#dt> {
+                &self.next_stop
+            }
+            fn set_next_stop(&mut self, next_stop: #option<#dt>) {
+                self.next_stop = next_stop;
+            }
+            fn last_run(&self) -> &#option<#dt> {
+                &self.last_run
+            }
+            fn set_last_run(&mut self, last_run: #option<#dt>) {
+                self.last_run = last_run;
+            }
+            fn last_stop(&self) -> &#option<#dt> {
+                &self.last_stop
+            }
+            fn set_last_stop(&mut self, last_stop: #option<#dt>) {
+                self.last_stop = last_stop;
+            }
+            fn last_update(&self) -> &#option<#dt> {
+                &self.last_update
+            }
+            fn set_last_update(&mut self, last_update: #option<#dt>) {
+                self.last_update = last_update;
+            }
+            fn opts(&self) -> &#krate::RustlerOpts {
+                &self.opts
+            }
+            fn set_opts(&mut self, opts: #krate::RustlerOpts) {
+                self.opts = opts;
+            }
+            fn tickers(&self) -> &#tickers {
+                &self.tickers
+            }
+            fn tickers_mut(&mut self) -> &mut #tickers {
+                &mut self.tickers
+            }
+            fn set_tickers(&mut self, tickers: #tickers) {
+                self.tickers = tickers;
+            }
+            fn msg_sender(&self) -> &#option<#sender> {
+                &self.msg_sender
+            }
+            fn msg_sender_mut(&mut self) -> &mut #option<#sender> {
+                &mut self.msg_sender
+            }
+            fn set_msg_sender(&mut self, sender: #option<#sender>) {
+                self.msg_sender = sender;
+            }
+        }
+    })
+}
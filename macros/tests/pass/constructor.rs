@@ -0,0 +1,49 @@
+use {
+    async_trait::async_trait,
+    rustler_core::rustlers::{eyre::Result, rustler, Rustler, RustlerAccessor, Ticker},
+};
+
+/// doesn't implement `Default`
+pub struct Endpoint {
+    url: String,
+}
+
+#[rustler(constructor = "create")]
+pub struct ApiRustler {
+    endpoint: Endpoint,
+    retries: u32,
+}
+
+#[async_trait]
+impl Rustler for ApiRustler {
+    async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let endpoint = Endpoint {
+        url: "wss://example.com".into(),
+    };
+    let rustler = ApiRustler::create(endpoint, 3);
+
+    assert_eq!(rustler.endpoint.url, "wss://example.com");
+    assert_eq!(rustler.retries, 3);
+
+    // the state fields take their defaults
+    assert!(rustler.is_disconnected());
+    assert!(rustler.tickers().is_empty());
+    assert!(rustler.msg_sender().is_none());
+}
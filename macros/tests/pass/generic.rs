@@ -0,0 +1,58 @@
+use {
+    async_trait::async_trait,
+    rustler_core::rustlers::{eyre::Result, rustler, Rustler, RustlerAccessor, Ticker},
+};
+
+pub trait Client: Send + Sync + 'static {
+    fn url(&self) -> String;
+}
+
+#[derive(Clone)]
+pub struct HttpClient;
+
+impl Client for HttpClient {
+    fn url(&self) -> String {
+        "https://example.com".into()
+    }
+}
+
+#[rustler]
+pub struct GenericRustler<C: Client, const N: usize>
+where
+    C: Clone,
+{
+    client: C,
+    symbols: [&'static str; N],
+}
+
+#[async_trait]
+impl<C, const N: usize> Rustler for GenericRustler<C, N>
+where
+    C: Client + Clone,
+{
+    async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let rustler = GenericRustler::new(HttpClient, ["AAPL", "MSFT"]);
+
+    assert_eq!(rustler.name(), "GenericRustler");
+    assert_eq!(rustler.client.url(), "https://example.com");
+    assert_eq!(rustler.symbols.len(), 2);
+
+    let _: Box<dyn Rustler> = Box::new(rustler);
+}
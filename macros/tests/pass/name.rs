@@ -0,0 +1,56 @@
+use {
+    async_trait::async_trait,
+    rustler_core::rustlers::{eyre::Result, rustler, Rustler, RustlerAccessor, Ticker},
+};
+
+#[rustler(name = "Binance")]
+pub struct LiteralRustler {}
+
+#[rustler(name = format!("{}-{}", self.provider, self.region))]
+pub struct ExprRustler {
+    provider: &'static str,
+    region: &'static str,
+}
+
+#[async_trait]
+impl Rustler for LiteralRustler {
+    async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Rustler for ExprRustler {
+    async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    assert_eq!(LiteralRustler::new().name(), "Binance");
+    assert_eq!(ExprRustler::new("yahoo", "us").name(), "yahoo-us");
+}
@@ -0,0 +1,53 @@
+use {
+    async_trait::async_trait,
+    rustler_core::rustlers::{eyre::Result, rustler, Rustler, RustlerAccessor, Ticker},
+};
+
+#[rustler(no_constructor)]
+pub struct ManualRustler {
+    interval: u64,
+}
+
+impl ManualRustler {
+    /// a `new` constructor of its own doesn't clash with the generated one
+    pub fn new() -> Self {
+        Self {
+            status: Default::default(),
+            next_run: Default::default(),
+            next_stop: None,
+            last_run: None,
+            last_stop: None,
+            last_update: None,
+            opts: Default::default(),
+            tickers: Default::default(),
+            msg_sender: None,
+            interval: 1000,
+        }
+    }
+}
+
+#[async_trait]
+impl Rustler for ManualRustler {
+    async fn on_add(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn on_delete(&mut self, _tickers: &[Ticker]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let rustler = ManualRustler::new();
+
+    assert_eq!(rustler.interval, 1000);
+    assert_eq!(rustler.name(), "ManualRustler");
+}
@@ -0,0 +1,6 @@
+use rustler_core_macros::rustler;
+
+#[rustler(no_constructor, constructor = "create")]
+struct Rustler {}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rustler_core_macros::rustler;
+
+#[rustler]
+struct Rustler {
+    status: u32,
+}
+
+fn main() {}
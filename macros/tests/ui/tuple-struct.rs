@@ -0,0 +1,6 @@
+use rustler_core_macros::rustler;
+
+#[rustler]
+struct Rustler(u32);
+
+fn main() {}
@@ -0,0 +1,6 @@
+use rustler_core_macros::rustler;
+
+#[rustler(interval = 5)]
+struct Rustler {}
+
+fn main() {}
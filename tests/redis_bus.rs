@@ -0,0 +1,136 @@
+//! integration tests of the redis bus
+//!
+//! they need a redis server, so they're ignored by default:
+//!
+//! ```sh
+//! REDIS_URL=redis://127.0.0.1/ cargo test --test redis_bus -- --ignored
+//! ```
+//!
+//! every test uses its own key prefix, so they don't see each other's values
+
+use rustler_core::{
+    bus::{redis, PrefixedPubSub, PublisherTrait, SnapshotTrait},
+    rustlers::{stats::SessionStats, MarketHourType, Quote},
+};
+
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".into())
+}
+
+/// returns a key prefix no other test uses
+fn prefix() -> String {
+    format!("rustler-test-{}", uuid::Uuid::new_v4())
+}
+
+fn quote(market: &str, symbol: &str, price: f64) -> Quote {
+    Quote {
+        id: symbol.into(),
+        market: market.into(),
+        price,
+        change_percent: 1.5,
+        time: 1_000,
+        market_hours: MarketHourType::Regular,
+        source: Some("rustler".into()),
+        volume: None,
+        stats: None,
+    }
+}
+
+fn prices(quotes: &[Quote]) -> Vec<(&str, f64)> {
+    let mut prices: Vec<_> = quotes.iter().map(|q| (q.id.as_str(), q.price)).collect();
+    prices.sort_by(|a, b| a.0.cmp(b.0));
+    prices
+}
+
+async fn publisher(prefix: &str) -> redis::publish::RedisPublisher<Quote> {
+    let mut publisher = redis::publisher::<Quote, _>(&redis_url()).await.unwrap();
+    publisher.set_prefix(prefix);
+    publisher
+}
+
+async fn snapshot(prefix: &str) -> redis::snapshot::RedisSnapshot<Quote> {
+    let mut snapshot = redis::snapshot::<Quote, _>(&redis_url()).await.unwrap();
+    snapshot.set_prefix(prefix);
+    snapshot
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn snapshots_get_the_last_value_of_a_key() {
+    let prefix = prefix();
+    let mut publisher = publisher(&prefix).await;
+    let mut snapshot = snapshot(&prefix).await;
+
+    assert!(snapshot.get("quote:NASDAQ:AAPL").await.unwrap().is_none());
+
+    let mut last = quote("NASDAQ", "AAPL", 101.0);
+    last.stats = Some(SessionStats::from(&last));
+    publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await.unwrap();
+    publisher.publish(last.clone()).await.unwrap();
+
+    let stored = snapshot.get("quote:NASDAQ:AAPL").await.unwrap().unwrap();
+    assert_eq!(
+        (stored.id.as_str(), stored.market.as_str()),
+        ("AAPL", "NASDAQ")
+    );
+    assert_eq!(
+        (stored.price, stored.change_percent, stored.time),
+        (101.0, 1.5, 1_000)
+    );
+    assert_eq!(stored.source.as_deref(), Some("rustler"));
+    assert_eq!(stored.stats, last.stats);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn snapshots_get_the_values_matching_a_pattern() {
+    let prefix = prefix();
+    let mut publisher = publisher(&prefix).await;
+
+    // more keys than the batch, so they're read in several round trips
+    for i in 0..5 {
+        publisher.publish(quote("NASDAQ", &format!("S{}", i), i as f64)).await.unwrap();
+    }
+    publisher.publish(quote("NYSE", "IBM", 200.0)).await.unwrap();
+
+    let mut snapshot = snapshot(&prefix).await;
+    snapshot.with_batch(2);
+
+    let nasdaq = snapshot.get_many("quote:NASDAQ:*").await.unwrap();
+    assert_eq!(
+        prices(&nasdaq),
+        [("S0", 0.0), ("S1", 1.0), ("S2", 2.0), ("S3", 3.0), ("S4", 4.0)]
+    );
+    assert_eq!(snapshot.get_many("quote:*").await.unwrap().len(), 6);
+    assert!(snapshot.get_many("quote:BCBA:*").await.unwrap().is_empty());
+
+    // other prefixes don't see them
+    let mut other = self::snapshot(&self::prefix()).await;
+    assert!(other.get_many("quote:*").await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn publishers_reload_the_script_when_redis_loses_it() {
+    let prefix = prefix();
+    let mut publisher = publisher(&prefix).await;
+    let mut snapshot = snapshot(&prefix).await;
+
+    publisher.publish(quote("NASDAQ", "AAPL", 100.0)).await.unwrap();
+
+    // like a redis restart, as far as the scripts are concerned
+    let client = ::redis::Client::open(redis_url()).unwrap();
+    let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+    let _: () = ::redis::cmd("SCRIPT").arg("FLUSH").query_async(&mut conn).await.unwrap();
+
+    publisher
+        .publish_batch(vec![
+            quote("NASDAQ", "AAPL", 101.0),
+            quote("NYSE", "IBM", 200.0),
+        ])
+        .await
+        .unwrap();
+
+    let stored = snapshot.get_many("quote:*").await.unwrap();
+    assert_eq!(prices(&stored), [("AAPL", 101.0), ("IBM", 200.0)]);
+}
@@ -0,0 +1,195 @@
+//! integration tests of the redis streams bus
+//!
+//! they need a redis server, so they're ignored by default:
+//!
+//! ```sh
+//! REDIS_URL=redis://127.0.0.1/ cargo test --test redis_streams -- --ignored
+//! ```
+//!
+//! every test uses its own key prefix, so they don't see each other's messages
+
+use {
+    futures::{Stream, StreamExt},
+    rustler_core::{
+        bus::{
+            redis_streams::{self, subscribe::RedisStreamSubscriber},
+            PrefixedPubSub, PublisherTrait, StreamEntry, StreamPosition, StreamSubscriberTrait,
+        },
+        rustlers::{MarketHourType, Quote},
+    },
+    std::{collections::HashSet, time::Duration},
+    tokio::time::timeout,
+};
+
+/// max time a test waits for the entries it expects
+const WAIT: Duration = Duration::from_secs(10);
+
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".into())
+}
+
+/// returns a key prefix no other test uses
+fn prefix() -> String {
+    format!("rustler-test-{}", uuid::Uuid::new_v4())
+}
+
+fn quote(market: &str, symbol: &str, price: f64) -> Quote {
+    Quote {
+        id: symbol.into(),
+        market: market.into(),
+        price,
+        change_percent: 0.0,
+        time: 0,
+        market_hours: MarketHourType::Regular,
+        source: None,
+        volume: None,
+        stats: None,
+    }
+}
+
+/// publishes `count` quotes of NASDAQ and NYSE tickers (alternating), priced 0, 1, 2...
+async fn publish(prefix: &str, count: usize) {
+    let mut publisher = redis_streams::publisher::<Quote, _>(&redis_url()).await.unwrap();
+    publisher.set_prefix(prefix);
+
+    for i in 0..count {
+        let market = if i % 2 == 0 { "NASDAQ" } else { "NYSE" };
+        publisher.publish(quote(market, &format!("S{}", i), i as f64)).await.unwrap();
+    }
+}
+
+/// creates a subscriber of the quotes of a consumer group that starts at the beginning
+async fn subscriber(prefix: &str, group: &str, consumer: &str) -> RedisStreamSubscriber<Quote> {
+    let mut subscriber =
+        redis_streams::subscriber::<Quote, _>(&redis_url(), "quote").await.unwrap();
+
+    subscriber.set_prefix(prefix);
+    subscriber
+        .with_group(group, consumer)
+        .with_group_start(StreamPosition::Beginning)
+        .with_batch(5, Duration::from_millis(100));
+
+    subscriber
+}
+
+/// takes `count` entries from the stream, failing if they don't arrive in time
+async fn take<S>(stream: &mut S, count: usize) -> Vec<StreamEntry<Quote>>
+where
+    S: Stream<Item = StreamEntry<Quote>> + Unpin,
+{
+    let entries = stream.take(count).collect::<Vec<_>>();
+    let entries = timeout(WAIT, entries).await.expect("timed out waiting for entries");
+    assert_eq!(entries.len(), count);
+
+    entries
+}
+
+fn ids(entries: &[StreamEntry<Quote>]) -> Vec<String> {
+    entries.iter().map(|e| e.id.clone()).collect()
+}
+
+fn prices(entries: &[StreamEntry<Quote>]) -> Vec<f64> {
+    entries.iter().map(|e| e.message.price).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn consumers_of_a_group_share_the_entries() {
+    let prefix = prefix();
+    let mut first = subscriber(&prefix, "shared", "first").await;
+    let mut second = subscriber(&prefix, "shared", "second").await;
+    let mut other = subscriber(&prefix, "other", "other").await;
+
+    let mut shared = futures::stream::select(
+        first.entries().await.unwrap(),
+        second.entries().await.unwrap(),
+    );
+    let mut all = other.entries().await.unwrap();
+
+    publish(&prefix, 20).await;
+
+    // every entry goes to a single consumer of each group
+    let entries = take(&mut shared, 20).await;
+    let unique: HashSet<String> = ids(&entries).into_iter().collect();
+    assert_eq!(unique.len(), 20);
+
+    let entries = take(&mut all, 20).await;
+    assert_eq!(prices(&entries), (0..20).map(f64::from).collect::<Vec<_>>());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn unacknowledged_entries_are_delivered_again_after_a_restart() {
+    let prefix = prefix();
+
+    let received = {
+        let mut subscriber = subscriber(&prefix, "restart", "consumer").await;
+        let mut entries = subscriber.entries().await.unwrap();
+        publish(&prefix, 5).await;
+
+        let mut received = take(&mut entries, 5).await;
+        subscriber.ack(&ids(&received[..2])).await.unwrap();
+        received.split_off(2)
+    };
+
+    // the same consumer gets the entries it didn't acknowledge
+    let mut subscriber = subscriber(&prefix, "restart", "consumer").await;
+    let mut entries = subscriber.entries().await.unwrap();
+    let redelivered = take(&mut entries, 3).await;
+
+    assert_eq!(ids(&redelivered), ids(&received));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn pending_entries_of_gone_consumers_are_claimed() {
+    let prefix = prefix();
+
+    let received = {
+        let mut subscriber = subscriber(&prefix, "claim", "gone").await;
+        let mut entries = subscriber.entries().await.unwrap();
+        publish(&prefix, 3).await;
+        take(&mut entries, 3).await
+    };
+
+    let mut subscriber = subscriber(&prefix, "claim", "claimer").await;
+    subscriber.with_pending(Duration::from_millis(100), Duration::from_millis(300));
+    let mut entries = subscriber.entries().await.unwrap();
+    let claimed = take(&mut entries, 3).await;
+
+    assert_eq!(ids(&claimed), ids(&received));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a redis server (REDIS_URL)"]
+async fn stored_entries_are_replayed() {
+    let prefix = prefix();
+    publish(&prefix, 10).await;
+
+    let mut subscriber = subscriber(&prefix, "replay", "replay").await;
+    let all: Vec<_> = subscriber.replay(StreamPosition::Beginning).await.unwrap().collect().await;
+    assert_eq!(prices(&all), (0..10).map(f64::from).collect::<Vec<_>>());
+
+    // ids are inclusive
+    let from_id = StreamPosition::Id(all[4].id.clone());
+    let replayed: Vec<_> = subscriber.replay(from_id).await.unwrap().collect().await;
+    assert_eq!(ids(&replayed), ids(&all[4..]));
+
+    // every entry added from the given time on, including the ones added in the same millisecond
+    let time = |entry: &StreamEntry<Quote>| -> i64 {
+        entry.id.split('-').next().unwrap().parse().unwrap()
+    };
+    let from_time = StreamPosition::Time(time(&all[4]));
+    let replayed: Vec<_> = subscriber.replay(from_time).await.unwrap().collect().await;
+    let expected: Vec<_> = all.iter().filter(|e| time(e) >= time(&all[4])).cloned().collect();
+    assert_eq!(ids(&replayed), ids(&expected));
+
+    let latest: Vec<_> = subscriber.replay(StreamPosition::Latest).await.unwrap().collect().await;
+    assert!(latest.is_empty());
+
+    // only the entries matching the pattern are replayed
+    subscriber.with_pattern("quote:NASDAQ:*");
+    let nasdaq: Vec<_> =
+        subscriber.replay(StreamPosition::Beginning).await.unwrap().collect().await;
+    assert_eq!(prices(&nasdaq), [0.0, 2.0, 4.0, 6.0, 8.0]);
+}